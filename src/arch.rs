@@ -0,0 +1,97 @@
+use crate::register::{Reg, RegDescriptor};
+
+// Only the host backend is reachable outside of the tests
+#[cfg_attr(not(target_arch = "aarch64"), allow(dead_code))]
+pub mod aarch64;
+#[cfg_attr(not(target_arch = "x86_64"), allow(dead_code))]
+pub mod x86_64;
+
+#[cfg_attr(not(target_arch = "aarch64"), allow(unused_imports))]
+pub use aarch64::AArch64;
+#[cfg_attr(not(target_arch = "x86_64"), allow(unused_imports))]
+pub use x86_64::X86_64;
+
+/// An instruction copied to another address, executed there while the
+/// original stays covered by a trap (displaced stepping)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelocatedInstruction {
+    /// The instruction encoded for its new address, PC relative operands still reach the same targets
+    pub bytes: Vec<u8>,
+    /// Length of the original instruction
+    pub len: usize,
+    /// A call pushes the address following the copy, it must be replaced by the one following the original
+    pub is_call: bool,
+}
+
+/// Everything the debugger needs to know about the tracee's CPU: how its
+/// general purpose register set is laid out, how DWARF numbers map to it and
+/// which instruction is used as a software breakpoint.
+pub trait Arch: Sync {
+    fn name(&self) -> &'static str;
+
+    /// `e_machine` of the ELF files made for the architecture, core files included.
+    fn elf_machine(&self) -> u16;
+
+    /// Registers of the `NT_PRSTATUS` register set, in the order they are dumped.
+    fn registers(&self) -> &'static [RegDescriptor];
+
+    /// Number of 64 bits words in the `NT_PRSTATUS` register set.
+    fn regset_len(&self) -> usize;
+
+    fn pc_reg(&self) -> Reg;
+
+    fn sp_reg(&self) -> Reg;
+
+    fn fp_reg(&self) -> Reg;
+
+    /// The trap instruction written at a breakpoint address, in memory order.
+    fn breakpoint_instruction(&self) -> &'static [u8];
+
+    /// How far past the breakpoint address the program counter is when the trap is reported.
+    fn trap_pc_adjustment(&self) -> u64;
+
+    /// Register holding the number of the syscall being made, still set when it returns.
+    fn syscall_number_reg(&self) -> Reg;
+
+    /// Registers holding the arguments of a syscall, in order.
+    fn syscall_arg_regs(&self) -> &'static [Reg];
+
+    /// Register holding the value a syscall returns.
+    fn syscall_return_reg(&self) -> Reg;
+
+    /// Register set the number of a syscall is changed through at its entry, when writing
+    /// `syscall_number_reg` doesn't change it.
+    fn syscall_number_regset(&self) -> Option<u32> {
+        None
+    }
+
+    /// Register sets saved in core files besides `NT_PRSTATUS`: the type and name of their note.
+    fn core_regsets(&self) -> &'static [(u32, &'static str)];
+
+    /// Numbers and names of the syscalls, sorted by number.
+    fn syscalls(&self) -> &'static [(u64, &'static str)];
+
+    /// Re-encodes the instruction at the start of `code`, found at `from`, so it can run at `to`.
+    /// `None` when the architecture or the instruction can't be relocated.
+    fn relocate_instruction(
+        &self,
+        _code: &[u8],
+        _from: u64,
+        _to: u64,
+    ) -> Option<RelocatedInstruction> {
+        None
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn native() -> &'static dyn Arch {
+    &X86_64
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn native() -> &'static dyn Arch {
+    &AArch64
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+compile_error!("vdebugger only supports x86_64 and aarch64 hosts");
@@ -1,9 +1,8 @@
-use std::ffi::{c_long, c_void};
+use std::ffi::c_void;
 
-use nix::{sys::ptrace, unistd::Pid};
+use nix::unistd::Pid;
 
-#[derive(Clone)]
-pub struct RealPtraceOps;
+use crate::{arch::Arch, error::DebuggerError};
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
@@ -11,72 +10,128 @@ use mockall::{automock, predicate::*};
 #[cfg_attr(test, automock)]
 pub trait PtraceOps {
     fn read(&self, pid: Pid, addr: *mut c_void) -> Result<i64, nix::Error>;
-    fn write(&self, pid: Pid, addr: *mut c_void, data: i64);
-}
-
-impl PtraceOps for RealPtraceOps {
-    fn read(&self, pid: Pid, addr: *mut c_void) -> Result<i64, nix::Error> {
-        ptrace::read(pid, addr)
-    }
-
-    fn write(&self, pid: Pid, addr: *mut c_void, data: i64) {
-        ptrace::write(pid, addr, data as c_long).expect("Cannot write to this address");
-    }
+    fn write(&self, pid: Pid, addr: *mut c_void, data: i64) -> Result<(), nix::Error>;
 }
 
 #[derive(Clone)]
 pub struct Breakpoint<T: PtraceOps> {
-    pid: Pid,
     addr: *mut c_void,
     saved_data: i64,
     pub enabled: bool,
+    /// Waiting for a shared library defining its location, it has no address meanwhile
+    pub pending: bool,
+    arch: &'static dyn Arch,
     ptrace_ops: T,
 }
 
 impl<T: PtraceOps> Breakpoint<T> {
-    pub fn new(pid: Pid, addr: *mut c_void, ptrace_ops: T) -> Self {
+    pub fn new(addr: *mut c_void, arch: &'static dyn Arch, ptrace_ops: T) -> Self {
         Self {
-            pid,
             addr,
             saved_data: 0i64,
             enabled: false,
+            pending: false,
+            arch,
             ptrace_ops,
         }
     }
 
-    pub fn enable(&mut self) {
-        let Ok(old_line) = self.ptrace_ops.read(self.pid, self.addr) else {
-            std::process::exit(-1);
-        };
+    /// A breakpoint whose address is only known once its library is loaded
+    pub fn new_pending(arch: &'static dyn Arch, ptrace_ops: T) -> Self {
+        Self {
+            pending: true,
+            ..Self::new(std::ptr::null_mut(), arch, ptrace_ops)
+        }
+    }
 
-        self.saved_data = old_line & 0xff;
-        let int3: i64 = 0xcc; // the int 3 interruption signal instruction
-        let data_with_int3_added = (old_line & !0xff) | int3; // set the bottom byte of the address to int3 (0xcc)
+    /// The bytes of the instruction the trap replaced, in memory order
+    pub fn saved_bytes(&self) -> Vec<u8> {
+        self.saved_data.to_le_bytes()[..self.arch.breakpoint_instruction().len()].to_vec()
+    }
 
-        let _ = self
+    pub fn address(&self) -> u64 {
+        self.addr as u64
+    }
+
+    /// Gives an address to a pending breakpoint, it still has to be enabled
+    pub fn resolve(&mut self, addr: *mut c_void) {
+        self.addr = addr;
+        self.pending = false;
+    }
+
+    /// Makes the breakpoint pending again once the code holding the trap is unmapped
+    pub fn unresolve(&mut self) {
+        self.addr = std::ptr::null_mut();
+        self.enabled = false;
+        self.pending = true;
+    }
+
+    /// Mask covering the bytes of the word that the trap instruction overwrites
+    fn instruction_mask(&self) -> i64 {
+        match self.arch.breakpoint_instruction().len() {
+            8 => -1,
+            len => (1i64 << (len * 8)) - 1,
+        }
+    }
+
+    /// Inserts the trap in `pid`, the breakpoint itself outlives the process so it can be reinserted after a restart
+    pub fn enable(&mut self, pid: Pid) -> Result<(), DebuggerError> {
+        if self.pending {
+            return Ok(());
+        }
+        let old_line = self
             .ptrace_ops
-            .write(self.pid, self.addr, data_with_int3_added);
+            .read(pid, self.addr)
+            .map_err(|errno| self.memory_error(errno))?;
+
+        let mask = self.instruction_mask();
+        self.saved_data = old_line & mask;
+
+        // the trap instruction (int3 on x86_64, brk #0 on aarch64) in little endian order
+        let trap = self
+            .arch
+            .breakpoint_instruction()
+            .iter()
+            .rev()
+            .fold(0i64, |acc, &byte| (acc << 8) | byte as i64);
+        let data_with_trap_added = (old_line & !mask) | trap; // replace the bottom bytes of the address with the trap
+
+        self.ptrace_ops
+            .write(pid, self.addr, data_with_trap_added)
+            .map_err(|errno| self.memory_error(errno))?;
 
         self.enabled = true;
+        Ok(())
     }
 
-    pub fn disable(&mut self) {
-        let Ok(line) = self.ptrace_ops.read(self.pid, self.addr) else {
-            eprintln!("Couldn't remove breakpoint at an adress that doesn't contain any data");
-            std::process::exit(-1);
-        };
+    pub fn disable(&mut self, pid: Pid) -> Result<(), DebuggerError> {
+        let line = self
+            .ptrace_ops
+            .read(pid, self.addr)
+            .map_err(|errno| self.memory_error(errno))?;
 
-        let restored_line = (line & !0xff) | self.saved_data as i64;
+        let restored_line = (line & !self.instruction_mask()) | self.saved_data;
 
-        let _ = self.ptrace_ops.write(self.pid, self.addr, restored_line);
+        self.ptrace_ops
+            .write(pid, self.addr, restored_line)
+            .map_err(|errno| self.memory_error(errno))?;
 
         self.enabled = false;
+        Ok(())
+    }
+
+    fn memory_error(&self, errno: nix::Error) -> DebuggerError {
+        DebuggerError::Memory {
+            address: self.address(),
+            errno,
+        }
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::arch::{AArch64, X86_64};
     use lazy_static::lazy_static;
     use std::sync::Mutex;
 
@@ -99,14 +154,13 @@ mod test {
             .times(1)
             .return_const(Ok(initial_data));
 
-
         mock_ops
             .expect_write()
             .withf(move |&p, &a, &d| {
                 p == pid && a as usize == *ADDR.lock().unwrap() && d == expected_data_after_enable
             })
             .times(1)
-            .return_const(());
+            .return_const(Ok(()));
 
         // Expectations for disable
         mock_ops
@@ -120,18 +174,108 @@ mod test {
                 p == pid && a as usize == *ADDR.lock().unwrap() && d == initial_data
             })
             .times(1)
-            .return_const(());
+            .return_const(Ok(()));
 
-        let mut breakpoint = Breakpoint::new(pid, addr, mock_ops);
+        let mut breakpoint = Breakpoint::new(addr, &X86_64, mock_ops);
 
         // Enable the breakpoint
-        breakpoint.enable();
+        breakpoint.enable(pid).unwrap();
 
         // Check that the breakpoint is enabled
         assert!(breakpoint.enabled);
         assert_eq!(breakpoint.saved_data, 0x88);
 
-        breakpoint.disable();
+        breakpoint.disable(pid).unwrap();
+        assert!(!breakpoint.enabled);
+    }
+
+    #[test]
+    fn test_aarch64_enable_disable_data_preservation() {
+        let pid = Pid::from_raw(1234); // Dummy PID
+        let addr = *ADDR.lock().unwrap() as *mut c_void;
+        let mut mock_ops = MockPtraceOps::new();
+        let initial_data = 0x1122334455667788i64;
+        let expected_data_after_enable = 0x11223344D4200000i64; // brk #0
+
+        mock_ops
+            .expect_read()
+            .times(1)
+            .return_const(Ok(initial_data));
+        mock_ops
+            .expect_write()
+            .withf(move |&p, _, &d| p == pid && d == expected_data_after_enable)
+            .times(1)
+            .return_const(Ok(()));
+
+        mock_ops
+            .expect_read()
+            .times(1)
+            .return_const(Ok(expected_data_after_enable));
+        mock_ops
+            .expect_write()
+            .withf(move |&p, _, &d| p == pid && d == initial_data)
+            .times(1)
+            .return_const(Ok(()));
+
+        let mut breakpoint = Breakpoint::new(addr, &AArch64, mock_ops);
+
+        breakpoint.enable(pid).unwrap();
+        assert!(breakpoint.enabled);
+        assert_eq!(breakpoint.saved_data, 0x55667788);
+
+        breakpoint.disable(pid).unwrap();
+        assert!(!breakpoint.enabled);
+    }
+
+    #[test]
+    fn test_pending_breakpoint() {
+        let pid = Pid::from_raw(1234);
+        let mut mock_ops = MockPtraceOps::new();
+        mock_ops
+            .expect_read()
+            .withf(|_, &a| a as usize == 0x2000)
+            .times(1)
+            .return_const(Ok(0x1122334455667788i64));
+        mock_ops
+            .expect_write()
+            .withf(|_, &a, &d| a as usize == 0x2000 && d == 0x11223344556677CCi64)
+            .times(1)
+            .return_const(Ok(()));
+
+        let mut breakpoint = Breakpoint::new_pending(&X86_64, mock_ops);
+        // nothing to write to while the breakpoint has no address
+        breakpoint.enable(pid).unwrap();
+        assert!(!breakpoint.enabled);
+
+        breakpoint.resolve(0x2000 as *mut c_void);
+        breakpoint.enable(pid).unwrap();
+        assert!(breakpoint.enabled && !breakpoint.pending);
+        assert_eq!(breakpoint.saved_bytes(), vec![0x88]);
+
+        // the library was unloaded, its memory can't be restored
+        breakpoint.unresolve();
+        assert!(breakpoint.pending && !breakpoint.enabled);
+        assert_eq!(breakpoint.address(), 0);
+    }
+
+    #[test]
+    fn test_enable_unmapped_address() {
+        let pid = Pid::from_raw(1234);
+        let mut mock_ops = MockPtraceOps::new();
+        mock_ops
+            .expect_read()
+            .times(1)
+            .return_const(Err(nix::Error::EIO));
+        mock_ops.expect_write().never();
+
+        let mut breakpoint = Breakpoint::new(0x3000 as *mut c_void, &X86_64, mock_ops);
+        assert_eq!(
+            breakpoint.enable(pid),
+            Err(DebuggerError::Memory {
+                address: 0x3000,
+                errno: nix::Error::EIO
+            })
+        );
         assert!(!breakpoint.enabled);
     }
 }
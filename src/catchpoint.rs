@@ -0,0 +1,248 @@
+use nix::{errno::Errno, sys::signal::Signal, unistd::Pid};
+
+use crate::arch::Arch;
+
+/// An event the debuggee stops on, set with the `catch` command
+#[derive(Debug, Clone, PartialEq)]
+pub enum CatchKind {
+    /// Entries to and exits from the syscalls of these numbers, any syscall when empty
+    Syscall(Vec<u64>),
+    Fork,
+    Vfork,
+    Exec,
+    /// Creation of a thread
+    Clone,
+    /// The process about to exit, its memory and registers can still be read
+    Exit,
+}
+
+impl CatchKind {
+    /// `syscall [NAME | NUMBER]...`, `fork`, `vfork`, `exec`, `clone` or `exit`
+    pub fn parse(arch: &dyn Arch, args: &[&str]) -> Result<Self, String> {
+        match args {
+            ["syscall", syscalls @ ..] => parse_syscalls(arch, syscalls).map(CatchKind::Syscall),
+            ["fork"] => Ok(CatchKind::Fork),
+            ["vfork"] => Ok(CatchKind::Vfork),
+            ["exec"] => Ok(CatchKind::Exec),
+            ["clone"] => Ok(CatchKind::Clone),
+            ["exit"] => Ok(CatchKind::Exit),
+            _ => Err(
+                "Usage: catch syscall [NAME | NUMBER]... | catch fork|vfork|exec|clone|exit"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// A change of the debuggee's processes or threads, reported by a ptrace event
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessEvent {
+    /// The child forked
+    Fork(Pid),
+    Vfork(Pid),
+    /// The program now run
+    Exec(String),
+    /// The thread created
+    Clone(Pid),
+    /// The wait status the process is exiting with
+    Exit(i32),
+}
+
+impl ProcessEvent {
+    /// `forked process 1234`, `exec'd /bin/ls`, `exiting with code 1`...
+    pub fn describe(&self) -> String {
+        match self {
+            ProcessEvent::Fork(child) => format!("forked process {child}"),
+            ProcessEvent::Vfork(child) => format!("vforked process {child}"),
+            ProcessEvent::Exec(program) => format!("exec'd {program}"),
+            ProcessEvent::Clone(tid) => format!("cloned thread LWP {tid}"),
+            ProcessEvent::Exit(status) if status & 0x7f == 0 => {
+                format!("exiting with code {}", (status >> 8) & 0xff)
+            }
+            ProcessEvent::Exit(status) => match Signal::try_from(status & 0x7f) {
+                Ok(signal) => format!("exiting on {signal}"),
+                Err(_) => format!("exiting with status 0x{status:x}"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catchpoint {
+    /// Number shown to the user
+    pub id: usize,
+    pub kind: CatchKind,
+}
+
+impl Catchpoint {
+    /// Whether the catchpoint stops on the syscall `number`
+    pub fn catches_syscall(&self, number: u64) -> bool {
+        match &self.kind {
+            CatchKind::Syscall(numbers) => numbers.is_empty() || numbers.contains(&number),
+            _ => false,
+        }
+    }
+
+    pub fn catches_event(&self, event: &ProcessEvent) -> bool {
+        matches!(
+            (&self.kind, event),
+            (CatchKind::Fork, ProcessEvent::Fork(_))
+                | (CatchKind::Vfork, ProcessEvent::Vfork(_))
+                | (CatchKind::Exec, ProcessEvent::Exec(_))
+                | (CatchKind::Clone, ProcessEvent::Clone(_))
+                | (CatchKind::Exit, ProcessEvent::Exit(_))
+        )
+    }
+
+    /// `syscall openat [257] mmap [9]`, `any syscall`, or the event caught like `fork`
+    pub fn describe(&self, arch: &dyn Arch) -> String {
+        match &self.kind {
+            CatchKind::Syscall(numbers) if numbers.is_empty() => "any syscall".to_string(),
+            CatchKind::Syscall(numbers) => {
+                let names: Vec<String> = numbers
+                    .iter()
+                    .map(|&number| format!("{} [{number}]", syscall_name(arch, number)))
+                    .collect();
+                format!("syscall {}", names.join(" "))
+            }
+            CatchKind::Fork => "fork".to_string(),
+            CatchKind::Vfork => "vfork".to_string(),
+            CatchKind::Exec => "exec".to_string(),
+            CatchKind::Clone => "clone".to_string(),
+            CatchKind::Exit => "exit".to_string(),
+        }
+    }
+}
+
+pub fn syscall_name(arch: &dyn Arch, number: u64) -> String {
+    arch.syscalls()
+        .binary_search_by_key(&number, |&(n, _)| n)
+        .map(|index| arch.syscalls()[index].1.to_string())
+        .unwrap_or_else(|_| format!("syscall {number}"))
+}
+
+/// The numbers of the syscalls given by name or number after `catch syscall`
+pub fn parse_syscalls(arch: &dyn Arch, args: &[&str]) -> Result<Vec<u64>, String> {
+    args.iter()
+        .map(|arg| {
+            if let Ok(number) = arg.parse::<u64>() {
+                return Ok(number);
+            }
+            arch.syscalls()
+                .iter()
+                .find(|(_, name)| name == arg)
+                .map(|&(number, _)| number)
+                .ok_or_else(|| format!("Unknown syscall name '{arg}'"))
+        })
+        .collect()
+}
+
+/// `openat(0xffffff9c, 0x402004, 0x0, 0x0, 0x0, 0x0)`
+pub fn describe_syscall_call(arch: &dyn Arch, number: u64, args: &[u64]) -> String {
+    let args: Vec<String> = args.iter().map(|arg| format!("0x{arg:x}")).collect();
+    format!("{}({})", syscall_name(arch, number), args.join(", "))
+}
+
+/// `openat = 3`, or `openat = -2 (ENOENT)` for a failure
+pub fn describe_syscall_return(arch: &dyn Arch, number: u64, value: u64) -> String {
+    let value = value as i64;
+    let name = syscall_name(arch, number);
+    // the kernel returns errors as -errno, in the last page of the address space
+    if (-4095..0).contains(&value) {
+        format!("{name} = {value} ({:?})", Errno::from_raw(-value as i32))
+    } else {
+        format!("{name} = {value}")
+    }
+}
+
+/// A value for a syscall to return: a number, or an errno name like `ENOSPC` or `-ENOSPC`
+/// standing for the negated errno the kernel returns
+pub fn parse_syscall_value(value: &str) -> Option<u64> {
+    if let Some(hex) = value.strip_prefix("0x") {
+        return u64::from_str_radix(hex, 16).ok();
+    }
+    if let Ok(number) = value.parse::<i64>() {
+        return Some(number as u64);
+    }
+    let name = value.strip_prefix('-').unwrap_or(value);
+    (1..4096)
+        .map(Errno::from_raw)
+        .find(|errno| format!("{errno:?}") == name)
+        .map(|errno| -(errno as i64) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::arch::{AArch64, X86_64};
+
+    #[test]
+    fn test_parse_syscalls() {
+        assert_eq!(parse_syscalls(&X86_64, &["openat", "9"]), Ok(vec![257, 9]));
+        assert_eq!(
+            parse_syscalls(&AArch64, &["openat", "mmap"]),
+            Ok(vec![56, 222])
+        );
+        assert!(parse_syscalls(&X86_64, &["opnat"]).is_err());
+
+        let catchpoint = Catchpoint {
+            id: 1,
+            kind: CatchKind::Syscall(vec![257, 9]),
+        };
+        assert!(catchpoint.catches_syscall(9));
+        assert!(!catchpoint.catches_syscall(0));
+        assert_eq!(
+            catchpoint.describe(&X86_64),
+            "syscall openat [257] mmap [9]"
+        );
+    }
+
+    #[test]
+    fn test_describe_syscall() {
+        assert_eq!(
+            describe_syscall_call(&X86_64, 3, &[4, 0, 0, 0, 0, 0]),
+            "close(0x4, 0x0, 0x0, 0x0, 0x0, 0x0)"
+        );
+        assert_eq!(describe_syscall_return(&X86_64, 257, 3), "openat = 3");
+        assert_eq!(
+            describe_syscall_return(&X86_64, 257, -2i64 as u64),
+            "openat = -2 (ENOENT)"
+        );
+        assert_eq!(syscall_name(&X86_64, 100_000), "syscall 100000");
+    }
+
+    #[test]
+    fn test_process_events() {
+        assert_eq!(CatchKind::parse(&X86_64, &["exec"]), Ok(CatchKind::Exec));
+        assert_eq!(
+            CatchKind::parse(&X86_64, &["syscall", "close"]),
+            Ok(CatchKind::Syscall(vec![3]))
+        );
+        assert!(CatchKind::parse(&X86_64, &["exec", "ls"]).is_err());
+
+        let catchpoint = Catchpoint {
+            id: 2,
+            kind: CatchKind::Fork,
+        };
+        assert!(catchpoint.catches_event(&ProcessEvent::Fork(Pid::from_raw(42))));
+        assert!(!catchpoint.catches_event(&ProcessEvent::Vfork(Pid::from_raw(42))));
+        assert!(!catchpoint.catches_syscall(57));
+        assert_eq!(catchpoint.describe(&X86_64), "fork");
+
+        assert_eq!(ProcessEvent::Exit(1 << 8).describe(), "exiting with code 1");
+        assert_eq!(ProcessEvent::Exit(11).describe(), "exiting on SIGSEGV");
+        assert_eq!(
+            ProcessEvent::Exec("/bin/ls".to_string()).describe(),
+            "exec'd /bin/ls"
+        );
+    }
+
+    #[test]
+    fn test_parse_syscall_value() {
+        assert_eq!(parse_syscall_value("ENOSPC"), Some(-28i64 as u64));
+        assert_eq!(parse_syscall_value("-EINTR"), Some(-4i64 as u64));
+        assert_eq!(parse_syscall_value("-1"), Some(u64::MAX));
+        assert_eq!(parse_syscall_value("0x10"), Some(16));
+        assert_eq!(parse_syscall_value("ENOPE"), None);
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    io::Write,
+    os::fd::{AsFd, AsRawFd, OwnedFd},
+    sync::{Arc, Mutex, OnceLock},
+    thread::{self, JoinHandle},
+};
+
+use linefeed::{DefaultTerminal, Interface};
+use nix::{
+    errno::Errno,
+    libc,
+    poll::{poll, PollFd, PollFlags},
+    unistd::{read, write},
+};
+
+/// Prefix of every line written by the debuggee on its pseudo-terminal
+static INFERIOR_PREFIX: &str = "[debuggee] ";
+
+/// How long the forwarder waits for the end of a line before printing what it has
+const PARTIAL_LINE_TIMEOUT_MS: u16 = 50;
+
+/// Where the debuggee's output is printed: above the prompt once the debugger
+/// reads commands, straight on stdout before that.
+#[derive(Default)]
+pub struct Console {
+    interface: OnceLock<Arc<Interface<DefaultTerminal>>>,
+    // keeps the lines of concurrent forwarders from interleaving
+    output: Mutex<()>,
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_interface(&self, interface: Arc<Interface<DefaultTerminal>>) {
+        let _ = self.interface.set(interface);
+    }
+
+    /// Prints text written by the debuggee, each line prefixed so it can't be
+    /// mistaken for the debugger's own output
+    pub fn print_inferior(&self, text: &str) {
+        let _guard = self.output.lock().unwrap();
+        let text: String = text
+            .lines()
+            .map(|line| format!("{INFERIOR_PREFIX}{line}\n"))
+            .collect();
+
+        if let Some(interface) = self.interface.get().filter(|_| terminal_has_width()) {
+            if let Ok(mut writer) = interface.lock_writer_erase() {
+                let _ = write!(writer, "{text}");
+                return;
+            }
+        }
+        let mut stdout = std::io::stdout().lock();
+        let _ = stdout.write_all(text.as_bytes());
+        let _ = stdout.flush();
+    }
+}
+
+/// linefeed can't redraw its prompt on a terminal reporting no columns
+pub fn terminal_has_width() -> bool {
+    let mut winsize: libc::winsize = unsafe { std::mem::zeroed() };
+    let res = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut winsize) };
+    res == 0 && winsize.ws_col > 0
+}
+
+/// Prints everything the debuggee writes on its pseudo-terminal until every
+/// process holding the other side of it is gone.
+pub fn forward_output(master: OwnedFd, console: Arc<Console>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut pending: Vec<u8> = vec![];
+        let mut buf = [0u8; 4096];
+
+        loop {
+            let mut fds = [PollFd::new(master.as_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, PARTIAL_LINE_TIMEOUT_MS) {
+                Ok(0) => {
+                    // no end of line is coming, the debuggee is probably waiting for input
+                    if !pending.is_empty() {
+                        console.print_inferior(&String::from_utf8_lossy(&pending));
+                        pending.clear();
+                    }
+                    continue;
+                }
+                Err(Errno::EINTR) => continue,
+                Err(_) => break,
+                Ok(_) => {}
+            }
+
+            match read(master.as_raw_fd(), &mut buf) {
+                Ok(0) | Err(Errno::EIO) => break,
+                Err(Errno::EINTR) => continue,
+                Err(_) => break,
+                Ok(n) => pending.extend_from_slice(&buf[..n]),
+            }
+
+            if let Some(last_newline) = pending.iter().rposition(|&b| b == b'\n') {
+                let lines: Vec<u8> = pending.drain(..=last_newline).collect();
+                console.print_inferior(&String::from_utf8_lossy(&lines).replace('\r', ""));
+            }
+        }
+
+        if !pending.is_empty() {
+            console.print_inferior(&String::from_utf8_lossy(&pending));
+        }
+    })
+}
+
+/// Waits up to `timeout_ms` for input on the debugger's stdin and sends it to
+/// the debuggee's pseudo-terminal.
+pub fn forward_input(master: &OwnedFd, timeout_ms: u16) {
+    let stdin = std::io::stdin();
+    let mut fds = [PollFd::new(stdin.as_fd(), PollFlags::POLLIN)];
+    let Ok(1..) = poll(&mut fds, timeout_ms) else {
+        return;
+    };
+
+    let mut buf = [0u8; 4096];
+    match read(libc::STDIN_FILENO, &mut buf) {
+        Ok(0) | Err(_) => {
+            // stdin is closed, don't spin on it
+            std::thread::sleep(std::time::Duration::from_millis(timeout_ms as u64));
+        }
+        Ok(n) => {
+            let mut data = &buf[..n];
+            while !data.is_empty() {
+                match write(master, data) {
+                    Ok(written) => data = &data[written..],
+                    Err(Errno::EINTR) => continue,
+                    Err(_) => return,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,869 @@
+use std::{
+    fs::{self, File},
+    io,
+    os::unix::fs::FileExt,
+    path::{Path, PathBuf},
+};
+
+use nix::{
+    libc,
+    sys::{
+        ptrace::Options,
+        signal::Signal,
+        wait::{WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+use object::{
+    elf,
+    read::elf::{FileHeader, ProgramHeader},
+    Endianness,
+};
+
+use crate::{
+    arch::Arch,
+    launch::{LaunchConfig, LaunchError, Launched},
+    procfs::{self, MemoryMap},
+    target::Target,
+};
+
+/// Offsets in `struct elf_prstatus`, the same on every 64 bits architecture
+const PRSTATUS_CURSIG: usize = 12;
+const PRSTATUS_PID: usize = 32;
+const PRSTATUS_REG: usize = 112;
+
+/// Offsets and lengths in `struct elf_prpsinfo`
+const PRPSINFO_LEN: usize = 136;
+const PRPSINFO_SNAME: usize = 1;
+const PRPSINFO_UID: usize = 16;
+const PRPSINFO_PID: usize = 24;
+const PRPSINFO_FNAME: usize = 40;
+const PRPSINFO_FNAME_LEN: usize = 16;
+const PRPSINFO_PSARGS: usize = 56;
+const PRPSINFO_PSARGS_LEN: usize = 80;
+
+/// A thread of the dumped process, from its `NT_PRSTATUS` note
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoreThread {
+    pub tid: Pid,
+    /// Signal the thread stopped with when the process was dumped, 0 for none
+    pub signal: i32,
+    /// General purpose registers, laid out like the `NT_PRSTATUS` register set
+    pub regs: Vec<u64>,
+}
+
+/// A file mapped in the dumped process, from the `NT_FILE` note
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileMapping {
+    pub start: u64,
+    pub end: u64,
+    /// Offset in the file, in bytes
+    pub offset: u64,
+    pub path: String,
+}
+
+/// A `PT_LOAD` segment of the core, memory past `filesz` wasn't dumped
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CoreSegment {
+    vaddr: u64,
+    memsz: u64,
+    offset: u64,
+    filesz: u64,
+    flags: u32,
+}
+
+impl CoreSegment {
+    fn contains(&self, address: u64) -> bool {
+        self.vaddr <= address && address - self.vaddr < self.memsz
+    }
+}
+
+/// The state of a process dumped in an ELF core file. Memory the kernel didn't dump, such as
+/// code, is read from the files that were mapped there.
+#[derive(Debug, Default)]
+pub struct CoreFile {
+    pub path: PathBuf,
+    /// From `NT_PRPSINFO`, 0 when the core has none
+    pid: i32,
+    /// Name of the program, as in `/proc/<pid>/comm`
+    pub name: String,
+    /// Start of the command line the program was run with
+    pub command: String,
+    /// The thread that got the fatal signal comes first
+    pub threads: Vec<CoreThread>,
+    pub auxv: Vec<(u64, u64)>,
+    pub files: Vec<FileMapping>,
+    /// The `siginfo_t` of the fatal signal
+    siginfo: Option<Vec<u8>>,
+    /// Path the program had in the dumped process, its mappings are read from `program`
+    pub executable: Option<String>,
+    program: PathBuf,
+    segments: Vec<CoreSegment>,
+    data: Vec<u8>,
+}
+
+/// The NUL terminated string at the start of `bytes`
+fn c_string(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn int_at(bytes: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_ne_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn word_at(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_ne_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+/// Reads a `NT_PRSTATUS` note, with `regset_len` registers
+fn parse_prstatus(desc: &[u8], regset_len: usize) -> Option<CoreThread> {
+    let signal = i16::from_ne_bytes(
+        desc.get(PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2)?
+            .try_into()
+            .ok()?,
+    );
+    let regs = (0..regset_len)
+        .map(|i| word_at(desc, PRSTATUS_REG + i * 8))
+        .collect::<Option<Vec<u64>>>()?;
+    Some(CoreThread {
+        tid: Pid::from_raw(int_at(desc, PRSTATUS_PID)?),
+        signal: signal as i32,
+        regs,
+    })
+}
+
+/// Reads a `NT_FILE` note: the number of mappings, the page size, the start, end and page
+/// offset of each mapping, then their paths
+fn parse_file_note(desc: &[u8]) -> Result<Vec<FileMapping>, String> {
+    let truncated = || "its NT_FILE note is truncated".to_string();
+    let (Some(count), Some(page_size)) = (word_at(desc, 0), word_at(desc, 8)) else {
+        return Err(truncated());
+    };
+    // the count comes from the file, a forged one must not wrap the table size around
+    let names_start = usize::try_from(count)
+        .ok()
+        .and_then(|count| count.checked_mul(24)?.checked_add(16))
+        .ok_or_else(truncated)?;
+    let names = desc.get(names_start..).ok_or_else(truncated)?;
+    names
+        .split(|&b| b == 0)
+        .take(count as usize)
+        .enumerate()
+        .map(|(i, name)| {
+            let entry = 16 + i * 24;
+            let word = |offset| word_at(desc, entry + offset).ok_or_else(truncated);
+            Ok(FileMapping {
+                start: word(0)?,
+                end: word(8)?,
+                offset: word(16)?
+                    .checked_mul(page_size)
+                    .ok_or("its NT_FILE note has a file offset out of range")?,
+                path: String::from_utf8_lossy(name).into_owned(),
+            })
+        })
+        .collect()
+}
+
+fn describe_flags(flags: u32) -> String {
+    let perm = |flag: u32, c: char| if flags & flag != 0 { c } else { '-' };
+    format!(
+        "{}{}{}p",
+        perm(elf::PF_R, 'r'),
+        perm(elf::PF_W, 'w'),
+        perm(elf::PF_X, 'x')
+    )
+}
+
+impl CoreFile {
+    /// Loads the core `path` dumped by `program`, the program's path in the core is used when
+    /// `program` is empty
+    pub fn load(path: &Path, arch: &dyn Arch, program: &Path) -> Result<Self, String> {
+        let data =
+            fs::read(path).map_err(|err| format!("Cannot read {}: {err}", path.display()))?;
+        Self::parse(path, data, arch, program)
+    }
+
+    pub fn parse(
+        path: &Path,
+        data: Vec<u8>,
+        arch: &dyn Arch,
+        program: &Path,
+    ) -> Result<Self, String> {
+        let parse_error =
+            |err: object::Error| format!("{} is not a valid core file: {err}", path.display());
+        let header = elf::FileHeader64::<Endianness>::parse(&*data).map_err(parse_error)?;
+        let endian = header.endian().map_err(parse_error)?;
+        if header.e_type(endian) != elf::ET_CORE {
+            return Err(format!("{} is not a core file", path.display()));
+        }
+        if header.e_machine(endian) != arch.elf_machine() {
+            return Err(format!(
+                "{} wasn't dumped by an {} process",
+                path.display(),
+                arch.name()
+            ));
+        }
+
+        let mut core = CoreFile {
+            path: path.to_path_buf(),
+            program: program.to_path_buf(),
+            ..Default::default()
+        };
+        for phdr in header
+            .program_headers(endian, &*data)
+            .map_err(parse_error)?
+        {
+            match phdr.p_type(endian) {
+                elf::PT_LOAD => core.segments.push(CoreSegment {
+                    vaddr: phdr.p_vaddr(endian),
+                    memsz: phdr.p_memsz(endian),
+                    offset: phdr.p_offset(endian),
+                    filesz: phdr.p_filesz(endian),
+                    flags: phdr.p_flags(endian),
+                }),
+                elf::PT_NOTE => {
+                    let Some(mut notes) = phdr.notes(endian, &*data).map_err(parse_error)? else {
+                        continue;
+                    };
+                    while let Some(note) = notes.next().map_err(parse_error)? {
+                        if note.name() == b"CORE" {
+                            core.read_note(note.n_type(endian), note.desc(), arch)
+                                .map_err(|err| {
+                                    format!("{} is not a valid core file: {err}", path.display())
+                                })?;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        if core.threads.is_empty() {
+            return Err(format!("{} has no thread", path.display()));
+        }
+
+        let entry = procfs::auxv_value(&core.auxv, procfs::AT_ENTRY);
+        core.executable = core
+            .files
+            .iter()
+            .find(|file| entry.is_some_and(|entry| file.start <= entry && entry < file.end))
+            .map(|file| file.path.clone());
+        if core.program.as_os_str().is_empty() {
+            core.program = core.executable.clone().unwrap_or_default().into();
+        }
+        core.data = data;
+        Ok(core)
+    }
+
+    fn read_note(&mut self, n_type: u32, desc: &[u8], arch: &dyn Arch) -> Result<(), String> {
+        match n_type {
+            elf::NT_PRSTATUS => self.threads.extend(parse_prstatus(desc, arch.regset_len())),
+            elf::NT_PRPSINFO => {
+                let field = |offset: usize, len: usize| {
+                    desc.get(offset..offset + len)
+                        .map(c_string)
+                        .unwrap_or_default()
+                };
+                self.pid = int_at(desc, PRPSINFO_PID).unwrap_or_default();
+                self.name = field(PRPSINFO_FNAME, PRPSINFO_FNAME_LEN);
+                self.command = field(PRPSINFO_PSARGS, PRPSINFO_PSARGS_LEN)
+                    .trim_end()
+                    .to_string();
+            }
+            elf::NT_AUXV => self.auxv = procfs::parse_auxv(desc),
+            elf::NT_FILE => self.files = parse_file_note(desc)?,
+            elf::NT_SIGINFO => self.siginfo = Some(desc.to_vec()),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// The dumped process
+    pub fn pid(&self) -> Pid {
+        match self.threads.first() {
+            Some(thread) if self.pid == 0 => thread.tid,
+            _ => Pid::from_raw(self.pid),
+        }
+    }
+
+    /// The program the core was dumped from
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    /// Where a mapped file is read from, the program may have moved since it was dumped
+    pub fn local_path(&self, path: &str) -> PathBuf {
+        if self.executable.as_deref() == Some(path) {
+            self.program.clone()
+        } else {
+            PathBuf::from(path)
+        }
+    }
+
+    /// The signal that killed the process, the one of the first thread
+    pub fn signal(&self) -> i32 {
+        self.threads.first().map_or(0, |thread| thread.signal)
+    }
+
+    /// What the kernel told about the fatal signal
+    pub fn siginfo(&self) -> Option<libc::siginfo_t> {
+        let bytes = self.siginfo.as_ref()?;
+        if bytes.len() < size_of::<libc::siginfo_t>() {
+            return None;
+        }
+        // siginfo_t is plain old data, any bytes make a valid one
+        Some(unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const libc::siginfo_t) })
+    }
+
+    /// Reads what `segment` holds at `address`, up to `len` bytes, from the core or from the
+    /// file mapped there
+    fn read_segment(&self, segment: &CoreSegment, address: u64, len: usize) -> Vec<u8> {
+        let start = address - segment.vaddr;
+        if start < segment.filesz {
+            let from = (segment.offset + start) as usize;
+            let len = len.min((segment.filesz - start) as usize);
+            return self.data.get(from..from + len).unwrap_or_default().to_vec();
+        }
+        let Some(file) = self
+            .files
+            .iter()
+            .find(|file| file.start <= address && address < file.end)
+        else {
+            return vec![];
+        };
+        let len = len.min((segment.vaddr + segment.memsz - address) as usize);
+        let mut bytes = vec![0u8; len];
+        let read = File::open(self.local_path(&file.path))
+            .and_then(|f| f.read_at(&mut bytes, file.offset + address - file.start));
+        bytes.truncate(read.unwrap_or(0));
+        bytes
+    }
+}
+
+impl Target for CoreFile {
+    fn get_regs(&self, tid: Pid, len: usize) -> nix::Result<Vec<u64>> {
+        let thread = self.threads.iter().find(|thread| thread.tid == tid);
+        let mut regs = thread.ok_or(nix::Error::ESRCH)?.regs.clone();
+        regs.resize(len, 0);
+        Ok(regs)
+    }
+
+    fn set_regs(&self, _tid: Pid, _regs: &[u64]) -> nix::Result<()> {
+        Err(nix::Error::EPERM)
+    }
+
+    fn read_memory(&self, _tid: Pid, address: u64, len: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(len);
+        while bytes.len() < len {
+            let address = address + bytes.len() as u64;
+            let Some(segment) = self.segments.iter().find(|seg| seg.contains(address)) else {
+                break;
+            };
+            let chunk = self.read_segment(segment, address, len - bytes.len());
+            if chunk.is_empty() {
+                break;
+            }
+            bytes.extend(chunk);
+        }
+        bytes
+    }
+
+    fn write_memory(&self, _tid: Pid, _address: u64, _bytes: &[u8]) -> nix::Result<()> {
+        Err(nix::Error::EPERM)
+    }
+
+    fn maps(&self, _pid: Pid) -> io::Result<Vec<MemoryMap>> {
+        Ok(self
+            .segments
+            .iter()
+            .map(|segment| {
+                let file = self.files.iter().find(|file| file.start == segment.vaddr);
+                MemoryMap {
+                    start: segment.vaddr,
+                    end: segment.vaddr + segment.memsz,
+                    perms: describe_flags(segment.flags),
+                    offset: file.map_or(0, |file| file.offset),
+                    device: "00:00".to_string(),
+                    inode: 0,
+                    pathname: file.map(|file| file.path.clone()),
+                }
+            })
+            .collect())
+    }
+
+    // the process is gone, nothing runs
+    fn resume(&self, _tid: Pid, _signal: Option<Signal>) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn resume_to_syscall(&self, _tid: Pid, _signal: Option<Signal>) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn step(&self, _tid: Pid, _signal: Option<Signal>) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn spawn(&self, _config: &LaunchConfig) -> Result<Launched, LaunchError> {
+        Err(LaunchError::Fork(nix::Error::EPERM))
+    }
+
+    fn wait(&self, _tid: Option<Pid>, _flags: Option<WaitPidFlag>) -> nix::Result<WaitStatus> {
+        Err(nix::Error::ECHILD)
+    }
+
+    fn seize(&self, _tid: Pid, _options: Options) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn set_options(&self, _tid: Pid, _options: Options) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn detach(&self, _tid: Pid, _signal: Option<Signal>) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn interrupt(&self, _tid: Pid) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn kill(&self, _pid: Pid, _signal: Signal) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn kill_thread(&self, _pid: Pid, _tid: Pid, _signal: Signal) -> nix::Result<()> {
+        Err(nix::Error::ESRCH)
+    }
+
+    fn event_message(&self, _tid: Pid) -> nix::Result<i64> {
+        Err(nix::Error::ESRCH)
+    }
+
+    // every thread is shown the fatal signal
+    fn siginfo(&self, _tid: Pid) -> nix::Result<libc::siginfo_t> {
+        self.siginfo().ok_or(nix::Error::ENODATA)
+    }
+
+    fn syscall_entry(&self, _tid: Pid) -> Option<bool> {
+        None
+    }
+
+    fn read_regset(&self, _tid: Pid, _n_type: u32) -> nix::Result<Vec<u8>> {
+        Err(nix::Error::ENODATA)
+    }
+
+    fn set_syscall_number(&self, _tid: Pid, _number: u64) -> nix::Result<()> {
+        Err(nix::Error::EPERM)
+    }
+
+    fn tasks(&self, _pid: Pid) -> io::Result<Vec<Pid>> {
+        Ok(self.threads.iter().map(|thread| thread.tid).collect())
+    }
+
+    fn executable(&self, _pid: Pid) -> io::Result<PathBuf> {
+        Ok(self.program.clone())
+    }
+
+    fn auxv(&self, _pid: Pid) -> io::Result<Vec<(u64, u64)>> {
+        Ok(self.auxv.clone())
+    }
+
+    fn thread_name(&self, _pid: Pid, _tid: Pid) -> io::Result<String> {
+        // only the name of the process is saved
+        Ok(self.name.clone())
+    }
+
+    fn stat_ids(&self, _pid: Pid) -> io::Result<[i32; 3]> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn owner(&self, _pid: Pid) -> io::Result<(u32, u32)> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn command_line(&self, _pid: Pid) -> io::Result<String> {
+        Ok(self.command.clone())
+    }
+
+    fn cwd(&self, _pid: Pid) -> io::Result<PathBuf> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    fn read_mapping(&self, pid: Pid, map: &MemoryMap) -> io::Result<Vec<u8>> {
+        Ok(self.read_memory(pid, map.start, (map.end - map.start) as usize))
+    }
+}
+
+/// A thread saved in a core file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DumpedThread {
+    pub tid: Pid,
+    /// Signal the thread stopped with, 0 for none
+    pub signal: i32,
+    /// General purpose registers, laid out like the `NT_PRSTATUS` register set
+    pub regs: Vec<u64>,
+    /// The other register sets: the type and name of their note, and their contents
+    pub regsets: Vec<(u32, &'static str, Vec<u8>)>,
+}
+
+/// What `generate-core-file` saves of a stopped process
+#[derive(Debug, Clone, Default)]
+pub struct CoreDump {
+    pub pid: i32,
+    pub ppid: i32,
+    pub pgrp: i32,
+    pub sid: i32,
+    pub uid: u32,
+    pub gid: u32,
+    pub name: String,
+    pub command: String,
+    /// The thread shown first when the core is loaded comes first
+    pub threads: Vec<DumpedThread>,
+    pub siginfo: Option<Vec<u8>>,
+    /// The raw auxiliary vector
+    pub auxv: Vec<u8>,
+    /// Every mapping with the contents saved of it, nothing for the ones left in their file
+    pub memory: Vec<(MemoryMap, Vec<u8>)>,
+}
+
+const PAGE_SIZE: u64 = 0x1000;
+const ELF_HEADER_LEN: usize = 64;
+const PROGRAM_HEADER_LEN: usize = 56;
+
+/// Appends a note: the sizes of its name and description, its type, then both padded to 4 bytes
+fn push_note(notes: &mut Vec<u8>, name: &str, n_type: u32, desc: &[u8]) {
+    notes.extend((name.len() as u32 + 1).to_ne_bytes());
+    notes.extend((desc.len() as u32).to_ne_bytes());
+    notes.extend(n_type.to_ne_bytes());
+    notes.extend(name.as_bytes());
+    notes.push(0);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+    notes.extend(desc);
+    notes.resize(notes.len().next_multiple_of(4), 0);
+}
+
+fn put(bytes: &mut [u8], offset: usize, value: &[u8]) {
+    bytes[offset..offset + value.len()].copy_from_slice(value);
+}
+
+/// Flags of a `PT_LOAD` segment from the permissions of a mapping
+fn segment_flags(perms: &str) -> u32 {
+    let perms = perms.as_bytes();
+    let flag = |i: usize, c: u8, flag: u32| if perms.get(i) == Some(&c) { flag } else { 0 };
+    flag(0, b'r', elf::PF_R) | flag(1, b'w', elf::PF_W) | flag(2, b'x', elf::PF_X)
+}
+
+impl CoreDump {
+    fn prstatus(&self, thread: &DumpedThread) -> Vec<u8> {
+        let mut desc = vec![0u8; PRSTATUS_REG + thread.regs.len() * 8 + 8];
+        // si_signo of pr_info, then pr_cursig
+        put(&mut desc, 0, &thread.signal.to_ne_bytes());
+        put(
+            &mut desc,
+            PRSTATUS_CURSIG,
+            &(thread.signal as i16).to_ne_bytes(),
+        );
+        for (i, id) in [thread.tid.as_raw(), self.ppid, self.pgrp, self.sid]
+            .iter()
+            .enumerate()
+        {
+            put(&mut desc, PRSTATUS_PID + i * 4, &id.to_ne_bytes());
+        }
+        let regs: Vec<u8> = thread
+            .regs
+            .iter()
+            .flat_map(|reg| reg.to_ne_bytes())
+            .collect();
+        put(&mut desc, PRSTATUS_REG, &regs);
+        // pr_fpvalid, the floating point registers follow in their own note
+        put(&mut desc, PRSTATUS_REG + regs.len(), &1i32.to_ne_bytes());
+        desc
+    }
+
+    fn prpsinfo(&self) -> Vec<u8> {
+        let mut desc = vec![0u8; PRPSINFO_LEN];
+        // traced
+        desc[PRPSINFO_SNAME] = b't';
+        put(&mut desc, PRPSINFO_UID, &self.uid.to_ne_bytes());
+        put(&mut desc, PRPSINFO_UID + 4, &self.gid.to_ne_bytes());
+        for (i, id) in [self.pid, self.ppid, self.pgrp, self.sid]
+            .iter()
+            .enumerate()
+        {
+            put(&mut desc, PRPSINFO_PID + i * 4, &id.to_ne_bytes());
+        }
+        // both names keep a terminating NUL
+        let name = self.name.as_bytes();
+        put(
+            &mut desc,
+            PRPSINFO_FNAME,
+            &name[..name.len().min(PRPSINFO_FNAME_LEN - 1)],
+        );
+        let command = self.command.as_bytes();
+        put(
+            &mut desc,
+            PRPSINFO_PSARGS,
+            &command[..command.len().min(PRPSINFO_PSARGS_LEN - 1)],
+        );
+        desc
+    }
+
+    /// The `NT_FILE` note of the mappings backed by a file
+    fn file_note(&self) -> Vec<u8> {
+        let files: Vec<(&MemoryMap, &str)> = self
+            .memory
+            .iter()
+            .filter_map(|(map, _)| Some((map, map.pathname.as_deref()?)))
+            .filter(|(_, path)| path.starts_with('/'))
+            .collect();
+        let mut desc = vec![];
+        desc.extend((files.len() as u64).to_ne_bytes());
+        desc.extend(PAGE_SIZE.to_ne_bytes());
+        for (map, _) in &files {
+            for value in [map.start, map.end, map.offset / PAGE_SIZE] {
+                desc.extend(value.to_ne_bytes());
+            }
+        }
+        for (_, path) in &files {
+            desc.extend(path.as_bytes());
+            desc.push(0);
+        }
+        desc
+    }
+
+    /// The notes, in the order the kernel writes them: the first thread with the notes of the
+    /// process, then the other threads
+    fn notes(&self) -> Vec<u8> {
+        let mut notes = vec![];
+        for (i, thread) in self.threads.iter().enumerate() {
+            push_note(&mut notes, "CORE", elf::NT_PRSTATUS, &self.prstatus(thread));
+            if i == 0 {
+                push_note(&mut notes, "CORE", elf::NT_PRPSINFO, &self.prpsinfo());
+                if let Some(siginfo) = &self.siginfo {
+                    push_note(&mut notes, "CORE", elf::NT_SIGINFO, siginfo);
+                }
+                push_note(&mut notes, "CORE", elf::NT_AUXV, &self.auxv);
+                push_note(&mut notes, "CORE", elf::NT_FILE, &self.file_note());
+            }
+            for (n_type, name, desc) in &thread.regsets {
+                push_note(&mut notes, name, *n_type, desc);
+            }
+        }
+        notes
+    }
+
+    /// The ELF core file: its header, a `PT_NOTE` segment, then a `PT_LOAD` segment for every
+    /// mapping, with their contents starting on a page boundary
+    pub fn to_bytes(&self, arch: &dyn Arch) -> Vec<u8> {
+        let notes = self.notes();
+        let phnum = 1 + self.memory.len();
+        let notes_offset = ELF_HEADER_LEN + phnum * PROGRAM_HEADER_LEN;
+        let memory_offset = (notes_offset + notes.len()).next_multiple_of(PAGE_SIZE as usize);
+
+        let mut core = vec![0u8; ELF_HEADER_LEN];
+        // e_ident: the magic, the class, the data encoding and the version
+        put(&mut core, 0, &elf::ELFMAG);
+        core[4] = elf::ELFCLASS64;
+        core[5] = if cfg!(target_endian = "little") {
+            elf::ELFDATA2LSB
+        } else {
+            elf::ELFDATA2MSB
+        };
+        core[6] = elf::EV_CURRENT;
+        put(&mut core, 16, &elf::ET_CORE.to_ne_bytes());
+        put(&mut core, 18, &arch.elf_machine().to_ne_bytes());
+        put(&mut core, 20, &(elf::EV_CURRENT as u32).to_ne_bytes());
+        // e_phoff, then e_ehsize, e_phentsize and e_phnum
+        put(&mut core, 32, &(ELF_HEADER_LEN as u64).to_ne_bytes());
+        put(&mut core, 52, &(ELF_HEADER_LEN as u16).to_ne_bytes());
+        put(&mut core, 54, &(PROGRAM_HEADER_LEN as u16).to_ne_bytes());
+        put(&mut core, 56, &(phnum as u16).to_ne_bytes());
+
+        let mut program_header = |p_type: u32,
+                                  flags: u32,
+                                  offset: usize,
+                                  vaddr: u64,
+                                  filesz: usize,
+                                  memsz: u64,
+                                  align: u64| {
+            for value in [p_type, flags] {
+                core.extend(value.to_ne_bytes());
+            }
+            for value in [offset as u64, vaddr, 0, filesz as u64, memsz, align] {
+                core.extend(value.to_ne_bytes());
+            }
+        };
+        program_header(elf::PT_NOTE, 0, notes_offset, 0, notes.len(), 0, 4);
+        let mut offset = memory_offset;
+        for (map, contents) in &self.memory {
+            let flags = segment_flags(&map.perms);
+            program_header(
+                elf::PT_LOAD,
+                flags,
+                offset,
+                map.start,
+                contents.len(),
+                map.end - map.start,
+                PAGE_SIZE,
+            );
+            offset += contents.len();
+        }
+
+        core.extend(notes);
+        core.resize(memory_offset, 0);
+        for (_, contents) in &self.memory {
+            core.extend(contents);
+        }
+        core
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::arch::X86_64;
+
+    fn words(values: &[u64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_file_note() {
+        let mut desc = words(&[2, 0x1000, 0x400000, 0x401000, 0, 0x401000, 0x402000, 1]);
+        desc.extend_from_slice(b"/tmp/prog\0/usr/lib/libc.so.6\0");
+        let files = parse_file_note(&desc).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "/tmp/prog");
+        assert_eq!(files[1].start, 0x401000);
+        assert_eq!(files[1].offset, 0x1000);
+        assert_eq!(files[1].path, "/usr/lib/libc.so.6");
+        assert!(parse_file_note(&words(&[3, 0x1000])).is_err());
+    }
+
+    #[test]
+    fn test_parse_forged_file_note() {
+        // unchecked, the table of this count would wrap around to the size of one mapping
+        let count = (1u64 << 61) + 1;
+        let mut desc = words(&[count, 0x1000, 0x400000, 0x401000, 0]);
+        desc.extend_from_slice(b"/tmp/prog\0");
+        assert_eq!(
+            parse_file_note(&desc),
+            Err("its NT_FILE note is truncated".to_string())
+        );
+
+        let mut desc = words(&[1, 0x1000, 0x400000, 0x401000, u64::MAX]);
+        desc.extend_from_slice(b"/tmp/prog\0");
+        assert!(parse_file_note(&desc).is_err());
+    }
+
+    #[test]
+    fn test_parse_prstatus() {
+        let mut desc = vec![0u8; PRSTATUS_REG];
+        desc[PRSTATUS_CURSIG..PRSTATUS_CURSIG + 2].copy_from_slice(&11i16.to_ne_bytes());
+        desc[PRSTATUS_PID..PRSTATUS_PID + 4].copy_from_slice(&4242i32.to_ne_bytes());
+        desc.extend(words(&(0..27).collect::<Vec<u64>>()));
+        let thread = parse_prstatus(&desc, X86_64.regset_len()).unwrap();
+
+        assert_eq!(thread.tid, Pid::from_raw(4242));
+        assert_eq!(thread.signal, 11);
+        assert_eq!(thread.regs[16], 16);
+        assert_eq!(parse_prstatus(&desc[..200], X86_64.regset_len()), None);
+    }
+
+    #[test]
+    fn test_read_memory() {
+        let core = CoreFile {
+            segments: vec![
+                CoreSegment {
+                    vaddr: 0x1000,
+                    memsz: 0x10,
+                    offset: 4,
+                    filesz: 0x10,
+                    flags: elf::PF_R | elf::PF_W,
+                },
+                // contiguous with the first one, not dumped and backed by no file
+                CoreSegment {
+                    vaddr: 0x1010,
+                    memsz: 0x10,
+                    offset: 0,
+                    filesz: 0,
+                    flags: elf::PF_R | elf::PF_X,
+                },
+            ],
+            data: (0..0x20).collect(),
+            ..Default::default()
+        };
+        let pid = Pid::from_raw(1);
+
+        assert_eq!(core.read_memory(pid, 0x1002, 4), vec![6, 7, 8, 9]);
+        // stops where the dump does
+        assert_eq!(core.read_memory(pid, 0x100e, 8), vec![18, 19]);
+        assert!(core.read_memory(pid, 0x2000, 8).is_empty());
+        assert_eq!(core.maps(pid).unwrap()[1].perms, "r-xp");
+    }
+
+    #[test]
+    fn test_write_core() {
+        let map = |start: u64, perms: &str, offset: u64, path: Option<&str>| MemoryMap {
+            start,
+            end: start + 0x2000,
+            perms: perms.to_string(),
+            offset,
+            device: "00:00".to_string(),
+            inode: 0,
+            pathname: path.map(str::to_string),
+        };
+        let thread = |tid: i32, signal: i32| DumpedThread {
+            tid: Pid::from_raw(tid),
+            signal,
+            regs: (0..27).map(|i| i + tid as u64).collect(),
+            regsets: vec![(elf::NT_PRFPREG, "CORE", vec![7; 512])],
+        };
+        let dump = CoreDump {
+            pid: 100,
+            name: "prog".to_string(),
+            command: "/tmp/prog -v".to_string(),
+            threads: vec![thread(101, 11), thread(100, 0)],
+            auxv: words(&[procfs::AT_ENTRY, 0x401020, 0, 0]),
+            memory: vec![
+                (map(0x400000, "r-xp", 0, Some("/tmp/prog")), vec![]),
+                (
+                    map(0x7ff000, "rw-p", 0, Some("[stack]")),
+                    vec![0xab; 0x2000],
+                ),
+            ],
+            ..Default::default()
+        };
+        let core = CoreFile::parse(
+            Path::new("core.100"),
+            dump.to_bytes(&X86_64),
+            &X86_64,
+            Path::new(""),
+        )
+        .unwrap();
+        let pid = core.pid();
+
+        assert_eq!(pid, Pid::from_raw(100));
+        assert_eq!(core.name, "prog");
+        assert_eq!(core.command, "/tmp/prog -v");
+        assert_eq!(core.signal(), 11);
+        assert_eq!(core.threads[1].tid, Pid::from_raw(100));
+        assert_eq!(core.get_regs(Pid::from_raw(101), 27).unwrap()[16], 117);
+        assert_eq!(core.executable.as_deref(), Some("/tmp/prog"));
+        assert_eq!(core.program(), Path::new("/tmp/prog"));
+        assert_eq!(core.files.len(), 1);
+        assert_eq!(core.read_memory(pid, 0x800ffe, 4), vec![0xab, 0xab]);
+        let maps = core.maps(pid).unwrap();
+        assert_eq!(maps[0].perms, "r-xp");
+        assert_eq!(maps[0].pathname.as_deref(), Some("/tmp/prog"));
+    }
+}
@@ -1,312 +1,4977 @@
-use std::{collections::HashMap, ffi::c_void, ops::ControlFlow, str::FromStr};
+use std::{
+    ffi::c_void,
+    ops::ControlFlow,
+    os::fd::OwnedFd,
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
 
-use linefeed::{Interface, ReadResult};
+use linefeed::{DefaultTerminal, Interface, ReadResult};
 use nix::{
+    errno::Errno,
+    libc,
     sys::{
-        ptrace::{self, cont},
-        wait::waitpid,
+        ptrace::{Event, Options},
+        signal::Signal,
+        wait::{WaitPidFlag, WaitStatus},
     },
     unistd::Pid,
 };
 use strum_macros::EnumString;
 
+use crate::register::get_register_from_name;
 use crate::{
+    arch::{self, Arch, RelocatedInstruction},
     breakpoint::Breakpoint,
-    register::{get_register_value, Reg, REGISTERS_DESCRIPTORS},
-};
-use crate::{
-    breakpoint::RealPtraceOps,
-    register::{get_register_from_name, set_register_value},
+    catchpoint::{self, CatchKind, Catchpoint, ProcessEvent},
+    console::{self, Console},
+    corefile::{CoreDump, CoreFile, DumpedThread},
+    error::DebuggerError,
+    examine::{self, ExamineFormat},
+    inferior::Inferior,
+    launch::{self, LaunchConfig, TerminalMode},
+    location::Location,
+    procfs::{self, MemoryMap},
+    register::{self, read_register, write_register, Reg},
+    signals::{self, SigInfo, SignalTable},
+    solib::{self, RT_CONSISTENT},
+    strace::{self, SyscallFilter, TracedCall},
+    symbols::{self, Module, ObjectFile},
+    target::{self, LiveTarget, Target},
+    thread::ThreadState,
+    unwind,
 };
 
 static NO_COMMAND_PROVIDED_ERROR_MSG: &str = r#"
 No command or invalid command were provided
 Try using one of the following:
-1. continue
-2. break 0xADDRESS
-3. memory [read/write] 0xADDRESS
-4. register [dump/read/write] [0xADDRESS]
-5. exit
+1. run [ARGS...] [< IN] [> OUT] | restart
+2. continue [-a] | interrupt [-a]
+3. break [0xADDRESS | FUNCTION | FILE:LINE]
+4. memory [read/write] 0xADDRESS
+5. x[/NFU] [0xADDRESS | SYMBOL]
+6. register [dump/read/write] [0xADDRESS]
+7. info proc [mappings] | info sharedlibrary | info threads | info inferiors | info signals [SIGNAL] | info siginfo
+8. thread [N] | inferior [N]
+9. kill
+10. attach PID | detach
+11. set args [ARGS...] [< IN] [> OUT] | set env KEY=VALUE | set disable-randomization on|off | set non-stop on|off
+    set follow-fork-mode parent|child | set detach-on-fork on|off
+12. unset env [KEY]
+13. cd DIRECTORY
+14. tty [/dev/pts/N | pty | inherit]
+15. catch syscall [NAME | NUMBER]... | catch fork|vfork|exec|clone|exit
+    trace syscalls on [NAME | %CLASS]... | trace syscalls off
+    syscall skip [VALUE] | syscall return VALUE | syscall arg N VALUE
+16. handle SIGNAL [stop|nostop] [print|noprint] [pass|nopass] | signal SIGNAL | queue-signal SIGNAL
+17. backtrace [N] | print [$REGISTER | SYMBOL | &SYMBOL]
+    generate-core-file [PATH]
+18. exit
 "#;
 
+static CORE_FILE_ERROR_MSG: &str = "A core file can't be changed, run the program instead";
+
+/// Longest instruction of the supported architectures
+const MAX_INSTRUCTION_LEN: usize = 16;
+
+/// How often the threads running in non-stop mode are checked while the prompt waits
+const NON_STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Events followed in every traced process: new threads, forks, execs, exits, and syscalls
+/// told apart from SIGTRAP
+const TRACE_OPTIONS: Options = Options::PTRACE_O_TRACESYSGOOD
+    .union(Options::PTRACE_O_TRACECLONE)
+    .union(Options::PTRACE_O_TRACEFORK)
+    .union(Options::PTRACE_O_TRACEVFORK)
+    .union(Options::PTRACE_O_TRACEVFORKDONE)
+    .union(Options::PTRACE_O_TRACEEXEC)
+    .union(Options::PTRACE_O_TRACEEXIT);
+
 #[derive(Debug, PartialEq, EnumString)]
 #[strum(ascii_case_insensitive)]
+#[allow(clippy::upper_case_acronyms)]
 enum Command {
+    #[strum(serialize = "run", serialize = "r")]
+    RUN,
+    RESTART,
+    KILL,
+    ATTACH,
+    DETACH,
     CONTINUE,
+    INTERRUPT,
     REGISTER,
     MEMORY,
+    #[strum(serialize = "x", serialize = "examine")]
+    EXAMINE,
+    INFO,
+    THREAD,
+    INFERIOR,
     EXIT,
     BREAK,
+    SET,
+    UNSET,
+    CD,
+    TTY,
+    HANDLE,
+    CATCH,
+    TRACE,
+    SYSCALL,
+    SIGNAL,
+    #[strum(serialize = "queue-signal")]
+    QUEUESIGNAL,
+    #[strum(serialize = "backtrace", serialize = "bt")]
+    BACKTRACE,
+    #[strum(serialize = "print", serialize = "p")]
+    PRINT,
+    #[strum(serialize = "generate-core-file", serialize = "gcore")]
+    GENERATECOREFILE,
+}
+
+/// Why a thread stopped, told to the user by `report_stop`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// A signal, SIGTRAP for breakpoints and SIGSTOP for interruptions
+    Signal(Signal),
+    /// A syscall catchpoint, on entry to the syscall or when it returns
+    Syscall {
+        catchpoint: usize,
+        number: u64,
+        entry: bool,
+    },
+    /// A catchpoint on a fork, exec, thread creation or exit
+    Event {
+        catchpoint: usize,
+        event: ProcessEvent,
+    },
+}
+
+/// The process debugged after a fork, set with `set follow-fork-mode`
+#[derive(Debug, Clone, Copy, PartialEq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+enum FollowForkMode {
+    Parent,
+    Child,
 }
 
 pub struct Debugger {
-    prog_name: String,
-    pid: Pid,
-    breakpoints: HashMap<u64, Breakpoint<RealPtraceOps>>,
+    launch: LaunchConfig,
+    arch: &'static dyn Arch,
+    /// The inferior the commands act on
+    inferior: Inferior,
+    /// The other inferiors, every thread of their process is stopped
+    inferiors: Vec<Inferior>,
+    /// Only the thread reporting an event stops, the others keep running (non-stop mode)
+    non_stop: bool,
+    /// Whether the parent or the child is debugged after a fork
+    follow_fork_mode: FollowForkMode,
+    /// Whether the process not followed after a fork runs freely, or becomes a new inferior
+    detach_on_fork: bool,
+    /// Forked children whose first stop came before their parent reported the fork
+    fork_stops: Vec<Pid>,
+    /// Whether each signal stops the debuggee, is told about and is delivered to it
+    signals: SignalTable,
+    /// Events the debuggee stops on besides breakpoints
+    catchpoints: Vec<Catchpoint>,
+    /// The syscalls logged as the debuggee makes them, set with `trace syscalls on`
+    syscall_trace: Option<SyscallFilter>,
+    /// The core file being looked at instead of a live process, set with `--core`
+    core: Option<CoreFile>,
+    /// What the user asked to break on, resolved again on every run since addresses may move
+    locations: Vec<Location>,
+    console: Arc<Console>,
+    /// Master side of the debuggee's pseudo-terminal, typed input is sent there while it runs
+    pty: Option<OwnedFd>,
+    /// How the traced processes are controlled, ptrace unless a test mocks it
+    live: Rc<dyn Target>,
+    /// The thread that stopped last and why, until the debuggee is resumed
+    last_stop: Option<(Pid, StopReason)>,
+    /// Set by `exit` once the debuggees are ended, the prompt returns
+    exiting: bool,
 }
 
-fn str_addr_to_c_void(s: &str) -> *mut c_void {
-    let address =
-        i64::from_str_radix(s.trim_start_matches("0x"), 16).expect("Failed to parse address");
-    println!("The address is : {}", address);
+fn str_addr_to_c_void(s: &str) -> Result<*mut c_void, DebuggerError> {
+    let address = str_to_addr(s)?;
+    Ok(address as *mut c_void)
+}
 
-    address as *mut c_void
+/// The stop a thread reports when the debugger interrupts it, or when it starts
+fn is_stop_request(status: &WaitStatus) -> bool {
+    match status {
+        WaitStatus::Stopped(_, Signal::SIGSTOP) => true,
+        WaitStatus::PtraceEvent(_, _, event) => *event == Event::PTRACE_EVENT_STOP as i32,
+        _ => false,
+    }
 }
 
-fn str_to_addr(s: &str) -> i64 {
-    i64::from_str_radix(s.trim_start_matches("0x"), 16).expect("Failed to parse address")
+fn str_to_addr(s: &str) -> Result<i64, DebuggerError> {
+    i64::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| DebuggerError::InvalidNumber(s.to_string()))
 }
 
-fn str_to_reg_value(s: &str) -> u64 {
+fn str_to_reg_value(s: &str) -> Result<u64, DebuggerError> {
     u64::from_str_radix(s.trim_start_matches("0x"), 16)
-        .expect("Failed to parse the string to an u64 value")
+        .map_err(|_| DebuggerError::InvalidNumber(s.to_string()))
 }
 
 impl Debugger {
-    pub fn new(prog_name: String, pid: Pid) -> Self {
-        Self {
-            prog_name,
-            pid,
-            breakpoints: HashMap::new(),
-        }
+    pub fn new(launch: LaunchConfig) -> Self {
+        Self::with_target(launch, Rc::new(LiveTarget))
     }
 
-    pub fn continue_execution(&mut self) {
-        self.step_over_breakpoint();
-        cont(self.pid, None).unwrap();
-        let _wait_status = waitpid(self.pid, None);
+    /// A debugger controlling its processes through `live`
+    pub(crate) fn with_target(launch: LaunchConfig, live: Rc<dyn Target>) -> Self {
+        let mut debugger = Self {
+            launch,
+            arch: arch::native(),
+            inferior: Inferior::new(1),
+            inferiors: vec![],
+            non_stop: false,
+            follow_fork_mode: FollowForkMode::Parent,
+            detach_on_fork: true,
+            fork_stops: vec![],
+            signals: SignalTable::default(),
+            catchpoints: vec![],
+            syscall_trace: None,
+            core: None,
+            locations: vec![],
+            console: Arc::new(Console::new()),
+            pty: None,
+            live,
+            last_stop: None,
+            exiting: false,
+        };
+        debugger.load_program_symbols(None);
+        debugger
     }
 
-    pub fn handle_command(&mut self, command: &String) {
-        let command_line: Vec<String> = command
-            .split_whitespace()
-            .map(|el| el.to_string())
-            .collect();
+    /// Loads the program's symbols, relocated to where `pid` mapped it. Before
+    /// the program runs they are at their link-time address.
+    fn load_program_symbols(&mut self, pid: Option<Pid>) {
+        let path = match pid {
+            Some(pid) => match self.live.executable(pid) {
+                Ok(path) => path,
+                Err(err) => {
+                    eprintln!("No symbols loaded: {err}");
+                    self.inferior.modules.clear();
+                    return;
+                }
+            },
+            None if self.launch.program.is_empty() => return,
+            None => launch::resolve_program(
+                &self.launch.program,
+                self.launch.env.get("PATH").map(String::as_str),
+            ),
+        };
+        let object = match ObjectFile::load(&path) {
+            Ok(object) => object,
+            Err(err) => {
+                eprintln!("No symbols loaded: {err}");
+                self.inferior.modules.clear();
+                return;
+            }
+        };
 
-        let Some(command) = command_line.get(0) else {
-            println!("{NO_COMMAND_PROVIDED_ERROR_MSG}");
+        let bias = match pid.map(|pid| self.live.auxv(pid)) {
+            Some(Ok(auxv)) => symbols::executable_bias(&object, &auxv),
+            Some(Err(_)) => {
+                eprintln!("Cannot read the auxiliary vector, symbols are not relocated");
+                0
+            }
+            None => 0,
+        };
+        if bias != 0 {
+            println!("{} is loaded at 0x{bias:x}", self.launch.program);
+        }
+        self.inferior.modules = vec![Module::new(object, bias)];
+    }
+
+    /// Follows the libraries loaded by the dynamic loader. Before it has run, its own symbols
+    /// give the function it calls on every change; afterwards `r_debug` does.
+    fn watch_shared_libraries(&mut self, pid: Pid) {
+        self.inferior.r_debug = None;
+        self.inferior.solib_event = None;
+        // a static program has no dynamic loader to watch
+        let Some(program) = self.inferior.modules.first() else {
+            return;
+        };
+        let Some(dynamic) = program
+            .object
+            .dynamic_vaddr
+            .map(|vaddr| program.to_runtime(vaddr))
+        else {
             return;
         };
 
-        let arg1 = command_line.get(1);
-        let arg2 = command_line.get(2);
-        let arg3 = command_line.get(3);
+        self.inferior.r_debug = solib::find_r_debug(&self.live, pid, dynamic);
+        let event = match self.inferior.r_debug {
+            Some(r_debug) => solib::read_r_debug(&self.live, pid, r_debug).map(|r| r.brk),
+            None => self.load_dynamic_loader(pid).and_then(|loader| {
+                loader
+                    .symbol_address("_dl_debug_state")
+                    .or_else(|| loader.symbol_address("_r_debug_state"))
+            }),
+        };
+        let Some(event) = event.filter(|&event| event != 0) else {
+            eprintln!("Cannot follow the shared libraries of the program");
+            return;
+        };
 
-        if let Ok(ecommand) = Command::from_str(command) {
-            match ecommand {
-                Command::CONTINUE => self.continue_execution(),
-                Command::EXIT => std::process::exit(0),
-                Command::BREAK => {
-                    if let ControlFlow::Break(_) = self.break_command_handle(arg1) {
-                        return;
-                    }
-                }
-                Command::REGISTER => {
-                    if let ControlFlow::Break(_) = self.register_command_handle(arg1, arg2, arg3) {
-                        return;
-                    }
-                }
-                Command::MEMORY => {
-                    if let ControlFlow::Break(_) = self.memory_command_handle(arg1, arg2, arg3) {
-                        return;
-                    }
-                }
+        self.inferior.solib_event = Some(event);
+        if !self.inferior.breakpoints.contains_key(&event) {
+            let mut b = Breakpoint::new(event as *mut c_void, self.arch, self.live.clone());
+            if let Err(err) = b.enable(pid) {
+                eprintln!("Cannot follow the shared libraries of the program: {err}");
+                return;
             }
-        } else {
-            println!("{NO_COMMAND_PROVIDED_ERROR_MSG}");
+            self.inferior.breakpoints.insert(event, b);
+        }
+        if self.inferior.r_debug.is_some() {
+            self.update_shared_libraries(pid);
         }
     }
 
-    fn break_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
-        if arg1.is_some() {
-            self.set_breakpoint_at_address(arg1.unwrap());
-        } else {
-            eprintln!("No address provided for the breakpoint");
-            return ControlFlow::Break(());
+    /// Loads the dynamic loader's symbols from where the kernel mapped it (`AT_BASE`)
+    fn load_dynamic_loader(&mut self, pid: Pid) -> Option<&Module> {
+        let auxv = self.live.auxv(pid).ok()?;
+        let base = procfs::auxv_value(&auxv, procfs::AT_BASE).filter(|&base| base != 0)?;
+        let maps = self.live.maps(pid).ok()?;
+        let path = procfs::find_map(&maps, base)?.pathname.clone()?;
+        let object = ObjectFile::load(Path::new(&path)).ok()?;
+        // the link map gives the same bias for the loader once it is read
+        self.inferior.modules.push(Module::new(object, base));
+        self.inferior.modules.last()
+    }
+
+    /// Reads the link map and loads or drops the modules of the libraries that changed
+    fn update_shared_libraries(&mut self, pid: Pid) {
+        if self.inferior.r_debug.is_none() {
+            let Some(program) = self.inferior.modules.first() else {
+                return;
+            };
+            let dynamic = program
+                .object
+                .dynamic_vaddr
+                .map(|vaddr| program.to_runtime(vaddr));
+            self.inferior.r_debug =
+                dynamic.and_then(|dynamic| solib::find_r_debug(&self.live, pid, dynamic));
+        }
+        let Some(r_debug) = self
+            .inferior
+            .r_debug
+            .and_then(|address| solib::read_r_debug(&self.live, pid, address))
+        else {
+            return;
         };
-        ControlFlow::Continue(())
+        // the loader calls r_brk before and after changing the list, only the latter can be read
+        if r_debug.state != RT_CONSISTENT {
+            return;
+        }
+
+        // the main program comes first, with an empty name
+        let libraries: Vec<_> = solib::read_link_map(&self.live, pid, r_debug.map)
+            .into_iter()
+            .filter(|entry| !entry.name.is_empty())
+            .collect();
+
+        let (mut modules, unloaded): (Vec<_>, Vec<_>) = self
+            .inferior
+            .modules
+            .split_off(1.min(self.inferior.modules.len()))
+            .into_iter()
+            .partition(|module| libraries.iter().any(|entry| entry.addr == module.bias));
+        for module in &unloaded {
+            println!("Unloaded {}", module.object.path.display());
+        }
+        for entry in libraries {
+            if modules.iter().any(|module| module.bias == entry.addr) {
+                continue;
+            }
+            match ObjectFile::load(Path::new(&entry.name)) {
+                Ok(object) => {
+                    println!("Loaded symbols for {}", entry.name);
+                    modules.push(Module::new(object, entry.addr));
+                }
+                // the vDSO is in the list without being a file
+                Err(_) if !entry.name.contains('/') => {}
+                Err(err) => eprintln!("{err}"),
+            }
+        }
+        self.inferior.modules.append(&mut modules);
+
+        if !unloaded.is_empty() {
+            self.repend_breakpoints(&unloaded);
+        }
+        self.resolve_pending_breakpoints(pid);
     }
 
-    fn register_command_handle(
-        &mut self,
-        arg1: Option<&String>,
-        arg2: Option<&String>,
-        arg3: Option<&String>,
-    ) -> ControlFlow<()> {
-        if arg1.is_none() {
-            eprintln!("You cannot call the register command without any arguments");
-            return ControlFlow::Break(());
+    /// Takes out the traps that were in unloaded libraries, their locations wait again for
+    /// a library defining them
+    fn repend_breakpoints(&mut self, unloaded: &[Module]) {
+        let gone: Vec<u64> = self
+            .inferior
+            .breakpoints
+            .keys()
+            .copied()
+            .filter(|&address| unloaded.iter().any(|module| module.contains(address)))
+            .collect();
+        // the memory holding the traps is gone, there is nothing to restore
+        let mut removed: Vec<_> = gone
+            .iter()
+            .filter_map(|address| self.inferior.breakpoints.remove(address))
+            .collect();
+        if removed.is_empty() {
+            return;
         }
-        let arg1 = arg1.unwrap();
-        let arg1 = arg1.to_lowercase();
-        if arg1 == "dump" {
-            self.dump_registers();
-        } else if arg1 == "read" {
-            if let ControlFlow::Break(_) = self.register_read(arg2) {
-                return ControlFlow::Break(());
+
+        for location in self.locations.clone() {
+            let is_pending = self
+                .inferior
+                .pending
+                .iter()
+                .any(|(pending, _)| *pending == location);
+            if matches!(location, Location::Address(_))
+                || is_pending
+                || !self.resolve_location(&location).is_empty()
+            {
+                continue;
             }
-        } else if arg1 == "write" {
-            if let ControlFlow::Break(_) = self.register_write(arg2, arg3) {
-                return ControlFlow::Break(());
+            let mut bp = removed
+                .pop()
+                .unwrap_or_else(|| Breakpoint::new_pending(self.arch, self.live.clone()));
+            bp.unresolve();
+            println!("Breakpoint at {location} is pending again");
+            self.inferior.pending.push((location, bp));
+        }
+    }
+
+    /// Inserts the pending breakpoints whose location the loaded modules now define
+    fn resolve_pending_breakpoints(&mut self, pid: Pid) {
+        for (location, bp) in std::mem::take(&mut self.inferior.pending) {
+            for address in self.insert_location(pid, &location, bp) {
+                println!(
+                    "Pending breakpoint at {location} resolved to {}",
+                    self.describe_address(address)
+                );
             }
         }
-        ControlFlow::Continue(())
     }
 
-    fn memory_command_handle(
+    /// Gives `bp` the addresses of `location` and inserts a trap at each of them.
+    /// The breakpoint stays pending when no loaded module defines the location.
+    fn insert_location(
         &mut self,
-        arg1: Option<&String>,
-        arg2: Option<&String>,
-        arg3: Option<&String>,
-    ) -> ControlFlow<()> {
-        if arg1.is_none() {
-            eprintln!("Command memory cannot be called without any arguments");
-            return ControlFlow::Break(());
+        pid: Pid,
+        location: &Location,
+        bp: Breakpoint<Rc<dyn Target>>,
+    ) -> Vec<u64> {
+        let addresses = self.resolve_location(location);
+        if addresses.is_empty() {
+            self.inferior.pending.push((location.clone(), bp));
+            return addresses;
         }
-        let arg1 = arg1.unwrap();
-        if arg2.is_none() {
-            eprintln!("You should precise the address you want to manipulate");
-            return ControlFlow::Break(());
+        for &address in &addresses {
+            // an enabled trap is already there, its saved instruction must not be overwritten
+            if self.inferior.breakpoints.contains_key(&address) {
+                continue;
+            }
+            let mut b = bp.clone();
+            b.resolve(address as *mut c_void);
+            match b.enable(pid) {
+                Ok(()) => {
+                    self.inferior.breakpoints.insert(address, b);
+                }
+                Err(err) => eprintln!("{err}"),
+            }
         }
-        let arg2 = arg2.unwrap();
+        addresses
+    }
 
-        if arg1 == "read" {
-            let Ok(val) = ptrace::read(self.pid, str_addr_to_c_void(arg2)) else {
-                eprintln!("Cannot read data at this memory address");
-                return ControlFlow::Break(());
-            };
-            println!("{} --> {}", arg2, val);
-        } else if arg1 == "write" {
-            if let ControlFlow::Break(_) = self.memory_write(arg3, arg2) {
-                return ControlFlow::Break(());
-            }
+    /// Runtime addresses of a location, empty when it can't be found in the loaded modules
+    pub fn resolve_location(&self, location: &Location) -> Vec<u64> {
+        match location {
+            Location::Address(address) => vec![*address],
+            Location::Symbol(name) => self
+                .inferior
+                .modules
+                .iter()
+                .filter_map(|module| module.symbol_address(name))
+                .take(1)
+                .collect(),
+            Location::Line { file, line } => self
+                .inferior
+                .modules
+                .iter()
+                .flat_map(|module| module.line_addresses(file, *line))
+                .collect(),
         }
-        ControlFlow::Continue(())
     }
 
-    fn memory_write(&mut self, arg3: Option<&String>, arg2: &String) -> ControlFlow<()> {
-        if arg3.is_none() {
-            eprintln!("You should precise the value that will be set to the register");
-            return ControlFlow::Break(());
+    /// Inserts a trap for every address of every location in `pid`, the locations
+    /// found nowhere yet wait for their library
+    fn insert_breakpoints(&mut self, pid: Pid) {
+        self.inferior.breakpoints.clear();
+        self.inferior.pending.clear();
+        for location in self.locations.clone() {
+            let bp = Breakpoint::new_pending(self.arch, self.live.clone());
+            self.insert_location(pid, &location, bp);
         }
-        let arg3 = arg3.unwrap();
-        let val = str_to_addr(arg3);
-        let Ok(_) = ptrace::write(self.pid, str_addr_to_c_void(arg2), val) else {
-            eprintln!("Cannot write to that address");
-            return ControlFlow::Break(());
+    }
+
+    /// `0x401146 <add+4> at /src/prog.c:3` or as much of it as the symbols tell
+    fn describe_address(&self, address: u64) -> String {
+        let mut description = format!("0x{address:x}");
+        let Some(module) = self
+            .inferior
+            .modules
+            .iter()
+            .find(|module| module.contains(address))
+        else {
+            return description;
         };
-        ControlFlow::Continue(())
+        if let Some((sym, offset)) = module.symbol_at(address) {
+            description.push_str(&format!(" <{}+{offset}>", sym.name));
+        }
+        if let Some(line) = module.line_at(address) {
+            description.push_str(&format!(" at {}:{}", line.file.display(), line.line));
+        }
+        description
     }
 
-    fn register_write(&mut self, arg2: Option<&String>, arg3: Option<&String>) -> ControlFlow<()> {
-        if arg2.is_none() {
-            eprintln!("This command requires a register name");
-            return ControlFlow::Break(());
+    /// The selected thread of the traced process
+    fn tracee(&self) -> Result<Pid, DebuggerError> {
+        self.inferior
+            .pid
+            .and(self.inferior.current_thread)
+            .ok_or(DebuggerError::NoThread)
+    }
+
+    /// The selected thread when it is stopped, ptrace can't reach a running thread
+    fn stopped_tracee(&self) -> Result<Pid, DebuggerError> {
+        let tid = self.tracee()?;
+        if self
+            .inferior
+            .threads
+            .get(tid)
+            .is_some_and(|thread| thread.state == ThreadState::Running)
+        {
+            return Err(DebuggerError::ThreadRunning);
         }
-        let arg2 = arg2.unwrap();
-        if arg3.is_none() {
-            eprintln!("This command requires a value that will be set to the register");
-            return ControlFlow::Break(());
+        Ok(tid)
+    }
+
+    /// The selected thread when its registers and memory can be read: a stopped thread of the
+    /// process, or a thread of the core file
+    fn inspected_thread(&self) -> Result<Pid, DebuggerError> {
+        if self.core.is_some() {
+            return self.inferior.current_thread.ok_or(DebuggerError::NoThread);
         }
-        let arg3 = arg3.unwrap();
-        let Some(reg) = get_register_from_name(arg2) else {
-            eprintln!("This register doesn't exist in the table");
-            return ControlFlow::Break(());
-        };
-        let val = str_to_reg_value(arg3);
-        set_register_value(self.pid, reg, val).unwrap();
+        self.stopped_tracee()
+    }
 
-        ControlFlow::Continue(())
+    /// Where registers and memory are read from
+    fn target(&self) -> &dyn Target {
+        match &self.core {
+            Some(core) => core,
+            None => self.live.as_ref(),
+        }
     }
 
-    fn register_read(&mut self, arg2: Option<&String>) -> ControlFlow<()> {
-        if arg2.is_none() {
-            eprintln!("This command requires a register name");
-            return ControlFlow::Break(());
+    /// Opens a core file the program left instead of running it. Its threads, registers and
+    /// memory can be looked at, nothing can be changed.
+    pub fn load_core(&mut self, path: &Path) -> Result<(), DebuggerError> {
+        let program = PathBuf::from(&self.launch.program);
+        let core = CoreFile::load(path, self.arch, &program).map_err(DebuggerError::Core)?;
+        if self.launch.program.is_empty() {
+            self.launch.program = core.program().to_string_lossy().into_owned();
         }
-        let arg2 = arg2.unwrap();
-        let Some(reg) = get_register_from_name(arg2) else {
-            eprintln!("This register doesn't exist in the table");
-            return ControlFlow::Break(());
-        };
-        let Ok(val) = get_register_value(self.pid, reg) else {
-            eprintln!("Cannot get the value of this register");
-            return ControlFlow::Break(());
-        };
-        println!("{} -> {}", arg2, val);
-        ControlFlow::Continue(())
+        self.inferior.threads.clear();
+        for thread in &core.threads {
+            self.inferior.threads.add(thread.tid, ThreadState::Stopped);
+        }
+        // the thread that got the fatal signal comes first
+        let tid = core.threads[0].tid;
+        self.inferior.current_thread = Some(tid);
+        self.load_core_symbols(&core);
+
+        println!("Core was generated by `{}'.", core.command);
+        if let Ok(signal) = Signal::try_from(core.signal()) {
+            println!("Program terminated with signal {signal}.");
+        }
+        self.core = Some(core);
+        println!("#0  {}", self.describe_frame(tid));
+        Ok(())
     }
 
-    pub fn run(&mut self) {
-        let _wait_status = waitpid(self.pid, None);
-        let reader = Interface::new("vdebugger").unwrap();
-        println!("The program name is {}", self.prog_name);
-        reader.set_prompt("vdebugger> ").unwrap_or_else(|_| {});
+    /// Loads the symbols of the program and of the libraries it had mapped when it was dumped
+    fn load_core_symbols(&mut self, core: &CoreFile) {
+        self.inferior.modules.clear();
+        match ObjectFile::load(core.program()) {
+            Ok(object) => {
+                let bias = symbols::executable_bias(&object, &core.auxv);
+                self.inferior.modules.push(Module::new(object, bias));
+            }
+            Err(err) => eprintln!("No symbols loaded: {err}"),
+        }
 
-        while let ReadResult::Input(input) = reader.read_line().unwrap_or_else(|_| ReadResult::Eof)
-        {
-            reader.add_history_unique(input.clone());
-            self.handle_command(&input.clone());
+        let maps = core.maps(core.pid()).unwrap_or_default();
+        let mut paths: Vec<&str> = vec![];
+        for file in &core.files {
+            if core.executable.as_ref() != Some(&file.path) && !paths.contains(&file.path.as_str())
+            {
+                paths.push(&file.path);
+            }
+        }
+        for path in paths {
+            let local = core.local_path(path);
+            if !local.exists() {
+                eprintln!("Cannot find {path}, its symbols are not loaded");
+                continue;
+            }
+            // data files are mapped too
+            let Ok(object) = ObjectFile::load(&local) else {
+                continue;
+            };
+            if let Some(bias) = symbols::mapped_bias(&object, &maps, path) {
+                println!("Loaded symbols for {path}");
+                self.inferior.modules.push(Module::new(object, bias));
+            }
         }
     }
 
-    pub fn set_breakpoint_at_address(&mut self, address: &str) {
-        println!("Set breakpoint at address {}", address);
-        let addr = str_addr_to_c_void(address);
-        let mut b = Breakpoint::new(self.pid, addr, RealPtraceOps);
-        b.enable();
-        self.breakpoints.insert(str_to_reg_value(address), b);
+    /// Forgets the core file once a process is debugged instead
+    fn close_core(&mut self) {
+        if self.core.take().is_some() {
+            self.inferior.threads.clear();
+            self.inferior.current_thread = None;
+        }
     }
 
-    fn step_over_breakpoint(&mut self) {
-        let current_line = self.get_pc() - 1; // because execution will be past the breakpoint
-        if self.breakpoints.contains_key(&current_line) {
-            let bp = self.breakpoints.get_mut(&current_line).unwrap();
+    /// Starts the program from scratch, reinserts the breakpoints and runs it
+    /// until it stops. A debuggee that is already running is killed first.
+    pub fn start_debuggee(&mut self) -> Result<(), DebuggerError> {
+        if self.inferior.pid.is_some() {
+            println!("Restarting {} from the beginning", self.launch.program);
+            self.kill_debuggee()?;
+        }
+        self.close_core();
 
-            if bp.enabled {
-                let prev = current_line;
-                self.set_pc(prev);
+        let launched = self
+            .live
+            .spawn(&self.launch)
+            .map_err(|err| DebuggerError::Launch(err.to_string()))?;
+        let pid = launched.pid;
 
-                let bp = self.breakpoints.get_mut(&current_line).unwrap();
-                bp.disable();
+        // the child stops on the SIGTRAP raised by its exec
+        let Ok(WaitStatus::Stopped(..)) = self.live.wait(Some(pid), None) else {
+            return Err(DebuggerError::Launch(
+                "The debuggee didn't stop after its exec".to_string(),
+            ));
+        };
+        // never leave a debuggee full of trap instructions behind us, and follow its threads,
+        // forks and execs
+        let _ = self
+            .live
+            .set_options(pid, Options::PTRACE_O_EXITKILL | TRACE_OPTIONS);
 
-                let Ok(_) = ptrace::step(self.pid, None) else {
-                    eprintln!("Cannot go to the next line");
-                    std::process::exit(-1);
-                };
+        if let Some(output) = launched.pty.as_ref().and_then(|pty| pty.try_clone().ok()) {
+            console::forward_output(output, self.console.clone());
+        }
+        self.inferior.pid = Some(pid);
+        self.pty = launched.pty;
+        self.inferior.threads.add(pid, ThreadState::Stopped);
+        self.inferior.current_thread = Some(pid);
+        println!("Starting the debugging process {pid}");
 
-                let Ok(_) = waitpid(self.pid, None) else {
-                    eprintln!("Cannot communicate with the debuggee process");
-                    std::process::exit(-1);
-                };
+        self.load_program_symbols(Some(pid));
+        self.insert_breakpoints(pid);
+        self.watch_shared_libraries(pid);
+
+        if self.non_stop {
+            self.resume_threads(&[pid]);
+        } else {
+            self.resume_and_wait();
+        }
+        Ok(())
+    }
+
+    /// Starts tracing a process that is already running, with all its threads
+    pub fn attach(&mut self, pid: Pid) -> Result<(), DebuggerError> {
+        if let Some(current) = self.inferior.pid {
+            return Err(DebuggerError::AlreadyDebugging(current));
+        }
+
+        self.live
+            .seize(pid, TRACE_OPTIONS)
+            .map_err(|errno| DebuggerError::Attach { pid, errno })?;
+        self.close_core();
+        self.inferior.pid = Some(pid);
+        self.inferior.attached = true;
+        self.inferior.threads.add(pid, ThreadState::Running);
+        self.inferior.current_thread = Some(pid);
+
+        // threads created from now on are followed through TRACECLONE, the older ones are
+        // seized one by one until no new one shows up
+        loop {
+            let new_threads: Vec<Pid> = self
+                .live
+                .tasks(pid)
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|&tid| self.inferior.threads.get(tid).is_none())
+                .collect();
+            if new_threads.is_empty() {
+                break;
+            }
+            for tid in new_threads {
+                if self.live.seize(tid, TRACE_OPTIONS).is_ok() {
+                    self.inferior.threads.add(tid, ThreadState::Running);
+                }
+            }
+        }
+        self.stop_all_threads(pid);
+        if self.inferior.threads.get(pid).is_none() {
+            // it ended before it could be stopped
+            self.forget_debuggee();
+            return Err(DebuggerError::Attach {
+                pid,
+                errno: Errno::ESRCH,
+            });
+        }
 
-                bp.enable();
+        if let Ok(exe) = self.live.executable(pid) {
+            if self.launch.program.is_empty() {
+                self.launch.program = exe.to_string_lossy().into_owned();
             }
         }
+        println!("Attached to process {pid} ({})", self.launch.program);
+
+        self.load_program_symbols(Some(pid));
+        self.insert_breakpoints(pid);
+        self.watch_shared_libraries(pid);
+        Ok(())
     }
 
-    pub fn dump_registers(&self) {
-        REGISTERS_DESCRIPTORS.iter().for_each(|&desc| {
-            let Ok(val) = get_register_value(self.pid, desc.r) else {
-                eprintln!("Cannot get value of the register {:?}. Verify that the debuggee's process hasn't ended", desc.r);
-                std::process::exit(-1)
-            };
-            println!("{} 0x{:016x}", desc.name, val);
-        });
+    /// Removes every inserted breakpoint then lets the process run freely.
+    /// The breakpoints are kept for the next run or attach.
+    pub fn detach(&mut self) -> Result<(), DebuggerError> {
+        let pid = self.inferior.pid.ok_or(DebuggerError::NoThread)?;
+        self.detach_threads(pid);
+        println!("Detached from process {pid}");
+        self.forget_debuggee();
+        Ok(())
+    }
+
+    /// Stops every thread of `pid` and takes the traps out of it, `breakpoints` is left as is
+    fn remove_traps(&mut self, pid: Pid) {
+        // in non-stop mode some threads may be running
+        self.stop_all_threads(pid);
+
+        // a thread stopped on a trap must resume at the instruction the trap replaced
+        for tid in self.inferior.threads.tids() {
+            if let Some(trap_addr) = self.hit_trap(tid) {
+                if let Err(err) = self.set_pc(tid, trap_addr) {
+                    eprintln!("{err}");
+                }
+                let Some(thread) = self.inferior.threads.get_mut(tid) else {
+                    eprintln!("{}", DebuggerError::thread_gone(tid));
+                    continue;
+                };
+                thread.hit_trap = false;
+            }
+        }
+        let mut traps = self.inferior.breakpoints.clone();
+        for bp in traps.values_mut().filter(|bp| bp.enabled) {
+            if let Err(err) = bp.disable(pid) {
+                eprintln!("{err}");
+            }
+        }
+    }
+
+    /// The trap `tid` was reported hitting, when it still has to execute the instruction the
+    /// trap replaced. A thread found just past a trap may also have executed it already.
+    fn hit_trap(&self, tid: Pid) -> Option<u64> {
+        if !self.inferior.threads.get(tid)?.hit_trap {
+            return None;
+        }
+        self.trap_before(self.get_pc(tid).ok()?)
+    }
+
+    /// The enabled trap a thread reporting SIGTRAP at `pc` hit, the program counter having
+    /// moved past it on some architectures
+    fn trap_before(&self, pc: u64) -> Option<u64> {
+        // a thread that jumped to address 0 hit nothing
+        let trap_addr = pc.checked_sub(self.arch.trap_pc_adjustment())?;
+        self.inferior
+            .breakpoints
+            .get(&trap_addr)
+            .is_some_and(|bp| bp.enabled)
+            .then_some(trap_addr)
     }
 
-    fn get_pc(&self) -> u64 {
-        let Ok(pc) = get_register_value(self.pid, Reg::Rip) else {
-            eprintln!("Cannot get the program counter");
-            std::process::exit(-1);
+    /// Puts the program counter of a thread that just hit a trap, laid out in `regs`, back at
+    /// the trap, where the thread really is for the user
+    fn rewind_trap(&self, tid: Pid, regs: &mut [u64]) {
+        let Some(index) = self
+            .arch
+            .registers()
+            .iter()
+            .find(|desc| desc.r == self.arch.pc_reg())
+            .map(|desc| desc.index)
+        else {
+            return;
         };
-        pc
+        if let (Some(pc), Some(trap_addr)) = (regs.get_mut(index), self.hit_trap(tid)) {
+            *pc = trap_addr;
+        }
+    }
+
+    /// Cleans `pid` up and lets each of its threads go
+    fn detach_threads(&mut self, pid: Pid) {
+        self.remove_traps(pid);
+        for tid in self.inferior.threads.tids() {
+            // a stop still on its way would freeze the process once we are gone
+            if self
+                .inferior
+                .threads
+                .get(tid)
+                .is_some_and(|t| t.stop_expected)
+            {
+                let _ = self.live.resume(tid, None);
+                while let Ok(status) = self.live.wait(Some(tid), Some(WaitPidFlag::__WALL)) {
+                    if is_stop_request(&status) || !matches!(status, WaitStatus::Stopped(..)) {
+                        break;
+                    }
+                    let _ = self.live.resume(tid, None);
+                }
+            }
+            let signal = self
+                .inferior
+                .threads
+                .get(tid)
+                .and_then(|thread| thread.pending_signal);
+            if let Err(err) = self.live.detach(tid, signal) {
+                eprintln!("Cannot detach from thread {tid}: {}", err.desc());
+            }
+        }
+    }
+
+    pub fn kill_debuggee(&mut self) -> Result<(), DebuggerError> {
+        let pid = self.inferior.pid.ok_or(DebuggerError::NoThread)?;
+        let _ = self.live.kill(pid, Signal::SIGKILL);
+        // every thread reports its death, the leader last, after stopping on its way out
+        for tid in self.inferior.threads.tids().into_iter().rev() {
+            while let Ok(status) = self.live.wait(Some(tid), Some(WaitPidFlag::__WALL)) {
+                match status {
+                    WaitStatus::Exited(..) | WaitStatus::Signaled(..) => break,
+                    WaitStatus::PtraceEvent(..) => {
+                        let _ = self.live.resume(tid, None);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        println!("Process {pid} killed");
+        self.forget_debuggee();
+        Ok(())
+    }
+
+    /// Drops everything tied to the process that just ended, breakpoints are kept for the next run
+    fn forget_debuggee(&mut self) {
+        self.inferior.forget();
+        self.last_stop = None;
+        // the processes of the other inferiors may share the terminal
+        if self.inferiors.iter().all(|inferior| inferior.pid.is_none()) {
+            self.pty = None;
+        }
+    }
+
+    fn next_inferior_id(&self) -> usize {
+        self.inferiors
+            .iter()
+            .map(|inferior| inferior.id)
+            .fold(self.inferior.id, usize::max)
+            + 1
     }
 
-    fn set_pc(&self, pc: u64) {
-        let Ok(_) = set_register_value(self.pid, Reg::Rip, pc) else {
-            eprintln!("Cannot move the program counter");
-            std::process::exit(-1);
+    /// Runs `f` with the inferior at `index` of the background ones made the current one
+    fn in_inferior<R>(&mut self, index: usize, f: impl FnOnce(&mut Self) -> R) -> R {
+        std::mem::swap(&mut self.inferior, &mut self.inferiors[index]);
+        let result = f(self);
+        std::mem::swap(&mut self.inferior, &mut self.inferiors[index]);
+        result
+    }
+
+    /// Ends the debuggee: a process that was attached to is let go, one started is killed
+    fn end_debuggee(&mut self) {
+        if self.inferior.pid.is_none() {
+            return;
+        }
+        // a process we attached to was running before us and keeps running after
+        let ended = if self.inferior.attached {
+            self.detach()
+        } else {
+            self.kill_debuggee()
         };
+        if let Err(err) = ended {
+            eprintln!("{err}");
+        }
+    }
+
+    /// The traced process, `None` before the program is run or once it has ended
+    pub fn pid(&self) -> Option<Pid> {
+        self.inferior.pid
+    }
+
+    /// The thread the commands act on
+    pub fn current_thread(&self) -> Option<Pid> {
+        self.inferior.current_thread
+    }
+
+    /// The threads of the debuggee, in creation order
+    pub fn threads(&self) -> Vec<Pid> {
+        self.inferior.threads.tids()
+    }
+
+    /// The addresses of the breakpoints inserted in the debuggee
+    pub fn breakpoints(&self) -> Vec<u64> {
+        let mut addresses: Vec<u64> = self
+            .inferior
+            .breakpoints
+            .keys()
+            .copied()
+            .filter(|&address| self.inferior.solib_event != Some(address))
+            .collect();
+        addresses.sort();
+        addresses
+    }
+
+    /// The thread that stopped last and why, `None` while the debuggee runs or once it has
+    /// ended
+    pub fn last_stop(&self) -> Option<&(Pid, StopReason)> {
+        self.last_stop.as_ref()
+    }
+
+    /// Resumes every thread until one of them stops, and gives that stop (all-stop mode)
+    pub fn resume(&mut self) -> Result<Option<&(Pid, StopReason)>, DebuggerError> {
+        self.last_stop = None;
+        self.continue_execution(true)?;
+        Ok(self.last_stop())
+    }
+
+    /// Reads a register of the selected thread. The program counter of a thread that hit a
+    /// breakpoint is the address of the breakpoint.
+    pub fn read_register(&self, r: Reg) -> Result<u64, DebuggerError> {
+        let tid = self.inspected_thread()?;
+        self.shown_register(tid, r)
+    }
+
+    /// Changes a register of the selected thread
+    pub fn write_register(&self, r: Reg, value: u64) -> Result<(), DebuggerError> {
+        let tid = self.inspected_thread()?;
+        self.set_register(tid, r, value)
+    }
+
+    /// Reads `len` bytes of memory through the selected thread, failing when part of them
+    /// can't be read
+    pub fn read_memory(&self, address: u64, len: usize) -> Result<Vec<u8>, DebuggerError> {
+        let tid = self.inspected_thread()?;
+        let bytes = self.target().read_memory(tid, address, len);
+        if bytes.len() < len {
+            return Err(DebuggerError::UnreadableMemory(
+                address + bytes.len() as u64,
+            ));
+        }
+        Ok(bytes)
+    }
+
+    /// Writes `bytes` in memory through the selected thread
+    pub fn write_memory(&self, address: u64, bytes: &[u8]) -> Result<(), DebuggerError> {
+        let tid = self.inspected_thread()?;
+        self.target()
+            .write_memory(tid, address, bytes)
+            .map_err(|errno| DebuggerError::Memory { address, errno })
+    }
+
+    /// Resumes the debuggee. In all-stop mode every thread runs until one of them stops; in
+    /// non-stop mode only the selected thread, or every stopped one with `all_threads`, is
+    /// resumed and the prompt comes back at once.
+    pub fn continue_execution(&mut self, all_threads: bool) -> Result<(), DebuggerError> {
+        if self.inferior.pid.is_none() {
+            return Err(DebuggerError::NoThread);
+        }
+        if !self.non_stop {
+            self.resume_and_wait();
+            return Ok(());
+        }
+
+        let tids: Vec<Pid> = self
+            .inferior
+            .threads
+            .iter()
+            .filter(|thread| thread.state == ThreadState::Stopped)
+            .filter(|thread| all_threads || self.inferior.current_thread == Some(thread.tid))
+            .map(|thread| thread.tid)
+            .collect();
+        if tids.is_empty() {
+            return Err(DebuggerError::NoStoppedThread);
+        }
+        self.resume_threads(&tids);
+        println!("Continuing.");
+        Ok(())
+    }
+
+    /// Stops the selected thread, or every running one with `all_threads` (non-stop mode)
+    pub fn interrupt(&mut self, all_threads: bool) -> Result<(), DebuggerError> {
+        let pid = self.inferior.pid.ok_or(DebuggerError::NoThread)?;
+        let tids: Vec<Pid> = self
+            .inferior
+            .threads
+            .running()
+            .into_iter()
+            .filter(|&tid| all_threads || self.inferior.current_thread == Some(tid))
+            .collect();
+        if tids.is_empty() {
+            return Err(DebuggerError::NoRunningThread);
+        }
+        self.stop_threads(pid, &tids);
+        for tid in tids {
+            if let Some(thread) = self.inferior.threads.get(tid) {
+                println!(
+                    "Thread {} (LWP {tid}) stopped at {}",
+                    thread.id,
+                    self.describe_pc(tid)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Resumes every thread then reports the first one to stop, the others being
+    /// stopped as well (all-stop)
+    fn resume_and_wait(&mut self) {
+        self.last_stop = None;
+        self.resume_threads(&self.inferior.threads.tids());
+        if let Some((tid, reason)) = self.wait_for_event() {
+            self.report_stop(tid, reason);
+        }
+    }
+
+    /// Tells why `tid` stopped and selects it
+    fn report_stop(&mut self, tid: Pid, reason: StopReason) {
+        self.last_stop = Some((tid, reason.clone()));
+        if self.inferior.current_thread != Some(tid) {
+            self.inferior.current_thread = Some(tid);
+            if let Some(thread) = self.inferior.threads.get(tid) {
+                println!("[Switching to thread {} (LWP {tid})]", thread.id);
+            }
+        }
+        let signal = match reason {
+            StopReason::Signal(signal) => signal,
+            StopReason::Syscall {
+                catchpoint,
+                number,
+                entry,
+            } => {
+                self.print_syscall(tid, catchpoint, number, entry);
+                println!("Stopped at {}", self.describe_pc(tid));
+                return;
+            }
+            StopReason::Event { catchpoint, event } => {
+                println!("Catchpoint {catchpoint} ({})", event.describe());
+                println!("Stopped at {}", self.describe_pc(tid));
+                return;
+            }
+        };
+        if !matches!(signal, Signal::SIGTRAP | Signal::SIGSTOP) {
+            self.print_signal(tid, signal);
+        }
+        match self.hit_trap(tid) {
+            Some(trap_addr) if signal == Signal::SIGTRAP => {
+                println!("Breakpoint hit at {}", self.describe_address(trap_addr))
+            }
+            _ => println!("Stopped at {}", self.describe_pc(tid)),
+        }
+    }
+
+    fn print_syscall(&self, tid: Pid, catchpoint: usize, number: u64, entry: bool) {
+        let value_of = |reg| self.register_value(tid, reg).unwrap_or_default();
+        if entry {
+            let args: Vec<u64> = self
+                .arch
+                .syscall_arg_regs()
+                .iter()
+                .map(|&reg| value_of(reg))
+                .collect();
+            println!(
+                "Catchpoint {catchpoint} (call to syscall {})",
+                catchpoint::describe_syscall_call(self.arch, number, &args)
+            );
+        } else {
+            println!(
+                "Catchpoint {catchpoint} (returned from syscall {})",
+                catchpoint::describe_syscall_return(
+                    self.arch,
+                    number,
+                    value_of(self.arch.syscall_return_reg())
+                )
+            );
+        }
+    }
+
+    fn print_signal(&self, tid: Pid, signal: Signal) {
+        match self.inferior.threads.get(tid) {
+            Some(thread) if self.inferior.threads.tids().len() > 1 => {
+                println!("Thread {} (LWP {tid}) received signal {signal}", thread.id)
+            }
+            _ => println!("Program received signal {signal}"),
+        }
+    }
+
+    /// Steps the threads sitting on a trap over it, then lets them run, delivering the
+    /// signals they received
+    fn resume_threads(&mut self, tids: &[Pid]) {
+        for &tid in tids {
+            if let Err(err) = self.step_over_breakpoint(tid) {
+                eprintln!("{err}");
+            }
+        }
+        for &tid in tids {
+            let Some(signal) = self.inferior.threads.get(tid).map(|t| t.pending_signal) else {
+                continue;
+            };
+            if self.resume_thread(tid, signal).is_ok() {
+                let Some(thread) = self.inferior.threads.get_mut(tid) else {
+                    eprintln!("{}", DebuggerError::thread_gone(tid));
+                    continue;
+                };
+                thread.pending_signal = None;
+                thread.at_syscall_stop = false;
+                thread.state = ThreadState::Running;
+            }
+        }
+    }
+
+    /// Resumes `tid`, stopping at its syscalls too while they are traced or a syscall
+    /// catchpoint is set
+    fn resume_thread(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()> {
+        if self.syscall_trace.is_some()
+            || self
+                .catchpoints
+                .iter()
+                .any(|catchpoint| matches!(catchpoint.kind, CatchKind::Syscall(_)))
+        {
+            self.live.resume_to_syscall(tid, signal)
+        } else {
+            self.live.resume(tid, signal)
+        }
+    }
+
+    /// The number of the syscall `tid` stopped at and whether it is entering it. The syscall
+    /// is logged when syscalls are traced.
+    fn syscall_stopped(&mut self, tid: Pid) -> (u64, bool) {
+        let entry = self.at_syscall_entry(tid);
+        let skipped = match self.inferior.threads.get_mut(tid) {
+            // the syscall skipped before is over
+            Some(thread) if entry => {
+                thread.skipped_syscall = None;
+                None
+            }
+            Some(thread) => thread.skipped_syscall,
+            None => None,
+        };
+        if let Some((_, value)) = skipped {
+            // the kernel returned ENOSYS for the -1 the number was replaced by
+            let _ = self.set_register(tid, self.arch.syscall_return_reg(), value);
+        }
+        let number = self.syscall_number(tid);
+        self.trace_syscall(tid, number, entry);
+        (number, entry)
+    }
+
+    /// The syscall `tid` is stopped at, once skipped its number register only holds -1
+    fn syscall_number(&self, tid: Pid) -> u64 {
+        let skipped = self
+            .inferior
+            .threads
+            .get(tid)
+            .and_then(|thread| thread.skipped_syscall);
+        match skipped {
+            Some((number, _)) => number,
+            None => self
+                .register_value(tid, self.arch.syscall_number_reg())
+                .unwrap_or(u64::MAX),
+        }
+    }
+
+    /// Logs a syscall when it returns, with the arguments it read decoded on entry
+    fn trace_syscall(&mut self, tid: Pid, number: u64, entry: bool) {
+        let name = catchpoint::syscall_name(self.arch, number);
+        if !self
+            .syscall_trace
+            .as_ref()
+            .is_some_and(|filter| filter.matches(&name))
+        {
+            return;
+        }
+        let prefix = if self.inferior.threads.tids().len() > 1 {
+            format!("[pid {tid}] ")
+        } else {
+            String::new()
+        };
+        let value_of = |reg| self.register_value(tid, reg).unwrap_or_default();
+        if entry {
+            let args: Vec<u64> = self
+                .arch
+                .syscall_arg_regs()
+                .iter()
+                .map(|&reg| value_of(reg))
+                .collect();
+            let call = TracedCall::enter(&self.live, tid, name, &args);
+            if call.never_returns() {
+                println!("{prefix}{}", call.describe_unfinished());
+            }
+            if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                thread.traced_call = Some(call);
+            }
+            return;
+        }
+        let value = value_of(self.arch.syscall_return_reg());
+        match self
+            .inferior
+            .threads
+            .get_mut(tid)
+            .and_then(|thread| thread.traced_call.take())
+        {
+            Some(call) => println!("{prefix}{}", call.describe_return(&self.live, tid, value)),
+            // entered before the trace started
+            None => println!(
+                "{prefix}<... {name} resumed> = {}",
+                strace::describe_return_value(&name, value)
+            ),
+        }
+    }
+
+    /// Whether `tid`, stopped at a syscall, is entering it
+    fn at_syscall_entry(&mut self, tid: Pid) -> bool {
+        let Some(thread) = self.inferior.threads.get_mut(tid) else {
+            return true;
+        };
+        // without the kernel's help, entries and exits alternate
+        thread.in_syscall = self.live.syscall_entry(tid).unwrap_or(!thread.in_syscall);
+        thread.in_syscall
+    }
+
+    /// Waits for a thread to stop for a reason the user cares about. `None` when the process is gone.
+    fn wait_for_event(&mut self) -> Option<(Pid, StopReason)> {
+        // the debuggee changes when a fork is followed to the child
+        while let Some(pid) = self.inferior.pid {
+            let status = match self.wait_for_stop() {
+                Ok(status) => status,
+                Err(_) => {
+                    eprintln!("Lost the debuggee process {pid}");
+                    self.forget_debuggee();
+                    return None;
+                }
+            };
+            if let ControlFlow::Break(stop) = self.handle_status(pid, status) {
+                return stop;
+            }
+        }
+        None
+    }
+
+    /// Reports the stops of the threads running in non-stop mode, called while the user types
+    fn poll_events(&mut self, reader: &Interface<DefaultTerminal>) {
+        while let Ok(status) = self
+            .live
+            .wait(None, Some(WaitPidFlag::WNOHANG | WaitPidFlag::__WALL))
+        {
+            let (Some(pid), false) = (self.inferior.pid, status == WaitStatus::StillAlive) else {
+                break;
+            };
+            // the messages go above the prompt being edited
+            let _writer = console::terminal_has_width().then(|| reader.lock_writer_erase());
+            if let ControlFlow::Break(Some((tid, reason))) = self.handle_status(pid, status) {
+                self.report_stop(tid, reason);
+            }
+        }
+    }
+
+    /// Deals with what a thread reported. Thread creations and exits and library changes are
+    /// handled here and the waiting goes on; `Break` carries the stop to tell the user about,
+    /// `None` once the process is gone. In all-stop mode the other threads are stopped too.
+    fn handle_status(
+        &mut self,
+        pid: Pid,
+        status: WaitStatus,
+    ) -> ControlFlow<Option<(Pid, StopReason)>> {
+        if let Some(index) = status.pid().and_then(|tid| {
+            self.inferiors
+                .iter()
+                .position(|inferior| inferior.threads.get(tid).is_some())
+        }) {
+            self.background_event(index, status);
+            return ControlFlow::Continue(());
+        }
+        match status {
+            WaitStatus::Exited(tid, code) if tid == pid => {
+                println!("Process {pid} exited with code {code}");
+                self.forget_debuggee();
+                ControlFlow::Break(None)
+            }
+            WaitStatus::Signaled(tid, signal, _) if tid == pid => {
+                println!("Process {pid} terminated by {signal}");
+                self.forget_debuggee();
+                ControlFlow::Break(None)
+            }
+            WaitStatus::Exited(tid, _) | WaitStatus::Signaled(tid, ..) => {
+                self.thread_exited(pid, tid);
+                ControlFlow::Continue(())
+            }
+            WaitStatus::PtraceEvent(tid, _, event) if event == Event::PTRACE_EVENT_CLONE as i32 => {
+                match self.thread_created(tid) {
+                    Some(new) => self.catch_event(tid, ProcessEvent::Clone(new)),
+                    None => {
+                        let _ = self.resume_thread(tid, None);
+                        ControlFlow::Continue(())
+                    }
+                }
+            }
+            WaitStatus::PtraceEvent(tid, _, event)
+                if event == Event::PTRACE_EVENT_FORK as i32
+                    || event == Event::PTRACE_EVENT_VFORK as i32 =>
+            {
+                let vfork = event == Event::PTRACE_EVENT_VFORK as i32;
+                match self.process_forked(pid, tid, vfork) {
+                    Some((tid, child)) if vfork => {
+                        self.catch_event(tid, ProcessEvent::Vfork(child))
+                    }
+                    Some((tid, child)) => self.catch_event(tid, ProcessEvent::Fork(child)),
+                    None => {
+                        let _ = self.resume_thread(tid, None);
+                        ControlFlow::Continue(())
+                    }
+                }
+            }
+            WaitStatus::PtraceEvent(tid, _, event)
+                if event == Event::PTRACE_EVENT_VFORK_DONE as i32 =>
+            {
+                // the vforked child no longer shares the memory, the traps go back in
+                for bp in self
+                    .inferior
+                    .breakpoints
+                    .values_mut()
+                    .filter(|bp| !bp.enabled)
+                {
+                    if let Err(err) = bp.enable(tid) {
+                        eprintln!("{err}");
+                    }
+                }
+                let _ = self.resume_thread(tid, None);
+                ControlFlow::Continue(())
+            }
+            WaitStatus::PtraceSyscall(tid) => {
+                let (number, entry) = self.syscall_stopped(tid);
+                match self
+                    .catchpoints
+                    .iter()
+                    .find(|catchpoint| catchpoint.catches_syscall(number))
+                {
+                    Some(catchpoint) => {
+                        let id = catchpoint.id;
+                        if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                            thread.at_syscall_stop = true;
+                        }
+                        let reason = StopReason::Syscall {
+                            catchpoint: id,
+                            number,
+                            entry,
+                        };
+                        self.stop_on_event(pid, tid, reason)
+                    }
+                    None => {
+                        let _ = self.resume_thread(tid, None);
+                        ControlFlow::Continue(())
+                    }
+                }
+            }
+            WaitStatus::PtraceEvent(_, _, event) if event == Event::PTRACE_EVENT_EXEC as i32 => {
+                let program = self.process_executed(pid);
+                self.catch_event(pid, ProcessEvent::Exec(program))
+            }
+            WaitStatus::PtraceEvent(tid, _, event) if event == Event::PTRACE_EVENT_EXIT as i32 => {
+                // every thread reports its exit, the process exits with its leader
+                match self.live.event_message(tid) {
+                    Ok(status) if tid == pid => {
+                        self.catch_event(tid, ProcessEvent::Exit(status as i32))
+                    }
+                    _ => {
+                        let _ = self.resume_thread(tid, None);
+                        ControlFlow::Continue(())
+                    }
+                }
+            }
+            status if is_stop_request(&status) => {
+                // the initial stop of a new thread, or one the debugger asked for earlier
+                let tid = status.pid().unwrap();
+                if self.inferior.threads.get(tid).is_none()
+                    && !self.live.tasks(pid).unwrap_or_default().contains(&tid)
+                {
+                    // a forked child, its parent hasn't reported the fork yet
+                    self.fork_stops.push(tid);
+                    return ControlFlow::Continue(());
+                }
+                let expected = self
+                    .inferior
+                    .threads
+                    .get(tid)
+                    .is_none_or(|t| t.stop_expected);
+                if self.inferior.threads.get(tid).is_none() {
+                    self.add_thread(tid, ThreadState::Running);
+                }
+                if expected {
+                    if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                        thread.stop_expected = false;
+                    }
+                    let _ = self.resume_thread(tid, None);
+                    return ControlFlow::Continue(());
+                }
+                self.stop_on_event(pid, tid, StopReason::Signal(Signal::SIGSTOP))
+            }
+            WaitStatus::Stopped(tid, signal) => {
+                if self.inferior.threads.get(tid).is_none() {
+                    self.add_thread(tid, ThreadState::Running);
+                }
+                // a thread whose registers can't be read is about to report its end
+                let trap_addr = match signal {
+                    Signal::SIGTRAP => self.get_pc(tid).ok().and_then(|pc| self.trap_before(pc)),
+                    _ => None,
+                };
+                if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                    thread.hit_trap = trap_addr.is_some();
+                }
+                if trap_addr.is_some() && self.inferior.solib_event == trap_addr {
+                    // library changes are handled without bothering the user
+                    self.mark_stopped(tid);
+                    if self.non_stop {
+                        self.update_shared_libraries(tid);
+                        self.resume_threads(&[tid]);
+                    } else {
+                        self.stop_all_threads(pid);
+                        self.update_shared_libraries(tid);
+                        self.resume_threads(&self.inferior.threads.tids());
+                    }
+                    return ControlFlow::Continue(());
+                }
+
+                let policy = self.signals.get(signal);
+                if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                    thread.pending_signal = policy.pass.then_some(signal);
+                }
+                if !policy.stop {
+                    if policy.print {
+                        self.print_signal(tid, signal);
+                    }
+                    let pending = self
+                        .inferior
+                        .threads
+                        .get_mut(tid)
+                        .and_then(|thread| thread.pending_signal.take());
+                    let _ = self.resume_thread(tid, pending);
+                    return ControlFlow::Continue(());
+                }
+                self.stop_on_event(pid, tid, StopReason::Signal(signal))
+            }
+            other => {
+                if let Some(tid) = other.pid() {
+                    let _ = self.resume_thread(tid, None);
+                }
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// Stops on an event a catchpoint catches, otherwise lets `tid`, which reported it, go on
+    fn catch_event(
+        &mut self,
+        tid: Pid,
+        event: ProcessEvent,
+    ) -> ControlFlow<Option<(Pid, StopReason)>> {
+        let caught = self
+            .catchpoints
+            .iter()
+            .find(|catchpoint| catchpoint.catches_event(&event));
+        match (caught, self.inferior.pid) {
+            (Some(catchpoint), Some(pid)) => {
+                let reason = StopReason::Event {
+                    catchpoint: catchpoint.id,
+                    event,
+                };
+                self.stop_on_event(pid, tid, reason)
+            }
+            // a followed child or a process that exec'd was marked stopped
+            _ if self
+                .inferior
+                .threads
+                .get(tid)
+                .is_some_and(|thread| thread.state == ThreadState::Stopped) =>
+            {
+                self.resume_threads(&[tid]);
+                ControlFlow::Continue(())
+            }
+            _ => {
+                let _ = self.resume_thread(tid, None);
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    /// The processes of the background inferiors are stopped, only their end can be reported
+    fn background_event(&mut self, index: usize, status: WaitStatus) {
+        let (WaitStatus::Exited(tid, ..) | WaitStatus::Signaled(tid, ..)) = status else {
+            return;
+        };
+        let inferior = &mut self.inferiors[index];
+        inferior.threads.remove(tid);
+        if inferior.pid == Some(tid) {
+            println!(
+                "[Inferior {} ({}) exited]",
+                inferior.id,
+                inferior.describe()
+            );
+            inferior.forget();
+        }
+    }
+
+    fn stop_on_event(
+        &mut self,
+        pid: Pid,
+        tid: Pid,
+        reason: StopReason,
+    ) -> ControlFlow<Option<(Pid, StopReason)>> {
+        self.mark_stopped(tid);
+        if !self.non_stop {
+            self.stop_all_threads(pid);
+        }
+        ControlFlow::Break(Some((tid, reason)))
+    }
+
+    fn mark_stopped(&mut self, tid: Pid) {
+        if let Some(thread) = self.inferior.threads.get_mut(tid) {
+            thread.state = ThreadState::Stopped;
+        }
+    }
+
+    /// Registers a thread the first time one of its events shows up
+    fn add_thread(&mut self, tid: Pid, state: ThreadState) {
+        if self.inferior.threads.get(tid).is_some() {
+            return;
+        }
+        let id = self.inferior.threads.add(tid, state).id;
+        if id > 1 {
+            println!("[New Thread {id} (LWP {tid})]");
+        }
+    }
+
+    /// `parent` cloned a thread, which starts with a stop of its own
+    fn thread_created(&mut self, parent: Pid) -> Option<Pid> {
+        let tid = self.live.event_message(parent).ok()?;
+        let tid = Pid::from_raw(tid as i32);
+        // its first stop may already have been seen
+        if self.inferior.threads.get(tid).is_none() {
+            self.add_thread(tid, ThreadState::Running);
+            if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                thread.stop_expected = true;
+            }
+        }
+        Some(tid)
+    }
+
+    fn thread_exited(&mut self, pid: Pid, tid: Pid) {
+        if let Some(thread) = self.inferior.threads.remove(tid) {
+            println!("[Thread {} (LWP {tid}) exited]", thread.id);
+        }
+        if self.inferior.current_thread == Some(tid) {
+            self.inferior.current_thread = Some(pid);
+        }
+    }
+
+    fn stop_all_threads(&mut self, pid: Pid) {
+        self.stop_threads(pid, &self.inferior.threads.running());
+    }
+
+    /// Stops the given running threads. The events they report meanwhile are dropped, a thread
+    /// that hit a trap is moved back onto it so that it hits it again once resumed.
+    fn stop_threads(&mut self, pid: Pid, tids: &[Pid]) {
+        for &tid in tids {
+            let Some(thread) = self.inferior.threads.get_mut(tid) else {
+                continue;
+            };
+            if thread.state != ThreadState::Running || thread.stop_expected {
+                continue;
+            }
+            thread.stop_expected = if self.inferior.attached {
+                // seized threads can be stopped without a signal
+                self.live.interrupt(tid).is_ok()
+            } else {
+                self.live.kill_thread(pid, tid, Signal::SIGSTOP).is_ok()
+            };
+        }
+
+        // new threads showing up meanwhile are stopped as well
+        let mut waited: Vec<Pid> = tids.to_vec();
+        while let Some(tid) = self
+            .inferior
+            .threads
+            .running()
+            .into_iter()
+            .find(|tid| waited.contains(tid))
+        {
+            let status = match self.live.wait(Some(tid), Some(WaitPidFlag::__WALL)) {
+                Ok(status) => status,
+                Err(_) => {
+                    self.inferior.threads.remove(tid);
+                    continue;
+                }
+            };
+            match status {
+                status if is_stop_request(&status) => {
+                    let Some(thread) = self.inferior.threads.get_mut(tid) else {
+                        eprintln!("{}", DebuggerError::thread_gone(tid));
+                        continue;
+                    };
+                    thread.state = ThreadState::Stopped;
+                    thread.stop_expected = false;
+                }
+                WaitStatus::PtraceEvent(_, _, event)
+                    if event == Event::PTRACE_EVENT_CLONE as i32 =>
+                {
+                    self.mark_stopped(tid);
+                    let known = self.inferior.threads.tids();
+                    self.thread_created(tid);
+                    waited.extend(
+                        self.inferior
+                            .threads
+                            .tids()
+                            .into_iter()
+                            .filter(|t| !known.contains(t)),
+                    );
+                }
+                WaitStatus::PtraceEvent(_, _, event)
+                    if event == Event::PTRACE_EVENT_FORK as i32
+                        || event == Event::PTRACE_EVENT_VFORK as i32 =>
+                {
+                    // too late to switch to the child, it is handled as if the parent was followed
+                    self.mark_stopped(tid);
+                    if let Some(child) = self.fork_child(tid) {
+                        self.leave_child(child, event == Event::PTRACE_EVENT_VFORK as i32);
+                    }
+                }
+                WaitStatus::Stopped(_, signal) if signal != Signal::SIGTRAP => {
+                    // kept for when the thread is resumed
+                    self.mark_stopped(tid);
+                    let policy = self.signals.get(signal);
+                    if policy.print {
+                        self.print_signal(tid, signal);
+                    }
+                    if let Some(thread) = self.inferior.threads.get_mut(tid) {
+                        thread.pending_signal = policy.pass.then_some(signal);
+                    }
+                }
+                WaitStatus::Stopped(..) => {
+                    self.mark_stopped(tid);
+                    let Ok(pc) = self.get_pc(tid) else {
+                        continue;
+                    };
+                    if self.arch.trap_pc_adjustment() != 0 {
+                        if let Some(trap_addr) = self.trap_before(pc) {
+                            if let Err(err) = self.set_pc(tid, trap_addr) {
+                                eprintln!("{err}");
+                            }
+                        }
+                    }
+                }
+                WaitStatus::PtraceSyscall(_) => {
+                    // it is not reported, but the next stop of the syscall must be told apart
+                    self.mark_stopped(tid);
+                    self.syscall_stopped(tid);
+                }
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => self.thread_exited(pid, tid),
+                _ => self.mark_stopped(tid),
+            }
+        }
+    }
+
+    /// `tid` forked: the process not followed is detached once the traps it inherited are
+    /// taken out or, with detach-on-fork off, becomes a new inferior. Gives the stopped thread
+    /// of the process followed, to be resumed, and the child.
+    fn process_forked(&mut self, pid: Pid, tid: Pid, vfork: bool) -> Option<(Pid, Pid)> {
+        let child = self.fork_child(tid)?;
+        match self.follow_fork_mode {
+            FollowForkMode::Parent => {
+                self.leave_child(child, vfork);
+                Some((tid, child))
+            }
+            FollowForkMode::Child => {
+                self.follow_child(pid, tid, child, vfork);
+                Some((child, child))
+            }
+        }
+    }
+
+    /// The child `tid` just forked, once it has reported its first stop
+    fn fork_child(&mut self, tid: Pid) -> Option<Pid> {
+        let child = Pid::from_raw(self.live.event_message(tid).ok()? as i32);
+        if let Some(index) = self.fork_stops.iter().position(|&pid| pid == child) {
+            self.fork_stops.remove(index);
+            return Some(child);
+        }
+        loop {
+            match self.live.wait(Some(child), Some(WaitPidFlag::__WALL)) {
+                Ok(status) if is_stop_request(&status) => return Some(child),
+                Ok(WaitStatus::Stopped(..) | WaitStatus::PtraceEvent(..)) => {}
+                _ => return None,
+            }
+        }
+    }
+
+    /// Lets the child of a fork go once the traps it inherited are taken out, or makes it a
+    /// new inferior with detach-on-fork off
+    fn leave_child(&mut self, child: Pid, vfork: bool) {
+        let kind = if vfork { "vfork" } else { "fork" };
+        if vfork {
+            // the memory is shared until the child execs or exits, the parent reports it
+            // with PTRACE_EVENT_VFORK_DONE and its traps are inserted again
+            for bp in self
+                .inferior
+                .breakpoints
+                .values_mut()
+                .filter(|bp| bp.enabled)
+            {
+                if let Err(err) = bp.disable(child) {
+                    eprintln!("{err}");
+                }
+            }
+        }
+        if !self.detach_on_fork {
+            let inferior = self.inferior.forked(self.next_inferior_id(), child);
+            println!("[New inferior {} ({})]", inferior.id, inferior.describe());
+            self.inferiors.push(inferior);
+            return;
+        }
+
+        if !vfork {
+            let mut traps = self.inferior.breakpoints.clone();
+            for bp in traps.values_mut().filter(|bp| bp.enabled) {
+                if let Err(err) = bp.disable(child) {
+                    eprintln!("{err}");
+                }
+            }
+        }
+        println!("[Detaching after {kind} from child process {child}]");
+        let _ = self.live.detach(child, None);
+    }
+
+    /// Makes the child of a fork the debuggee. The parent is let go, or stays stopped in the
+    /// background with detach-on-fork off.
+    fn follow_child(&mut self, pid: Pid, tid: Pid, child: Pid, vfork: bool) {
+        let kind = if vfork { "vfork" } else { "fork" };
+        println!("[Attaching after process {pid} {kind} to child process {child}]");
+        self.mark_stopped(tid);
+        if vfork {
+            // the child sees the same memory, its traps are inserted again once it execs
+            for bp in self
+                .inferior
+                .breakpoints
+                .values_mut()
+                .filter(|bp| bp.enabled)
+            {
+                if let Err(err) = bp.disable(tid) {
+                    eprintln!("{err}");
+                }
+            }
+        }
+        if self.detach_on_fork {
+            // the child takes the place of its parent, under the same number
+            let inferior = self.inferior.forked(self.inferior.id, child);
+            self.detach_threads(pid);
+            println!("[Detaching after {kind} from parent process {pid}]");
+            self.inferior = inferior;
+        } else {
+            let inferior = self.inferior.forked(self.next_inferior_id(), child);
+            self.stop_all_threads(pid);
+            let parent = std::mem::replace(&mut self.inferior, inferior);
+            self.inferiors.push(parent);
+            println!(
+                "[New inferior {} ({})]",
+                self.inferior.id,
+                self.inferior.describe()
+            );
+        }
+    }
+
+    /// The debuggee replaced its program: the other threads are gone and the symbols and
+    /// breakpoints are those of the new program. Gives the path of the program.
+    fn process_executed(&mut self, pid: Pid) -> String {
+        // the thread that called exec took the process id
+        for tid in self.inferior.threads.tids() {
+            if tid != pid {
+                self.inferior.threads.remove(tid);
+            }
+        }
+        if self.inferior.threads.get(pid).is_none() {
+            self.inferior.threads.add(pid, ThreadState::Stopped);
+        }
+        self.mark_stopped(pid);
+        self.inferior.current_thread = Some(pid);
+        // the next run starts the new program, and the messages name it
+        match self.live.executable(pid) {
+            Ok(exe) => self.launch.program = exe.to_string_lossy().into_owned(),
+            Err(err) => eprintln!("Cannot find the new program of process {pid}: {err}"),
+        }
+        let program = self.launch.program.clone();
+        println!("process {pid} is executing new program: {program}");
+
+        self.load_program_symbols(Some(pid));
+        self.insert_breakpoints(pid);
+        self.watch_shared_libraries(pid);
+        program
+    }
+
+    /// Waits for any thread to report, passing what is typed meanwhile to the debuggee's terminal
+    fn wait_for_stop(&self) -> Result<WaitStatus, nix::Error> {
+        let Some(pty) = &self.pty else {
+            return self.live.wait(None, Some(WaitPidFlag::__WALL));
+        };
+
+        loop {
+            match self
+                .live
+                .wait(None, Some(WaitPidFlag::WNOHANG | WaitPidFlag::__WALL))?
+            {
+                WaitStatus::StillAlive => console::forward_input(pty, 50),
+                status => return Ok(status),
+            }
+        }
+    }
+
+    #[allow(clippy::needless_return)]
+    pub fn handle_command(&mut self, command: &str) {
+        let command_line: Vec<String> = command
+            .split_whitespace()
+            .map(|el| el.to_string())
+            .collect();
+
+        let Some(command) = command_line.first() else {
+            println!("{NO_COMMAND_PROVIDED_ERROR_MSG}");
+            return;
+        };
+        // x/4xg: the output format is glued to the command name
+        let (command, format) = match command.split_once('/') {
+            Some((command, format)) => (command, Some(format)),
+            None => (command.as_str(), None),
+        };
+
+        let arg1 = command_line.get(1);
+        let arg2 = command_line.get(2);
+        let arg3 = command_line.get(3);
+
+        if let Ok(ecommand) = Command::from_str(command) {
+            match ecommand {
+                Command::RUN => {
+                    if let ControlFlow::Break(_) = self.run_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::RESTART => {
+                    if let Err(err) = self.start_debuggee() {
+                        eprintln!("{err}");
+                    }
+                }
+                Command::KILL => {
+                    if let Err(err) = self.kill_debuggee() {
+                        eprintln!("{err}");
+                    }
+                }
+                Command::ATTACH => {
+                    if let ControlFlow::Break(_) = self.attach_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::DETACH => {
+                    if let Err(err) = self.detach() {
+                        eprintln!("{err}");
+                    }
+                }
+                Command::CONTINUE => {
+                    if let Err(err) = self.continue_execution(arg1.is_some_and(|arg| arg == "-a")) {
+                        eprintln!("{err}");
+                    }
+                }
+                Command::INTERRUPT => {
+                    if let Err(err) = self.interrupt(arg1.is_some_and(|arg| arg == "-a")) {
+                        eprintln!("{err}");
+                    }
+                }
+                Command::EXIT => {
+                    for index in 0..self.inferiors.len() {
+                        self.in_inferior(index, Self::end_debuggee);
+                    }
+                    self.end_debuggee();
+                    self.exiting = true;
+                }
+                Command::BREAK => {
+                    if let ControlFlow::Break(_) = self.break_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::REGISTER => {
+                    if let ControlFlow::Break(_) = self.register_command_handle(arg1, arg2, arg3) {
+                        return;
+                    }
+                }
+                Command::MEMORY => {
+                    if let ControlFlow::Break(_) = self.memory_command_handle(arg1, arg2, arg3) {
+                        return;
+                    }
+                }
+                Command::EXAMINE => {
+                    if let ControlFlow::Break(_) = self.examine_command_handle(format, arg1) {
+                        return;
+                    }
+                }
+                Command::INFO => {
+                    if let ControlFlow::Break(_) = self.info_command_handle(arg1, arg2) {
+                        return;
+                    }
+                }
+                Command::THREAD => {
+                    if let ControlFlow::Break(_) = self.thread_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::INFERIOR => {
+                    if let ControlFlow::Break(_) = self.inferior_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::SET => {
+                    if let ControlFlow::Break(_) = self.set_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::UNSET => {
+                    if let ControlFlow::Break(_) = self.unset_command_handle(arg1, arg2) {
+                        return;
+                    }
+                }
+                Command::CD => {
+                    if let ControlFlow::Break(_) = self.cd_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::TTY => {
+                    if let ControlFlow::Break(_) = self.tty_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::HANDLE => {
+                    if let ControlFlow::Break(_) = self.handle_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::CATCH => {
+                    if let ControlFlow::Break(_) = self.catch_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::TRACE => {
+                    if let ControlFlow::Break(_) = self.trace_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::SYSCALL => {
+                    if let ControlFlow::Break(_) = self.syscall_command_handle(&command_line[1..]) {
+                        return;
+                    }
+                }
+                Command::SIGNAL => {
+                    if let ControlFlow::Break(_) = self.signal_command_handle(arg1, true) {
+                        return;
+                    }
+                }
+                Command::QUEUESIGNAL => {
+                    if let ControlFlow::Break(_) = self.signal_command_handle(arg1, false) {
+                        return;
+                    }
+                }
+                Command::BACKTRACE => {
+                    if let ControlFlow::Break(_) = self.backtrace_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::PRINT => {
+                    if let ControlFlow::Break(_) = self.print_command_handle(arg1) {
+                        return;
+                    }
+                }
+                Command::GENERATECOREFILE => {
+                    if let ControlFlow::Break(_) = self.generate_core_file_command_handle(arg1) {
+                        return;
+                    }
+                }
+            }
+        } else {
+            println!("{NO_COMMAND_PROVIDED_ERROR_MSG}");
+        }
+    }
+
+    fn run_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        // like set args, arguments given to run are kept for the following runs
+        if !args.is_empty() {
+            let (args, redirections) = match launch::parse_redirections(args) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ControlFlow::Break(());
+                }
+            };
+            self.launch.args = args;
+            self.launch.redirections = redirections;
+        }
+        if let Err(err) = self.start_debuggee() {
+            eprintln!("{err}");
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn attach_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(pid) = arg1.and_then(|pid| pid.parse::<i32>().ok()) else {
+            eprintln!("Usage: attach PID");
+            return ControlFlow::Break(());
+        };
+        if let Err(err) = self.attach(Pid::from_raw(pid)) {
+            eprintln!("{err}");
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn break_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(arg1) = arg1 else {
+            eprintln!("No location provided for the breakpoint");
+            return ControlFlow::Break(());
+        };
+        let location = match arg1.parse::<Location>() {
+            Ok(location) => location,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        match self.set_breakpoint(location) {
+            Ok(()) => ControlFlow::Continue(()),
+            // kept pending, not an error for the user
+            Err(err @ DebuggerError::UnresolvedLocation(_)) => {
+                println!("{err}");
+                ControlFlow::Continue(())
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    fn set_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        let Some((setting, values)) = args.split_first() else {
+            eprintln!("The set command requires a setting, try set args or set env");
+            return ControlFlow::Break(());
+        };
+
+        match setting.as_str() {
+            "args" => {
+                let (args, redirections) = match launch::parse_redirections(values) {
+                    Ok(parsed) => parsed,
+                    Err(err) => {
+                        eprintln!("{err}");
+                        return ControlFlow::Break(());
+                    }
+                };
+                self.launch.args = args;
+                self.launch.redirections = redirections;
+                println!(
+                    "Arguments for the next run of {}: {}{}",
+                    self.launch.program,
+                    self.launch.args.join(" "),
+                    self.launch.redirections
+                );
+            }
+            "disable-randomization" => {
+                self.launch.disable_randomization = match values.first().map(String::as_str) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => {
+                        eprintln!("Usage: set disable-randomization on|off");
+                        return ControlFlow::Break(());
+                    }
+                };
+                println!(
+                    "Address space randomization {} for the next run",
+                    if self.launch.disable_randomization {
+                        "disabled"
+                    } else {
+                        "enabled"
+                    }
+                );
+            }
+            "non-stop" => {
+                if self.inferior.pid.is_some() {
+                    eprintln!("Cannot change non-stop mode while the program is running");
+                    return ControlFlow::Break(());
+                }
+                self.non_stop = match values.first().map(String::as_str) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => {
+                        eprintln!("Usage: set non-stop on|off");
+                        return ControlFlow::Break(());
+                    }
+                };
+                println!(
+                    "Non-stop mode {}",
+                    if self.non_stop { "enabled" } else { "disabled" }
+                );
+            }
+            "follow-fork-mode" => {
+                let Some(mode) = values.first().and_then(|mode| mode.parse().ok()) else {
+                    eprintln!("Usage: set follow-fork-mode parent|child");
+                    return ControlFlow::Break(());
+                };
+                self.follow_fork_mode = mode;
+                println!(
+                    "The {} is debugged after a fork",
+                    if mode == FollowForkMode::Parent {
+                        "parent"
+                    } else {
+                        "child"
+                    }
+                );
+            }
+            "detach-on-fork" => {
+                self.detach_on_fork = match values.first().map(String::as_str) {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => {
+                        eprintln!("Usage: set detach-on-fork on|off");
+                        return ControlFlow::Break(());
+                    }
+                };
+                println!(
+                    "The process not followed after a fork is {}",
+                    if self.detach_on_fork {
+                        "detached"
+                    } else {
+                        "kept stopped"
+                    }
+                );
+            }
+            "env" => {
+                let Some(assignment) = values.first() else {
+                    eprintln!("Usage: set env KEY=VALUE");
+                    return ControlFlow::Break(());
+                };
+                let Some((key, value)) = self.launch.set_env(assignment) else {
+                    eprintln!("Usage: set env KEY=VALUE");
+                    return ControlFlow::Break(());
+                };
+                println!("Environment variable {key} set to \"{value}\" for the next run");
+            }
+            _ => {
+                eprintln!("Unknown setting {setting}");
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn unset_command_handle(
+        &mut self,
+        arg1: Option<&String>,
+        arg2: Option<&String>,
+    ) -> ControlFlow<()> {
+        if arg1.map(String::as_str) != Some("env") {
+            eprintln!("Usage: unset env [KEY]");
+            return ControlFlow::Break(());
+        }
+        self.launch.unset_env(arg2.map(String::as_str));
+        match arg2 {
+            Some(key) => println!("Environment variable {key} removed for the next run"),
+            None => println!("Environment cleared for the next run"),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn cd_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(dir) = arg1 else {
+            eprintln!("The cd command requires a directory");
+            return ControlFlow::Break(());
+        };
+        let dir = match &self.launch.cwd {
+            Some(cwd) => cwd.join(dir),
+            None => Path::new(dir).to_path_buf(),
+        };
+        let Ok(dir) = dir.canonicalize() else {
+            eprintln!("{} is not an accessible directory", dir.display());
+            return ControlFlow::Break(());
+        };
+        if !dir.is_dir() {
+            eprintln!("{} is not a directory", dir.display());
+            return ControlFlow::Break(());
+        }
+        println!("Working directory {} for the next run", dir.display());
+        self.launch.cwd = Some(dir);
+        ControlFlow::Continue(())
+    }
+
+    fn tty_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(terminal) = arg1 else {
+            eprintln!("Usage: tty [/dev/pts/N | pty | inherit]");
+            return ControlFlow::Break(());
+        };
+        self.launch.terminal = match terminal.as_str() {
+            "pty" => TerminalMode::Pty,
+            "inherit" => TerminalMode::Inherit,
+            path => TerminalMode::Tty(PathBuf::from(path)),
+        };
+        match &self.launch.terminal {
+            TerminalMode::Pty => println!("The next run will use its own pseudo-terminal"),
+            TerminalMode::Inherit => println!("The next run will share the debugger's terminal"),
+            TerminalMode::Tty(path) => println!("The next run will use {}", path.display()),
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn handle_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        let Some((name, keywords)) = args.split_first() else {
+            eprintln!("Usage: handle SIGNAL [stop|nostop] [print|noprint] [pass|nopass]");
+            return ControlFlow::Break(());
+        };
+        let signal = match signals::parse_signal(name) {
+            Ok(signal) => signal,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+        match self.signals.handle(signal, &keywords) {
+            Ok(policy) => {
+                println!("{}", signals::TABLE_HEADER);
+                println!("{}", signals::describe_policy(signal, policy));
+                ControlFlow::Continue(())
+            }
+            Err(err) => {
+                eprintln!("{err}");
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    fn catch_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let kind = match CatchKind::parse(self.arch, &args) {
+            Ok(kind) => kind,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        let catchpoint = Catchpoint {
+            id: self.catchpoints.len() + 1,
+            kind,
+        };
+        println!(
+            "Catchpoint {} ({})",
+            catchpoint.id,
+            catchpoint.describe(self.arch)
+        );
+        self.catchpoints.push(catchpoint);
+        ControlFlow::Continue(())
+    }
+
+    fn trace_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["syscalls", "on", filter @ ..] => match SyscallFilter::parse(self.arch, filter) {
+                Ok(filter) => {
+                    println!("Tracing {}", filter.describe());
+                    self.syscall_trace = Some(filter);
+                }
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ControlFlow::Break(());
+                }
+            },
+            ["syscalls", "off"] => {
+                self.syscall_trace = None;
+                println!("Syscalls are no longer traced");
+            }
+            _ => {
+                eprintln!("Usage: trace syscalls on [NAME | %CLASS]... | trace syscalls off");
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Changes the syscall the selected thread is stopped at by a catchpoint: on entry it can
+    /// be skipped and its arguments changed, on return its value can be replaced
+    fn syscall_command_handle(&mut self, args: &[String]) -> ControlFlow<()> {
+        let Ok(tid) = self.stopped_tracee().inspect_err(|err| eprintln!("{err}")) else {
+            return ControlFlow::Break(());
+        };
+        let Some(thread) = self
+            .inferior
+            .threads
+            .get(tid)
+            .filter(|thread| thread.at_syscall_stop)
+        else {
+            eprintln!("The selected thread is not stopped at a syscall catchpoint");
+            return ControlFlow::Break(());
+        };
+        let entry = thread.in_syscall;
+        let number = self.syscall_number(tid);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let parse_value = |value: &str| {
+            let value = catchpoint::parse_syscall_value(value);
+            if value.is_none() {
+                eprintln!("The value must be a number or an errno name like ENOSPC");
+            }
+            value
+        };
+
+        let result = match args.as_slice() {
+            ["skip", value @ ..] if value.len() <= 1 => {
+                if !entry {
+                    eprintln!(
+                        "The syscall has already been made, only its return value can be changed"
+                    );
+                    return ControlFlow::Break(());
+                }
+                let Some(value) = value
+                    .first()
+                    .map_or(Some(-(Errno::ENOSYS as i64) as u64), |value| {
+                        parse_value(value)
+                    })
+                else {
+                    return ControlFlow::Break(());
+                };
+                self.skip_syscall(tid, number, value)
+            }
+            ["return", value] => {
+                let Some(value) = parse_value(value) else {
+                    return ControlFlow::Break(());
+                };
+                if entry {
+                    // the syscall is not made, like with skip
+                    self.skip_syscall(tid, number, value)
+                } else {
+                    self.set_register(tid, self.arch.syscall_return_reg(), value)
+                        .map(|()| {
+                            println!(
+                                "Return value changed: {}",
+                                catchpoint::describe_syscall_return(self.arch, number, value)
+                            );
+                        })
+                }
+            }
+            ["arg", index, value] => {
+                if !entry {
+                    eprintln!("The syscall has already been made, its arguments can't be changed");
+                    return ControlFlow::Break(());
+                }
+                let regs = self.arch.syscall_arg_regs();
+                let Some(&reg) = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| regs.get(index.checked_sub(1)?))
+                else {
+                    eprintln!("Syscalls have arguments 1 to {}", regs.len());
+                    return ControlFlow::Break(());
+                };
+                let Some(value) = parse_value(value) else {
+                    return ControlFlow::Break(());
+                };
+                self.set_register(tid, reg, value).map(|()| {
+                    println!(
+                        "Argument {index} of {} set to 0x{value:x}",
+                        catchpoint::syscall_name(self.arch, number)
+                    );
+                })
+            }
+            _ => {
+                eprintln!(
+                    "Usage: syscall skip [VALUE] | syscall return VALUE | syscall arg N VALUE"
+                );
+                return ControlFlow::Break(());
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("Cannot change the syscall: {err}");
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// Skips the syscall `tid` is entering, it returns `value` instead
+    fn skip_syscall(&mut self, tid: Pid, number: u64, value: u64) -> Result<(), DebuggerError> {
+        self.live
+            .set_syscall_number(tid, u64::MAX)
+            .map_err(|errno| DebuggerError::Register {
+                tid,
+                reg: self.arch.syscall_number_reg(),
+                errno,
+            })?;
+        if let Some(thread) = self.inferior.threads.get_mut(tid) {
+            thread.skipped_syscall = Some((number, value));
+        }
+        println!(
+            "Syscall skipped: {}",
+            catchpoint::describe_syscall_return(self.arch, number, value)
+        );
+        Ok(())
+    }
+
+    /// `signal SIG` resumes the selected thread delivering `SIG`, 0 meaning none;
+    /// `queue-signal SIG` only sets the signal it gets when resumed
+    fn signal_command_handle(&mut self, arg1: Option<&String>, resume: bool) -> ControlFlow<()> {
+        let Some(name) = arg1 else {
+            eprintln!("Usage: signal SIGNAL | queue-signal SIGNAL");
+            return ControlFlow::Break(());
+        };
+        let signal = match name.as_str() {
+            "0" if resume => None,
+            name => match signals::parse_signal(name) {
+                Ok(signal) => Some(signal),
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ControlFlow::Break(());
+                }
+            },
+        };
+        let Ok(tid) = self.stopped_tracee().inspect_err(|err| eprintln!("{err}")) else {
+            return ControlFlow::Break(());
+        };
+        // like in gdb, a signal given explicitly is delivered whatever handle says, only a
+        // queued one must be passed
+        if let Some(signal) = signal.filter(|&signal| !resume && !self.signals.get(signal).pass) {
+            eprintln!("{signal} is not passed to the program, see handle {signal} pass");
+            return ControlFlow::Break(());
+        }
+
+        let Some(thread) = self.inferior.threads.get_mut(tid) else {
+            eprintln!("{}", DebuggerError::thread_gone(tid));
+            return ControlFlow::Break(());
+        };
+        thread.pending_signal = signal;
+        let id = thread.id;
+        if !resume {
+            return ControlFlow::Continue(());
+        }
+        match signal {
+            Some(signal) => println!("Continuing thread {id} (LWP {tid}) with signal {signal}."),
+            None => println!("Continuing thread {id} (LWP {tid}) without a signal."),
+        }
+        if let Err(err) = self.continue_execution(false) {
+            eprintln!("{err}");
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// The `siginfo_t` of the selected thread's stop
+    fn print_siginfo(&self) -> ControlFlow<()> {
+        if let Some(core) = &self.core {
+            let Some(info) = core.siginfo() else {
+                eprintln!("{} has no signal information", core.path.display());
+                return ControlFlow::Break(());
+            };
+            for line in SigInfo::decode(&info).describe() {
+                println!("{line}");
+            }
+            return ControlFlow::Continue(());
+        }
+        let Ok(tid) = self.stopped_tracee().inspect_err(|err| eprintln!("{err}")) else {
+            return ControlFlow::Break(());
+        };
+        let info = match self.live.siginfo(tid) {
+            Ok(info) => info,
+            Err(err) => {
+                eprintln!(
+                    "Cannot read the signal information of {tid}: {}",
+                    err.desc()
+                );
+                return ControlFlow::Break(());
+            }
+        };
+        for line in SigInfo::decode(&info).describe() {
+            println!("{line}");
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn print_signals(&self, arg2: Option<&String>) -> ControlFlow<()> {
+        if let Some(name) = arg2 {
+            let signal = match signals::parse_signal(name) {
+                Ok(signal) => signal,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ControlFlow::Break(());
+                }
+            };
+            println!("{}", signals::TABLE_HEADER);
+            println!(
+                "{}",
+                signals::describe_policy(signal, self.signals.get(signal))
+            );
+            return ControlFlow::Continue(());
+        }
+        println!("{}", signals::TABLE_HEADER);
+        for (signal, policy) in self.signals.iter() {
+            println!("{}", signals::describe_policy(signal, policy));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn register_command_handle(
+        &mut self,
+        arg1: Option<&String>,
+        arg2: Option<&String>,
+        arg3: Option<&String>,
+    ) -> ControlFlow<()> {
+        if arg1.is_none() {
+            eprintln!("You cannot call the register command without any arguments");
+            return ControlFlow::Break(());
+        }
+        let arg1 = arg1.unwrap();
+        let arg1 = arg1.to_lowercase();
+        let Ok(pid) = self
+            .inspected_thread()
+            .inspect_err(|err| eprintln!("{err}"))
+        else {
+            return ControlFlow::Break(());
+        };
+        if arg1 == "write" && self.core.is_some() {
+            eprintln!("{CORE_FILE_ERROR_MSG}");
+            return ControlFlow::Break(());
+        }
+        if arg1 == "dump" {
+            if let Err(err) = self.dump_registers(pid) {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        } else if arg1 == "read" {
+            if let ControlFlow::Break(_) = self.register_read(pid, arg2) {
+                return ControlFlow::Break(());
+            }
+        } else if arg1 == "write" {
+            if let ControlFlow::Break(_) = self.register_write(pid, arg2, arg3) {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn memory_command_handle(
+        &mut self,
+        arg1: Option<&String>,
+        arg2: Option<&String>,
+        arg3: Option<&String>,
+    ) -> ControlFlow<()> {
+        if arg1.is_none() {
+            eprintln!("Command memory cannot be called without any arguments");
+            return ControlFlow::Break(());
+        }
+        let arg1 = arg1.unwrap();
+        if arg2.is_none() {
+            eprintln!("You should precise the address you want to manipulate");
+            return ControlFlow::Break(());
+        }
+        let arg2 = arg2.unwrap();
+        let Ok(pid) = self
+            .inspected_thread()
+            .inspect_err(|err| eprintln!("{err}"))
+        else {
+            return ControlFlow::Break(());
+        };
+        if arg1 == "write" && self.core.is_some() {
+            eprintln!("{CORE_FILE_ERROR_MSG}");
+            return ControlFlow::Break(());
+        }
+
+        let address = match str_to_addr(arg2) {
+            Ok(address) => address as u64,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        let Some(map) = self.mapping_of(pid, address) else {
+            return ControlFlow::Break(());
+        };
+
+        if arg1 == "read" {
+            let Some(val) = target::read_word(self.target(), pid, address) else {
+                eprintln!("Cannot read 0x{address:x} in {}", map.describe());
+                return ControlFlow::Break(());
+            };
+            println!("{} --> {}    ({})", arg2, val as i64, map.describe());
+        } else if arg1 == "write" {
+            if let ControlFlow::Break(_) = self.memory_write(pid, arg3, arg2) {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn examine_command_handle(
+        &mut self,
+        format: Option<&str>,
+        arg1: Option<&String>,
+    ) -> ControlFlow<()> {
+        let examine = match format.unwrap_or_default().parse::<ExamineFormat>() {
+            Ok(examine) => examine,
+            Err(e) => {
+                eprintln!("{e}");
+                return ControlFlow::Break(());
+            }
+        };
+        let Some(arg1) = arg1 else {
+            eprintln!("You should precise the address you want to examine");
+            return ControlFlow::Break(());
+        };
+        let location = match arg1.parse::<Location>() {
+            Ok(location) => location,
+            Err(e) => {
+                eprintln!("{e}");
+                return ControlFlow::Break(());
+            }
+        };
+        let Ok(pid) = self
+            .inspected_thread()
+            .inspect_err(|err| eprintln!("{err}"))
+        else {
+            return ControlFlow::Break(());
+        };
+        let Some(&address) = self.resolve_location(&location).first() else {
+            eprintln!("Cannot find {location} in {}", self.launch.program);
+            return ControlFlow::Break(());
+        };
+        let Some(map) = self.mapping_of(pid, address) else {
+            return ControlFlow::Break(());
+        };
+        println!("In {}", map.describe());
+
+        let bytes = self.target().read_memory(pid, address, examine.byte_len());
+        let line_len = examine.units_per_line() * examine.unit;
+        for (i, line) in bytes.chunks(line_len).enumerate() {
+            let units: Vec<String> = line
+                .chunks_exact(examine.unit)
+                .map(|unit| examine.format_unit(unit))
+                .collect();
+            let line_address = address + (i * line_len) as u64;
+            println!(
+                "{}:\t{}",
+                self.describe_address(line_address),
+                units.join("\t")
+            );
+        }
+        if bytes.len() < examine.byte_len() {
+            eprintln!("Cannot read memory at 0x{:x}", address + bytes.len() as u64);
+            return ControlFlow::Break(());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn backtrace_command_handle(&self, arg1: Option<&String>) -> ControlFlow<()> {
+        let limit = match arg1.map(|n| n.parse::<usize>()) {
+            Some(Ok(limit)) => limit,
+            Some(Err(_)) => {
+                eprintln!("Usage: backtrace [N]");
+                return ControlFlow::Break(());
+            }
+            None => usize::MAX,
+        };
+        let Ok(tid) = self
+            .inspected_thread()
+            .inspect_err(|err| eprintln!("{err}"))
+        else {
+            return ControlFlow::Break(());
+        };
+        let Ok(mut regset) = self.target().get_regs(tid, self.arch.regset_len()) else {
+            eprintln!("Cannot read the registers of thread {tid}");
+            return ControlFlow::Break(());
+        };
+        if self.core.is_none() {
+            self.rewind_trap(tid, &mut regset);
+        }
+        let rule_at = |address| {
+            self.inferior
+                .modules
+                .iter()
+                .find(|module| module.contains(address))?
+                .frame_rule(address)
+        };
+        let read_word = |address| target::read_word(self.target(), tid, address);
+        let frames = unwind::backtrace(self.arch, &regset, rule_at, read_word);
+        for (i, &pc) in frames.iter().take(limit).enumerate() {
+            println!("#{i:<3}{}", self.describe_address(pc));
+        }
+        if frames.len() > limit {
+            println!("(More stack frames follow...)");
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn print_command_handle(&self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(expression) = arg1 else {
+            eprintln!("Usage: print $REGISTER | SYMBOL | &SYMBOL");
+            return ControlFlow::Break(());
+        };
+        let Ok(tid) = self
+            .inspected_thread()
+            .inspect_err(|err| eprintln!("{err}"))
+        else {
+            return ControlFlow::Break(());
+        };
+        if let Some(name) = expression.strip_prefix('$') {
+            let Some(reg) = register::find_register(self.arch, name) else {
+                eprintln!("No register named {name}");
+                return ControlFlow::Break(());
+            };
+            let value = match self.shown_register(tid, reg) {
+                Ok(value) => value,
+                Err(err) => {
+                    eprintln!("{err}");
+                    return ControlFlow::Break(());
+                }
+            };
+            if reg == self.arch.pc_reg() {
+                println!("{expression} = {}", self.describe_address(value));
+            } else {
+                println!("{expression} = {} (0x{value:x})", value as i64);
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let (address_of, name) = match expression.strip_prefix('&') {
+            Some(name) => (true, name),
+            None => (false, expression.as_str()),
+        };
+        let Some((module, symbol)) = self
+            .inferior
+            .modules
+            .iter()
+            .find_map(|module| Some((module, module.object.find_symbol(name)?)))
+        else {
+            eprintln!("No symbol \"{name}\" in {}", self.launch.program);
+            return ControlFlow::Break(());
+        };
+        let address = module.to_runtime(symbol.address);
+        if address_of || symbol.is_function {
+            println!("{expression} = {}", self.describe_address(address));
+            return ControlFlow::Continue(());
+        }
+        let bytes = self
+            .target()
+            .read_memory(tid, address, symbol.size as usize);
+        if bytes.len() < symbol.size as usize {
+            eprintln!("Cannot read memory at 0x{:x}", address + bytes.len() as u64);
+            return ControlFlow::Break(());
+        }
+        println!("{expression} = {}", examine::format_value(&bytes));
+        ControlFlow::Continue(())
+    }
+
+    fn generate_core_file_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Ok(tid) = self.stopped_tracee().inspect_err(|err| eprintln!("{err}")) else {
+            return ControlFlow::Break(());
+        };
+        let Some(pid) = self.inferior.pid else {
+            return ControlFlow::Break(());
+        };
+        if !self.inferior.threads.running().is_empty() {
+            eprintln!("Some threads are running, stop them all first with interrupt -a");
+            return ControlFlow::Break(());
+        }
+        let dump = match self.dump_process(pid, tid) {
+            Ok(dump) => dump,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        let path = arg1.cloned().unwrap_or_else(|| format!("core.{pid}"));
+        if let Err(err) = std::fs::write(&path, dump.to_bytes(self.arch)) {
+            eprintln!("Cannot write {path}: {err}");
+            return ControlFlow::Break(());
+        }
+        println!("Saved corefile {path}");
+        ControlFlow::Continue(())
+    }
+
+    /// Gathers what a core file holds of the stopped process `pid`, thread `tid` coming first
+    fn dump_process(&self, pid: Pid, tid: Pid) -> Result<CoreDump, String> {
+        let mut tids = self.inferior.threads.tids();
+        tids.sort_by_key(|&other| other != tid);
+        let mut threads = vec![];
+        for tid in tids {
+            let mut regs = self
+                .live
+                .get_regs(tid, self.arch.regset_len())
+                .map_err(|err| {
+                    format!("Cannot read the registers of thread {tid}: {}", err.desc())
+                })?;
+            // the trap is not saved, the thread must run the instruction it replaced
+            self.rewind_trap(tid, &mut regs);
+            let regsets = self
+                .arch
+                .core_regsets()
+                .iter()
+                .filter_map(|&(n_type, name)| {
+                    Some((n_type, name, self.live.read_regset(tid, n_type).ok()?))
+                })
+                .collect();
+            let signal = self
+                .inferior
+                .threads
+                .get(tid)
+                .and_then(|thread| thread.pending_signal);
+            threads.push(DumpedThread {
+                tid,
+                signal: signal.map_or(0, |signal| signal as i32),
+                regs,
+                regsets,
+            });
+        }
+
+        let maps = self
+            .live
+            .maps(pid)
+            .map_err(|err| format!("Cannot read the mappings of process {pid}: {err}"))?;
+        let memory = maps
+            .into_iter()
+            .map(|map| {
+                let contents = self.dumped_contents(pid, &map);
+                (map, contents)
+            })
+            .collect();
+        let [ppid, pgrp, sid] = self.live.stat_ids(pid).unwrap_or_default();
+        let (uid, gid) = self.live.owner(pid).unwrap_or_default();
+        // saved raw, with the AT_NULL entry ending it
+        let auxv = self.live.auxv(pid).unwrap_or_default();
+        let auxv = auxv
+            .iter()
+            .chain([&(0, 0)])
+            .flat_map(|&(key, value)| [key, value])
+            .flat_map(u64::to_ne_bytes)
+            .collect();
+        let siginfo = self.live.siginfo(tid).ok().map(|info| {
+            // siginfo_t is plain old data
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &info as *const libc::siginfo_t as *const u8,
+                    size_of::<libc::siginfo_t>(),
+                )
+            };
+            bytes.to_vec()
+        });
+        Ok(CoreDump {
+            pid: pid.as_raw(),
+            ppid,
+            pgrp,
+            sid,
+            uid,
+            gid,
+            name: self.live.thread_name(pid, pid).unwrap_or_default(),
+            command: self.live.command_line(pid).unwrap_or_default(),
+            threads,
+            siginfo,
+            auxv,
+            memory,
+        })
+    }
+
+    /// What a core file saves of a mapping, without the traps of the breakpoints. Like the
+    /// kernel, code and read-only data are left in their file.
+    fn dumped_contents(&self, pid: Pid, map: &MemoryMap) -> Vec<u8> {
+        let in_file = map
+            .pathname
+            .as_deref()
+            .is_some_and(|path| path.starts_with('/'));
+        let readable = map.perms.starts_with('r');
+        let writable = map.perms.get(1..2) == Some("w");
+        if !readable || (in_file && !writable) {
+            return vec![];
+        }
+        let mut contents = self.live.read_mapping(pid, map).unwrap_or_default();
+        for bp in self.inferior.breakpoints.values().filter(|bp| bp.enabled) {
+            let saved = bp.saved_bytes();
+            let Some(offset) = bp.address().checked_sub(map.start) else {
+                continue;
+            };
+            if let Some(trap) = contents.get_mut(offset as usize..offset as usize + saved.len()) {
+                trap.copy_from_slice(&saved);
+            }
+        }
+        contents
+    }
+
+    fn info_command_handle(
+        &mut self,
+        arg1: Option<&String>,
+        arg2: Option<&String>,
+    ) -> ControlFlow<()> {
+        match arg1.map(String::as_str) {
+            Some("sharedlibrary") => {
+                self.print_shared_libraries();
+                ControlFlow::Continue(())
+            }
+            Some("signals") => self.print_signals(arg2),
+            Some("siginfo") => self.print_siginfo(),
+            Some("inferiors") => {
+                self.print_inferiors();
+                ControlFlow::Continue(())
+            }
+            Some("threads") => {
+                if self.core.is_none() {
+                    if let Err(err) = self.tracee() {
+                        eprintln!("{err}");
+                        return ControlFlow::Break(());
+                    }
+                }
+                self.print_threads();
+                ControlFlow::Continue(())
+            }
+            Some("proc") => {
+                let pid = match &self.core {
+                    Some(core) => Ok(core.pid()),
+                    None => self
+                        .tracee()
+                        .and(self.inferior.pid.ok_or(DebuggerError::NoThread)),
+                };
+                let Ok(pid) = pid.inspect_err(|err| eprintln!("{err}")) else {
+                    return ControlFlow::Break(());
+                };
+                match arg2.map(String::as_str) {
+                    None => self.print_proc_info(pid),
+                    Some("mappings") => self.print_proc_mappings(pid),
+                    Some(other) => {
+                        eprintln!("Unknown info proc request {other}, try info proc mappings");
+                        ControlFlow::Break(())
+                    }
+                }
+            }
+            _ => {
+                eprintln!(
+                    "Try one of: info proc, info proc mappings, info sharedlibrary, info threads, info inferiors, info signals, info siginfo"
+                );
+                ControlFlow::Break(())
+            }
+        }
+    }
+
+    fn thread_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let current = match self.core {
+            Some(_) => self.inferior.current_thread.ok_or(DebuggerError::NoThread),
+            None => self.tracee(),
+        };
+        let Ok(current) = current.inspect_err(|err| eprintln!("{err}")) else {
+            return ControlFlow::Break(());
+        };
+        let Some(arg1) = arg1 else {
+            if let Some(thread) = self.inferior.threads.get(current) {
+                println!("[Current thread is {} (LWP {current})]", thread.id);
+            }
+            return ControlFlow::Continue(());
+        };
+        let Some(thread) = arg1
+            .parse()
+            .ok()
+            .and_then(|id| self.inferior.threads.by_id(id))
+        else {
+            eprintln!("Invalid thread ID: {arg1}, see info threads");
+            return ControlFlow::Break(());
+        };
+        let tid = thread.tid;
+        println!("[Switching to thread {} (LWP {tid})]", thread.id);
+        self.inferior.current_thread = Some(tid);
+        println!("#0  {}", self.describe_frame(tid));
+        ControlFlow::Continue(())
+    }
+
+    fn inferior_command_handle(&mut self, arg1: Option<&String>) -> ControlFlow<()> {
+        let Some(arg1) = arg1 else {
+            println!(
+                "[Current inferior is {} [{}] ({})]",
+                self.inferior.id,
+                self.inferior.describe(),
+                self.executable(&self.inferior)
+            );
+            return ControlFlow::Continue(());
+        };
+        let id: Option<usize> = arg1.parse().ok();
+        if id == Some(self.inferior.id) {
+            return ControlFlow::Continue(());
+        }
+        let Some(index) = self
+            .inferiors
+            .iter()
+            .position(|inferior| Some(inferior.id) == id)
+        else {
+            eprintln!("Invalid inferior ID: {arg1}, see info inferiors");
+            return ControlFlow::Break(());
+        };
+
+        // the inferiors in the background have all their threads stopped
+        if let Some(pid) = self.inferior.pid {
+            self.stop_all_threads(pid);
+        }
+        std::mem::swap(&mut self.inferior, &mut self.inferiors[index]);
+        self.inferiors.sort_by_key(|inferior| inferior.id);
+        println!(
+            "[Switching to inferior {} [{}] ({})]",
+            self.inferior.id,
+            self.inferior.describe(),
+            self.executable(&self.inferior)
+        );
+        if let Some(thread) = self
+            .inferior
+            .current_thread
+            .and_then(|tid| self.inferior.threads.get(tid))
+        {
+            println!("[Switching to thread {} (LWP {})]", thread.id, thread.tid);
+            println!("#0  {}", self.describe_frame(thread.tid));
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// The program an inferior runs, or would run
+    fn executable(&self, inferior: &Inferior) -> String {
+        inferior
+            .pid
+            .and_then(|pid| self.live.executable(pid).ok())
+            .map(|exe| exe.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.launch.program.clone())
+    }
+
+    fn print_inferiors(&self) {
+        println!("  {:<4} {:<17} Executable", "Num", "Description");
+        let mut inferiors: Vec<&Inferior> = self.inferiors.iter().collect();
+        inferiors.push(&self.inferior);
+        inferiors.sort_by_key(|inferior| inferior.id);
+        for inferior in inferiors {
+            let marker = if inferior.id == self.inferior.id {
+                '*'
+            } else {
+                ' '
+            };
+            println!(
+                "{marker} {:<4} {:<17} {}",
+                inferior.id,
+                inferior.describe(),
+                self.executable(inferior)
+            );
+        }
+    }
+
+    fn print_threads(&self) {
+        let Some(pid) = self.inferior.pid.or(self.core.as_ref().map(CoreFile::pid)) else {
+            return;
+        };
+        println!("  {:<4} {:<28} Frame", "Id", "Target Id");
+        for thread in self.inferior.threads.iter() {
+            let marker = if self.inferior.current_thread == Some(thread.tid) {
+                '*'
+            } else {
+                ' '
+            };
+            let name = self
+                .target()
+                .thread_name(pid, thread.tid)
+                .unwrap_or_default();
+            let target = format!("LWP {} \"{name}\"", thread.tid);
+            println!(
+                "{marker} {:<4} {target:<28} {}",
+                thread.id,
+                self.describe_frame(thread.tid)
+            );
+        }
+    }
+
+    /// Where a thread is stopped, its registers can't be read while it runs
+    fn describe_frame(&self, tid: Pid) -> String {
+        match self.inferior.threads.get(tid) {
+            Some(thread) if thread.state == ThreadState::Running => "(running)".to_string(),
+            _ => self.describe_pc(tid),
+        }
+    }
+
+    fn print_shared_libraries(&self) {
+        if self.inferior.modules.len() <= 1 {
+            println!("No shared libraries loaded at this time.");
+            return;
+        }
+        println!(
+            "{:<18}  {:<18}  {:<10}  Shared Object Library",
+            "From", "To", "Syms Read"
+        );
+        let mut missing_debug_info = false;
+        for module in &self.inferior.modules[1..] {
+            let (from, to) = module.code_range().unwrap_or((module.bias, module.bias));
+            let syms_read = if module.object.has_line_info() {
+                "Yes"
+            } else {
+                missing_debug_info = true;
+                "Yes (*)"
+            };
+            println!(
+                "0x{from:016x}  0x{to:016x}  {syms_read:<10}  {}",
+                module.object.path.display()
+            );
+        }
+        if missing_debug_info {
+            println!("(*): Shared library is missing debugging information.");
+        }
+    }
+
+    fn print_proc_info(&self, pid: Pid) -> ControlFlow<()> {
+        println!("process {pid}");
+        if let Some(core) = &self.core {
+            println!("cmdline = '{}'", core.command);
+            println!("exe = '{}'", core.program().display());
+            return ControlFlow::Continue(());
+        }
+        if let Ok(cmdline) = self.live.command_line(pid) {
+            println!("cmdline = '{cmdline}'");
+        }
+        for (link, target) in [
+            ("cwd", self.live.cwd(pid)),
+            ("exe", self.live.executable(pid)),
+        ] {
+            if let Ok(target) = target {
+                println!("{link} = '{}'", target.display());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn print_proc_mappings(&self, pid: Pid) -> ControlFlow<()> {
+        let maps = match self.target().maps(pid) {
+            Ok(maps) => maps,
+            Err(e) => {
+                eprintln!("Cannot read the mappings of process {pid}: {e}");
+                return ControlFlow::Break(());
+            }
+        };
+        println!("process {pid}");
+        println!(
+            "{:>18} {:>18} {:>10} {:>10} {:5} objfile",
+            "Start Addr", "End Addr", "Size", "Offset", "Perms"
+        );
+        for map in maps {
+            println!(
+                "{:>18} {:>18} {:>10} {:>10} {:5} {}",
+                format!("0x{:x}", map.start),
+                format!("0x{:x}", map.end),
+                format!("0x{:x}", map.end - map.start),
+                format!("0x{:x}", map.offset),
+                map.perms,
+                map.pathname.as_deref().unwrap_or("")
+            );
+        }
+        ControlFlow::Continue(())
+    }
+
+    /// The mapping holding `address`, warning when nothing is mapped there
+    fn mapping_of(&self, pid: Pid, address: u64) -> Option<MemoryMap> {
+        let maps = self.target().maps(pid).unwrap_or_default();
+        let map = procfs::find_map(&maps, address).cloned();
+        if map.is_none() {
+            eprintln!("Warning: 0x{address:x} is not mapped in process {pid}");
+        }
+        map
+    }
+
+    fn memory_write(&mut self, pid: Pid, arg3: Option<&String>, arg2: &str) -> ControlFlow<()> {
+        if arg3.is_none() {
+            eprintln!("You should precise the value that will be set to the register");
+            return ControlFlow::Break(());
+        }
+        let arg3 = arg3.unwrap();
+        let parsed = str_to_addr(arg3)
+            .and_then(|val| str_addr_to_c_void(arg2).map(|address| (val, address as u64)));
+        let (val, address) = match parsed {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        let Ok(_) = self.live.write_memory(pid, address, &val.to_ne_bytes()) else {
+            eprintln!("Cannot write to that address");
+            return ControlFlow::Break(());
+        };
+        ControlFlow::Continue(())
+    }
+
+    fn register_write(
+        &mut self,
+        pid: Pid,
+        arg2: Option<&String>,
+        arg3: Option<&String>,
+    ) -> ControlFlow<()> {
+        if arg2.is_none() {
+            eprintln!("This command requires a register name");
+            return ControlFlow::Break(());
+        }
+        let arg2 = arg2.unwrap();
+        if arg3.is_none() {
+            eprintln!("This command requires a value that will be set to the register");
+            return ControlFlow::Break(());
+        }
+        let arg3 = arg3.unwrap();
+        let Some(reg) = get_register_from_name(arg2) else {
+            eprintln!("This register doesn't exist in the table");
+            return ControlFlow::Break(());
+        };
+        let result = str_to_reg_value(arg3).and_then(|val| self.set_register(pid, reg, val));
+        if let Err(err) = result {
+            eprintln!("{err}");
+            return ControlFlow::Break(());
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    fn register_read(&mut self, pid: Pid, arg2: Option<&String>) -> ControlFlow<()> {
+        if arg2.is_none() {
+            eprintln!("This command requires a register name");
+            return ControlFlow::Break(());
+        }
+        let arg2 = arg2.unwrap();
+        let Some(reg) = get_register_from_name(arg2) else {
+            eprintln!("This register doesn't exist in the table");
+            return ControlFlow::Break(());
+        };
+        let val = match self.shown_register(pid, reg) {
+            Ok(val) => val,
+            Err(err) => {
+                eprintln!("{err}");
+                return ControlFlow::Break(());
+            }
+        };
+        println!("{} -> {}", arg2, val);
+        ControlFlow::Continue(())
+    }
+
+    /// Runs the program to its end logging the syscalls `filter` keeps, like strace, instead
+    /// of giving a prompt. The debuggee stops on nothing but the signals set to stop it, after
+    /// which it is resumed.
+    pub fn trace_syscalls(&mut self, filter: SyscallFilter) -> Result<(), DebuggerError> {
+        self.syscall_trace = Some(filter);
+        self.start_debuggee()?;
+        while self.inferior.pid.is_some() {
+            self.resume_and_wait();
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::unnecessary_lazy_evaluations)]
+    pub fn run(&mut self) -> Result<(), DebuggerError> {
+        let interface =
+            Interface::new("vdebugger").map_err(|err| DebuggerError::Terminal(err.to_string()))?;
+        let reader = Arc::new(interface);
+        self.console.set_interface(reader.clone());
+        println!("The program name is {}", self.launch.program);
+        reader.set_prompt("vdebugger> ").unwrap_or_else(|_| {});
+
+        loop {
+            // in non-stop mode, the threads report their stops while commands are typed
+            let timeout = (self.non_stop && !self.inferior.threads.running().is_empty())
+                .then_some(NON_STOP_POLL_INTERVAL);
+            match reader.read_line_step(timeout) {
+                Ok(Some(ReadResult::Input(input))) => {
+                    reader.add_history_unique(input.clone());
+                    self.handle_command(&input);
+                    if self.exiting {
+                        break;
+                    }
+                }
+                Ok(None) => self.poll_events(&reader),
+                _ => break,
+            }
+        }
+        Ok(())
+    }
+
+    /// Breaks on `location`. A location no loaded module defines is kept pending, a shared
+    /// library loaded later may define it: the error tells so.
+    pub fn set_breakpoint(&mut self, location: Location) -> Result<(), DebuggerError> {
+        // without a process, the breakpoint is inserted when the program is run
+        let resolved = if self.inferior.pid.is_none() {
+            let resolved = !self.resolve_location(&location).is_empty();
+            if resolved {
+                println!("Set breakpoint at {location}");
+            }
+            self.locations.push(location.clone());
+            resolved
+        } else {
+            // the trap is written through a stopped thread
+            let Some(tid) = self
+                .inferior
+                .threads
+                .iter()
+                .find(|thread| thread.state == ThreadState::Stopped)
+                .map(|thread| thread.tid)
+            else {
+                return Err(DebuggerError::NoStoppedThread);
+            };
+
+            self.locations.push(location.clone());
+            let bp = Breakpoint::new_pending(self.arch, self.live.clone());
+            let addresses = self.insert_location(tid, &location, bp);
+            for &address in &addresses {
+                // those that couldn't be inserted were reported
+                if self.inferior.breakpoints.contains_key(&address) {
+                    println!("Set breakpoint at {}", self.describe_address(address));
+                }
+            }
+            !addresses.is_empty()
+        };
+
+        // the processes of the other inferiors are stopped, they get the breakpoint as well
+        for index in 0..self.inferiors.len() {
+            self.in_inferior(index, |debugger| {
+                if let Some(&tid) = debugger.inferior.threads.tids().first() {
+                    let bp = Breakpoint::new_pending(debugger.arch, debugger.live.clone());
+                    debugger.insert_location(tid, &location, bp);
+                }
+            });
+        }
+        if !resolved {
+            return Err(DebuggerError::UnresolvedLocation(location));
+        }
+        Ok(())
+    }
+
+    /// Executes the instruction under the trap `tid` stopped on. While other threads run, a
+    /// copy of the instruction is executed so the trap stays in place for them; when it can't
+    /// be copied, they are stopped while the trap is removed.
+    fn step_over_breakpoint(&mut self, tid: Pid) -> Result<(), DebuggerError> {
+        let hit_trap = self.hit_trap(tid);
+        if let Some(thread) = self.inferior.threads.get_mut(tid) {
+            thread.hit_trap = false;
+        }
+        // a thread that hit a trap without it being reported hits it again
+        let Some(current_line) = hit_trap else {
+            return Ok(());
+        };
+        self.set_pc(tid, current_line)?;
+
+        let running = self.inferior.threads.running();
+        if !running.is_empty() {
+            if self.displaced_step(tid, current_line)? {
+                return Ok(());
+            }
+            if let Some(pid) = self.inferior.pid {
+                self.stop_threads(pid, &running);
+            }
+        }
+
+        let Some(bp) = self.inferior.breakpoints.get_mut(&current_line) else {
+            return Err(DebuggerError::NoBreakpoint(current_line));
+        };
+        let mut result = bp.disable(tid);
+        if result.is_ok() {
+            result = self.single_step(tid).map(|_| ());
+            // the trap goes back even if the step failed, through a thread still there
+            let via = match self.inferior.threads.get(tid) {
+                Some(_) => Some(tid),
+                None => self.inferior.threads.tids().first().copied(),
+            };
+            if let (Some(via), Some(bp)) = (via, self.inferior.breakpoints.get_mut(&current_line)) {
+                result = result.and(bp.enable(via));
+            }
+        }
+
+        // the threads stopped for the step go on
+        for other in running {
+            if self
+                .inferior
+                .threads
+                .get(other)
+                .is_some_and(|thread| thread.state == ThreadState::Stopped)
+                && self.resume_thread(other, None).is_ok()
+            {
+                match self.inferior.threads.get_mut(other) {
+                    Some(thread) => thread.state = ThreadState::Running,
+                    None => eprintln!("{}", DebuggerError::thread_gone(other)),
+                }
+            }
+        }
+        result
+    }
+
+    /// Executes one instruction of `tid`. `false` when it wasn't executed: the thread ended,
+    /// which is handled, or the instruction raised a fault, kept for when the thread is resumed.
+    fn single_step(&mut self, tid: Pid) -> Result<bool, DebuggerError> {
+        let lost = |errno| DebuggerError::Thread { tid, errno };
+        loop {
+            self.live.step(tid, None).map_err(lost)?;
+            let status = self
+                .live
+                .wait(Some(tid), Some(WaitPidFlag::__WALL))
+                .map_err(lost)?;
+            let Some(thread) = self.inferior.threads.get_mut(tid) else {
+                return Ok(true);
+            };
+            match status {
+                // a pending stop request comes before the step, the step has to be done again
+                status if thread.stop_expected && is_stop_request(&status) => {
+                    thread.stop_expected = false;
+                }
+                WaitStatus::Stopped(_, signal) if signal != Signal::SIGTRAP => {
+                    // a signal came before the instruction ran, it is delivered once the thread
+                    // is resumed like those received while threads are stopped
+                    let policy = self.signals.get(signal);
+                    if policy.pass {
+                        thread.pending_signal = Some(signal);
+                    }
+                    if policy.print {
+                        self.print_signal(tid, signal);
+                    }
+                    // the fault of an instruction comes again each time it is executed
+                    if matches!(
+                        signal,
+                        Signal::SIGSEGV | Signal::SIGBUS | Signal::SIGILL | Signal::SIGFPE
+                    ) {
+                        return Ok(false);
+                    }
+                }
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                    if let Some(pid) = self.inferior.pid {
+                        let _ = self.handle_status(pid, status);
+                    }
+                    return Ok(false);
+                }
+                _ => return Ok(true),
+            }
+        }
+    }
+
+    /// Displaced stepping: executes a copy of the instruction replaced by the trap at `address`
+    /// from the program's entry point, code that never runs again once the program started.
+    /// `false` when the instruction can't be moved there.
+    fn displaced_step(&mut self, tid: Pid, address: u64) -> Result<bool, DebuggerError> {
+        let Some(scratch) = self
+            .inferior
+            .modules
+            .first()
+            .map(|program| program.to_runtime(program.object.entry))
+        else {
+            return Ok(false);
+        };
+        let Some(bp) = self.inferior.breakpoints.get(&address) else {
+            return Ok(false);
+        };
+        let saved = bp.saved_bytes();
+        let mut code = self.target().read_memory(tid, address, MAX_INSTRUCTION_LEN);
+        if code.len() < saved.len() {
+            return Ok(false);
+        }
+        code[..saved.len()].copy_from_slice(&saved);
+        let Some(relocated) = self.arch.relocate_instruction(&code, address, scratch) else {
+            return Ok(false);
+        };
+        let scratch_end = scratch + relocated.bytes.len() as u64;
+        if self
+            .inferior
+            .breakpoints
+            .keys()
+            .any(|bp_address| (scratch..scratch_end).contains(bp_address))
+        {
+            return Ok(false);
+        }
+        let original = self
+            .target()
+            .read_memory(tid, scratch, relocated.bytes.len());
+        if original.len() < relocated.bytes.len()
+            || self
+                .live
+                .write_memory(tid, scratch, &relocated.bytes)
+                .is_err()
+        {
+            return Ok(false);
+        }
+
+        let stepped = self.step_copy(tid, address, scratch, &relocated);
+        // the code at the entry point is put back whatever happened
+        let _ = self.live.write_memory(tid, scratch, &original);
+        stepped.map(|()| true)
+    }
+
+    /// Steps `relocated`, the copy at `scratch` of the instruction at `address`, then takes
+    /// `tid` back to the original code
+    fn step_copy(
+        &mut self,
+        tid: Pid,
+        address: u64,
+        scratch: u64,
+        relocated: &RelocatedInstruction,
+    ) -> Result<(), DebuggerError> {
+        let scratch_end = scratch + relocated.bytes.len() as u64;
+        self.set_pc(tid, scratch)?;
+        if !self.single_step(tid)? && self.inferior.threads.get(tid).is_none() {
+            // the thread ended
+            return Ok(());
+        }
+        let pc = self.get_pc(tid)?;
+        if pc == scratch_end {
+            self.set_pc(tid, address + relocated.len as u64)?;
+        } else if pc == scratch {
+            // the instruction didn't complete, it will run again from its own address once the
+            // signal that stopped it, kept by the thread, is delivered
+            self.set_pc(tid, address)?;
+        }
+        if relocated.is_call {
+            // the return address pushed is the one following the copy
+            let sp = self.register_value(tid, self.arch.sp_reg())?;
+            if self.target().read_memory(tid, sp, 8) == scratch_end.to_ne_bytes() {
+                let return_address = (address + relocated.len as u64).to_ne_bytes();
+                self.live
+                    .write_memory(tid, sp, &return_address)
+                    .map_err(|errno| DebuggerError::Memory { address: sp, errno })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn dump_registers(&self, pid: Pid) -> Result<(), DebuggerError> {
+        for desc in self.arch.registers() {
+            let val = self.shown_register(pid, desc.r)?;
+            println!("{} 0x{:016x}", desc.name, val);
+        }
+        Ok(())
+    }
+
+    fn register_value(&self, tid: Pid, r: Reg) -> Result<u64, DebuggerError> {
+        read_register(self.target(), self.arch, tid, r).map_err(|errno| DebuggerError::Register {
+            tid,
+            reg: r,
+            errno,
+        })
+    }
+
+    fn set_register(&self, tid: Pid, r: Reg, value: u64) -> Result<(), DebuggerError> {
+        write_register(self.target(), self.arch, tid, r, value)
+            .map_err(|errno| DebuggerError::Register { tid, reg: r, errno })
+    }
+
+    fn get_pc(&self, pid: Pid) -> Result<u64, DebuggerError> {
+        self.register_value(pid, self.arch.pc_reg())
+    }
+
+    fn set_pc(&self, pid: Pid, pc: u64) -> Result<(), DebuggerError> {
+        self.set_register(pid, self.arch.pc_reg(), pc)
+    }
+
+    /// A register of `tid` as the user sees it: the program counter of a thread that hit a
+    /// breakpoint is the address of the breakpoint, like in backtraces
+    fn shown_register(&self, tid: Pid, r: Reg) -> Result<u64, DebuggerError> {
+        match self.hit_trap(tid) {
+            Some(trap_addr) if r == self.arch.pc_reg() => Ok(trap_addr),
+            _ => self.register_value(tid, r),
+        }
+    }
+
+    /// Where `tid` is stopped, or why that can't be told
+    fn describe_pc(&self, tid: Pid) -> String {
+        match self.shown_register(tid, self.arch.pc_reg()) {
+            Ok(pc) => self.describe_address(pc),
+            Err(err) => format!("an unknown address ({err})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        launch::{LaunchError, Launched},
+        target::MockTarget,
+    };
+    use lazy_static::lazy_static;
+    use std::{
+        collections::{HashMap, VecDeque},
+        fs::File,
+        io::{BufRead, BufReader, Write},
+        os::unix::fs::FileExt,
+        process::{self, Stdio},
+        sync::{Mutex, PoisonError},
+    };
+
+    lazy_static! {
+        /// Held by the tests tracing programs: the debugger waits for any child, including
+        /// those of another test
+        static ref TRACING: Mutex<()> = Mutex::new(());
+    }
+
+    /// Compiles a C program loaded at a fixed address, so breakpoints can be set before it runs
+    fn build_program(name: &str, source: &str) -> String {
+        compile(name, source, "-no-pie")
+    }
+
+    /// Compiles `source` as `build_program` does, `pie` telling how it is linked
+    fn compile(name: &str, source: &str, pie: &str) -> String {
+        let program = std::env::temp_dir().join(format!("vdebugger-test-{name}"));
+        let mut cc = process::Command::new("cc")
+            .args(["-g", "-O0", pie, "-x", "c", "-", "-o"])
+            .arg(&program)
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("cannot run cc");
+        cc.stdin
+            .take()
+            .unwrap()
+            .write_all(source.as_bytes())
+            .unwrap();
+        assert!(cc.wait().unwrap().success(), "cannot compile {name}");
+        program.to_string_lossy().into_owned()
+    }
+
+    /// The address where `program` starts, read from its ELF header
+    fn entry_point(program: &str) -> u64 {
+        let header = std::fs::read(program).unwrap();
+        u64::from_le_bytes(header[24..32].try_into().unwrap())
+    }
+
+    /// The byte at `address` in the memory of `pid`
+    fn memory_byte(pid: u32, address: u64) -> u8 {
+        let mut byte = [0];
+        File::open(format!("/proc/{pid}/mem"))
+            .and_then(|mem| mem.read_exact_at(&mut byte, address))
+            .unwrap();
+        byte[0]
+    }
+
+    #[test]
+    fn test_breakpoints_kept_across_runs() {
+        let _tracing = TRACING.lock().unwrap_or_else(PoisonError::into_inner);
+        let program = build_program("exit", "int main(void) { return 0; }");
+        let entry = entry_point(&program);
+        let mut config = LaunchConfig::new(program, vec![]);
+        config.terminal = TerminalMode::Inherit;
+        let mut debugger = Debugger::new(config);
+        let hit = entry + debugger.arch.trap_pc_adjustment();
+
+        // set before the program runs, the breakpoint is inserted by every run
+        debugger.handle_command(&format!("break 0x{entry:x}"));
+        debugger.handle_command("run");
+        let first = debugger.inferior.pid.unwrap();
+        assert_eq!(debugger.get_pc(first), Ok(hit));
+
+        debugger.handle_command("kill");
+        assert_eq!(debugger.inferior.pid, None);
+        assert!(debugger.inferior.breakpoints.is_empty());
+
+        debugger.handle_command("run");
+        let second = debugger.inferior.pid.unwrap();
+        assert_ne!(second, first);
+        assert_eq!(debugger.get_pc(second), Ok(hit));
+
+        // the running process is killed before starting again
+        debugger.handle_command("restart");
+        let third = debugger.inferior.pid.unwrap();
+        assert_ne!(third, second);
+        assert_eq!(debugger.get_pc(third), Ok(hit));
+
+        debugger.handle_command("continue");
+        assert_eq!(debugger.inferior.pid, None);
+        assert_eq!(debugger.locations.len(), 1);
+    }
+
+    #[test]
+    fn test_detach_removes_traps() {
+        let _tracing = TRACING.lock().unwrap_or_else(PoisonError::into_inner);
+        let program = build_program(
+            "tick",
+            r#"
+            #include <stdio.h>
+            #include <unistd.h>
+            void tick(void) {}
+            int main(void) {
+                printf("%p\n", (void *)tick);
+                fflush(stdout);
+                for (int i = 0; i < 20; i++) {
+                    tick();
+                    usleep(50000);
+                }
+                return 0;
+            }
+            "#,
+        );
+        let mut child = process::Command::new(program)
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut line = String::new();
+        BufReader::new(child.stdout.take().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        let tick = str_to_reg_value(line.trim()).unwrap();
+        let original = memory_byte(child.id(), tick);
+
+        let mut debugger = Debugger::new(LaunchConfig::new(String::new(), vec![]));
+        debugger.handle_command(&format!("attach {}", child.id()));
+        debugger.handle_command(&format!("break 0x{tick:x}"));
+        assert_ne!(memory_byte(child.id(), tick), original);
+        debugger.handle_command("continue");
+        let pid = debugger.inferior.pid.unwrap();
+        assert_eq!(
+            debugger.get_pc(pid),
+            Ok(tick + debugger.arch.trap_pc_adjustment())
+        );
+
+        debugger.handle_command("detach");
+        assert_eq!(debugger.inferior.pid, None);
+        assert!(debugger.inferior.breakpoints.is_empty());
+        assert_eq!(memory_byte(child.id(), tick), original);
+        // it goes on at the instruction the trap replaced, up to its normal end
+        assert!(child.wait().unwrap().success());
+    }
+
+    /// Forks a child that runs /bin/true
+    const FORK_EXEC: &str = r#"
+        #include <sys/wait.h>
+        #include <unistd.h>
+        void forked(void) {}
+        int main(void) {
+            if (fork() == 0) {
+                execl("/bin/true", "true", (char *)0);
+                return 1;
+            }
+            forked();
+            wait(0);
+            return 0;
+        }
+    "#;
+
+    #[test]
+    fn test_keep_forked_child() {
+        let _tracing = TRACING.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut config = LaunchConfig::new(build_program("fork", FORK_EXEC), vec![]);
+        config.terminal = TerminalMode::Inherit;
+        let mut debugger = Debugger::new(config);
+
+        debugger.handle_command("set detach-on-fork off");
+        debugger.handle_command("break forked");
+        debugger.handle_command("run");
+        // the parent is still debugged, the child is a new inferior kept stopped
+        let pid = debugger.inferior.pid.unwrap();
+        assert_eq!(debugger.inferior.threads.tids(), vec![pid]);
+        assert_eq!(debugger.inferiors.len(), 1);
+        let child = debugger.inferiors[0].pid.unwrap();
+        assert_ne!(child, pid);
+
+        debugger.handle_command("kill");
+        debugger.handle_command("inferior 2");
+        assert_eq!(debugger.inferior.pid, Some(child));
+        debugger.handle_command("kill");
+        assert!(debugger
+            .inferiors
+            .iter()
+            .all(|inferior| inferior.pid.is_none()));
+        assert_eq!(debugger.inferior.pid, None);
+    }
+
+    #[test]
+    fn test_follow_fork_and_exec() {
+        let _tracing = TRACING.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut config = LaunchConfig::new(build_program("exec", FORK_EXEC), vec![]);
+        config.terminal = TerminalMode::Inherit;
+        let mut debugger = Debugger::new(config);
+
+        debugger.handle_command("set follow-fork-mode child");
+        debugger.handle_command("run");
+        // the child ran its new program to the end, the next run starts that program
+        assert_eq!(debugger.inferior.pid, None);
+        assert_eq!(
+            PathBuf::from(&debugger.launch.program),
+            std::fs::canonicalize("/bin/true").unwrap()
+        );
+    }
+
+    const PID: Pid = Pid::from_raw(4242);
+
+    /// A process that only exists behind a mocked target
+    #[derive(Default)]
+    struct FakeProcess {
+        regs: Vec<u64>,
+        memory: HashMap<u64, u8>,
+        /// What the process reports each time it is waited for, and where it stopped
+        stops: VecDeque<(WaitStatus, u64)>,
+        resumed: usize,
+        /// The signals delivered when the process was resumed
+        delivered: Vec<Signal>,
+        /// The byte under the program counter each time an instruction is stepped
+        stepped: Vec<u8>,
+        /// The process was killed behind the debugger's back, its threads can't be reached
+        gone: bool,
+        /// The threads of the process besides its leader, as listed in `/proc`
+        tasks: Vec<Pid>,
+        /// What comes with each ptrace event reported, in order
+        messages: VecDeque<i64>,
+        /// The signals sent to threads, and the threads let go with the signal they got
+        signalled: Vec<(Pid, Signal)>,
+        detached: Vec<(Pid, Option<Signal>)>,
+        /// The processes started by `run`, in order, each with this memory at its exec
+        children: VecDeque<Pid>,
+        image: HashMap<u64, u8>,
+        /// The program the process runs, `/bin/prog` when not set, and what the kernel told it
+        program: Option<PathBuf>,
+        auxv: Vec<(u64, u64)>,
+    }
+
+    impl FakeProcess {
+        fn index(r: Reg) -> usize {
+            let desc = arch::native().registers().iter().find(|desc| desc.r == r);
+            desc.unwrap().index
+        }
+
+        fn pc_index() -> usize {
+            Self::index(arch::native().pc_reg())
+        }
+    }
+
+    fn mock_target(process: &Arc<Mutex<FakeProcess>>) -> MockTarget {
+        let mut target = MockTarget::new();
+        let p = process.clone();
+        target.expect_get_regs().returning(move |_, len| {
+            if p.lock().unwrap().gone {
+                return Err(Errno::ESRCH);
+            }
+            let mut regs = p.lock().unwrap().regs.clone();
+            regs.resize(len, 0);
+            Ok(regs)
+        });
+        let p = process.clone();
+        target.expect_set_regs().returning(move |_, regs| {
+            p.lock().unwrap().regs = regs.to_vec();
+            Ok(())
+        });
+        let p = process.clone();
+        target
+            .expect_read_memory()
+            .returning(move |_, address, len| {
+                let memory = &p.lock().unwrap().memory;
+                (address..address + len as u64)
+                    .map_while(|address| memory.get(&address).copied())
+                    .collect()
+            });
+        let p = process.clone();
+        target
+            .expect_write_memory()
+            .returning(move |_, address, bytes| {
+                let memory = &mut p.lock().unwrap().memory;
+                for (address, &byte) in (address..).zip(bytes) {
+                    memory.insert(address, byte);
+                }
+                Ok(())
+            });
+        target.expect_maps().returning(|_| {
+            Ok(vec![MemoryMap {
+                start: 0,
+                end: 0x10000,
+                perms: "rwxp".to_string(),
+                offset: 0,
+                device: "00:00".to_string(),
+                inode: 0,
+                pathname: None,
+            }])
+        });
+        let p = process.clone();
+        target.expect_resume().returning(move |_, signal| {
+            let mut process = p.lock().unwrap();
+            process.resumed += 1;
+            process.delivered.extend(signal);
+            Ok(())
+        });
+        let p = process.clone();
+        target
+            .expect_resume_to_syscall()
+            .returning(move |_, signal| {
+                let mut process = p.lock().unwrap();
+                process.resumed += 1;
+                process.delivered.extend(signal);
+                Ok(())
+            });
+        let p = process.clone();
+        target.expect_step().returning(move |_, _| {
+            let mut process = p.lock().unwrap();
+            let pc = process.regs[FakeProcess::pc_index()];
+            let byte = process.memory[&pc];
+            process.stepped.push(byte);
+            Ok(())
+        });
+        let p = process.clone();
+        target.expect_tasks().returning(move |pid| {
+            let mut tasks = p.lock().unwrap().tasks.clone();
+            tasks.insert(0, pid);
+            Ok(tasks)
+        });
+        let p = process.clone();
+        target
+            .expect_event_message()
+            .returning(move |_| p.lock().unwrap().messages.pop_front().ok_or(Errno::EINVAL));
+        let p = process.clone();
+        target
+            .expect_kill_thread()
+            .returning(move |_, tid, signal| {
+                p.lock().unwrap().signalled.push((tid, signal));
+                Ok(())
+            });
+        let p = process.clone();
+        target.expect_kill().returning(move |pid, signal| {
+            p.lock().unwrap().signalled.push((pid, signal));
+            Ok(())
+        });
+        let p = process.clone();
+        target.expect_detach().returning(move |tid, signal| {
+            p.lock().unwrap().detached.push((tid, signal));
+            Ok(())
+        });
+        target.expect_seize().returning(|_, _| Ok(()));
+        target.expect_set_options().returning(|_, _| Ok(()));
+        target.expect_interrupt().returning(|_| Ok(()));
+        target.expect_siginfo().returning(|_| Err(Errno::EINVAL));
+        target.expect_syscall_entry().returning(|_| None);
+        target
+            .expect_read_regset()
+            .returning(|_, _| Err(Errno::EINVAL));
+        let p = process.clone();
+        target
+            .expect_set_syscall_number()
+            .returning(move |_, number| {
+                let index = FakeProcess::index(arch::native().syscall_number_reg());
+                p.lock().unwrap().regs[index] = number;
+                Ok(())
+            });
+        let p = process.clone();
+        target.expect_executable().returning(move |_| {
+            let program = p.lock().unwrap().program.clone();
+            Ok(program.unwrap_or_else(|| PathBuf::from("/bin/prog")))
+        });
+        let p = process.clone();
+        target
+            .expect_auxv()
+            .returning(move |_| Ok(p.lock().unwrap().auxv.clone()));
+        target
+            .expect_thread_name()
+            .returning(|_, _| Ok("prog".to_string()));
+        target.expect_stat_ids().returning(|_| Ok([1, 4242, 4242]));
+        target.expect_owner().returning(|_| Ok((1000, 100)));
+        target
+            .expect_command_line()
+            .returning(|_| Ok("prog --verbose".to_string()));
+        target.expect_cwd().returning(|_| Ok(PathBuf::from("/")));
+        let p = process.clone();
+        target.expect_read_mapping().returning(move |_, map| {
+            // what was never written reads as zeros, like fresh anonymous memory
+            let memory = &p.lock().unwrap().memory;
+            Ok((map.start..map.end)
+                .map(|address| memory.get(&address).copied().unwrap_or(0))
+                .collect())
+        });
+        let p = process.clone();
+        target.expect_spawn().returning(move |_| {
+            let mut process = p.lock().unwrap();
+            let pid = process
+                .children
+                .pop_front()
+                .ok_or(LaunchError::InvalidArgument)?;
+            process.memory = process.image.clone();
+            Ok(Launched { pid, pty: None })
+        });
+        let p = process.clone();
+        target.expect_wait().returning(move |_, _| {
+            let mut process = p.lock().unwrap();
+            let (status, pc) = process.stops.pop_front().ok_or(Errno::ECHILD)?;
+            process.regs[FakeProcess::pc_index()] = pc;
+            Ok(status)
+        });
+        target
+    }
+
+    /// A debugger whose debuggee is stopped at `pc`
+    fn stopped_debugger(process: &Arc<Mutex<FakeProcess>>, pc: u64) -> Debugger {
+        {
+            let mut process = process.lock().unwrap();
+            process.regs = vec![0; arch::native().regset_len()];
+            process.regs[FakeProcess::pc_index()] = pc;
+        }
+        let launch = LaunchConfig::new(String::new(), vec![]);
+        let mut debugger = Debugger::with_target(launch, Rc::new(mock_target(process)));
+        debugger.inferior.pid = Some(PID);
+        debugger.inferior.threads.add(PID, ThreadState::Stopped);
+        debugger.inferior.current_thread = Some(PID);
+        debugger
+    }
+
+    #[test]
+    fn test_write_commands() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x1000);
+        let pc_name = format!("{:?}", debugger.arch.pc_reg());
+
+        debugger.handle_command(&format!("register write {pc_name} 0x2000"));
+        debugger.handle_command("memory write 0x3000 0x1122");
+        let process = process.lock().unwrap();
+        assert_eq!(process.regs[FakeProcess::pc_index()], 0x2000);
+        assert_eq!(process.memory[&0x3000], 0x22);
+        assert_eq!(process.memory[&0x3001], 0x11);
+        assert_eq!(process.memory[&0x3007], 0);
+    }
+
+    #[test]
+    fn test_continue_to_breakpoint() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        process
+            .lock()
+            .unwrap()
+            .memory
+            .extend((0x1000..0x1008).map(|address| (address, 0x90)));
+        let mut debugger = stopped_debugger(&process, 0x500);
+        let trap = debugger.arch.breakpoint_instruction()[0];
+        let hit = 0x1000 + debugger.arch.trap_pc_adjustment();
+
+        debugger.handle_command("break 0x1000");
+        assert_eq!(process.lock().unwrap().memory[&0x1000], trap);
+
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Stopped(PID, Signal::SIGTRAP), hit),
+            // the instruction under the trap is stepped, then the process ends
+            (WaitStatus::Stopped(PID, Signal::SIGTRAP), 0x1001),
+            (WaitStatus::Exited(PID, 0), 0),
+        ]);
+        debugger.handle_command("continue");
+        let thread = debugger.inferior.threads.get(PID).unwrap();
+        assert_eq!(thread.state, ThreadState::Stopped);
+        assert_eq!(debugger.get_pc(PID), Ok(hit));
+        assert_eq!(process.lock().unwrap().resumed, 1);
+
+        debugger.handle_command("continue");
+        assert_eq!(debugger.inferior.pid, None);
+        let process = process.lock().unwrap();
+        // the original instruction was stepped, and the trap put back
+        assert_eq!(process.stepped, vec![0x90]);
+        assert_eq!(process.memory[&0x1000], trap);
+        assert_eq!(process.resumed, 2);
+    }
+
+    /// A debugger stopped at `pc` with a breakpoint at 0x1000, over a nop
+    fn debugger_with_breakpoint(process: &Arc<Mutex<FakeProcess>>, pc: u64) -> Debugger {
+        process
+            .lock()
+            .unwrap()
+            .memory
+            .extend((0x1000..0x1008).map(|address| (address, 0x90)));
+        let mut debugger = stopped_debugger(process, pc);
+        debugger.handle_command("break 0x1000");
+        debugger
+    }
+
+    #[test]
+    fn test_stop_at_address_zero() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let hit = 0x1000 + debugger.arch.trap_pc_adjustment();
+        let pc_name = format!("{:?}", debugger.arch.pc_reg());
+
+        // a call through a null pointer
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGSEGV), 0));
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(PID, StopReason::Signal(Signal::SIGSEGV)))
+        );
+        assert_eq!(debugger.read_register(debugger.arch.pc_reg()), Ok(0));
+
+        // the program counter of a thread that hit a breakpoint is moved to 0
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Stopped(PID, Signal::SIGTRAP), hit),
+            (WaitStatus::Stopped(PID, Signal::SIGSEGV), 0),
+        ]);
+        debugger.handle_command(&format!("register write {pc_name} 0x{hit:x}"));
+        debugger.handle_command("continue");
+        debugger.handle_command(&format!("register write {pc_name} 0x0"));
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(PID, StopReason::Signal(Signal::SIGSEGV)))
+        );
+        assert!(process.lock().unwrap().stepped.is_empty());
+    }
+
+    #[test]
+    fn test_step_over_reported_trap_only() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        // stopped just past the trap without having been reported hitting it: it ran the
+        // instruction the trap replaced already, or was interrupted there
+        let adjustment = arch::native().trap_pc_adjustment();
+        let mut debugger = debugger_with_breakpoint(&process, 0x1000 + adjustment);
+        assert_eq!(
+            debugger.read_register(debugger.arch.pc_reg()),
+            Ok(0x1000 + adjustment)
+        );
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Exited(PID, 0), 0));
+        debugger.handle_command("continue");
+        assert_eq!(debugger.inferior.pid, None);
+        assert!(process.lock().unwrap().stepped.is_empty());
+    }
+
+    #[test]
+    fn test_signal_during_step_over() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let hit = 0x1000 + debugger.arch.trap_pc_adjustment();
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGTRAP), hit));
+        debugger.handle_command("continue");
+        // every view shows the thread at the breakpoint
+        assert_eq!(debugger.read_register(debugger.arch.pc_reg()), Ok(0x1000));
+        assert_eq!(debugger.describe_pc(PID), debugger.describe_address(0x1000));
+
+        process.lock().unwrap().stops.extend([
+            // a child ended while the instruction under the trap was stepped
+            (WaitStatus::Stopped(PID, Signal::SIGCHLD), 0x1000),
+            (WaitStatus::Stopped(PID, Signal::SIGTRAP), 0x1001),
+            (WaitStatus::Exited(PID, 0), 0),
+        ]);
+        debugger.handle_command("continue");
+        // the step was done again, the trap was not hit a second time
+        assert_eq!(debugger.inferior.pid, None);
+        let process = process.lock().unwrap();
+        assert_eq!(process.stepped, vec![0x90, 0x90]);
+        assert_eq!(process.delivered, vec![Signal::SIGCHLD]);
+    }
+
+    #[test]
+    fn test_thread_created_and_exit() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x500);
+        let new = Pid::from_raw(4243);
+        debugger.handle_command("catch clone");
+        debugger.handle_command("catch exit");
+
+        {
+            let mut process = process.lock().unwrap();
+            process.messages.push_back(new.as_raw() as i64);
+            process.stops.extend([
+                (
+                    WaitStatus::PtraceEvent(PID, Signal::SIGTRAP, libc::PTRACE_EVENT_CLONE),
+                    0x510,
+                ),
+                // the first stop of the new thread, waited for when the process is stopped
+                (WaitStatus::Stopped(new, Signal::SIGSTOP), 0x800),
+            ]);
+        }
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(
+                PID,
+                StopReason::Event {
+                    catchpoint: 1,
+                    event: ProcessEvent::Clone(new)
+                }
+            ))
+        );
+        assert_eq!(debugger.threads(), vec![PID, new]);
+        assert!(debugger
+            .inferior
+            .threads
+            .iter()
+            .all(|thread| thread.state == ThreadState::Stopped));
+
+        {
+            let mut process = process.lock().unwrap();
+            process.messages.push_back(3 << 8);
+            process.stops.extend([
+                (WaitStatus::Exited(new, 0), 0),
+                (
+                    WaitStatus::PtraceEvent(PID, Signal::SIGTRAP, libc::PTRACE_EVENT_EXIT),
+                    0x520,
+                ),
+            ]);
+        }
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(
+                PID,
+                StopReason::Event {
+                    catchpoint: 2,
+                    event: ProcessEvent::Exit(3 << 8)
+                }
+            ))
+        );
+        assert_eq!(debugger.threads(), vec![PID]);
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Exited(PID, 3), 0));
+        debugger.handle_command("continue");
+        assert_eq!(debugger.pid(), None);
+        assert!(process.lock().unwrap().signalled.is_empty());
+    }
+
+    #[test]
+    fn test_kill_and_restart_keep_breakpoints() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let trap = debugger.arch.breakpoint_instruction()[0];
+        let hit = 0x1000 + debugger.arch.trap_pc_adjustment();
+        let (first, second) = (Pid::from_raw(4300), Pid::from_raw(4301));
+        {
+            let mut process = process.lock().unwrap();
+            process.image = process.memory.clone();
+            process.children.extend([first, second]);
+        }
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Signaled(PID, Signal::SIGKILL, false), 0));
+        debugger.handle_command("kill");
+        assert_eq!(debugger.pid(), None);
+        assert_eq!(debugger.breakpoints(), Vec::<u64>::new());
+        assert_eq!(
+            process.lock().unwrap().signalled,
+            vec![(PID, Signal::SIGKILL)]
+        );
+
+        // the new process gets the trap again and hits it
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Stopped(first, Signal::SIGTRAP), 0x400),
+            (WaitStatus::Stopped(first, Signal::SIGTRAP), hit),
+        ]);
+        debugger.handle_command("run");
+        assert_eq!(debugger.pid(), Some(first));
+        assert_eq!(debugger.breakpoints(), vec![0x1000]);
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(first, StopReason::Signal(Signal::SIGTRAP)))
+        );
+        assert_eq!(debugger.read_register(debugger.arch.pc_reg()), Ok(0x1000));
+
+        // restarting kills the process on the breakpoint, the next one starts with the trap
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Signaled(first, Signal::SIGKILL, false), 0),
+            (WaitStatus::Stopped(second, Signal::SIGTRAP), 0x400),
+            (WaitStatus::Exited(second, 0), 0),
+        ]);
+        debugger.handle_command("restart");
+        assert_eq!(debugger.pid(), None);
+        let process = process.lock().unwrap();
+        assert_eq!(
+            process.signalled,
+            vec![(PID, Signal::SIGKILL), (first, Signal::SIGKILL)]
+        );
+        assert_eq!(process.memory[&0x1000], trap);
+        assert!(process.stepped.is_empty());
+    }
+
+    #[test]
+    fn test_detach_delivers_queued_signal() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let hit = 0x1000 + debugger.arch.trap_pc_adjustment();
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGTRAP), hit));
+        debugger.handle_command("continue");
+        debugger.handle_command("queue-signal SIGUSR1");
+        debugger.handle_command("detach");
+
+        assert_eq!(debugger.pid(), None);
+        let process = process.lock().unwrap();
+        // the process goes on at the instruction the trap replaced, with the signal it got
+        assert_eq!(process.memory[&0x1000], 0x90);
+        assert_eq!(process.regs[FakeProcess::pc_index()], 0x1000);
+        assert_eq!(process.detached, vec![(PID, Some(Signal::SIGUSR1))]);
+        assert!(process.signalled.is_empty());
+    }
+
+    /// A debugger in non-stop mode with `stopped` and `running` threads besides the leader,
+    /// itself stopped at `pc`
+    fn non_stop_debugger(
+        process: &Arc<Mutex<FakeProcess>>,
+        pc: u64,
+        stopped: &[Pid],
+        running: &[Pid],
+    ) -> Debugger {
+        let mut debugger = stopped_debugger(process, pc);
+        debugger.non_stop = true;
+        for &tid in stopped {
+            debugger.inferior.threads.add(tid, ThreadState::Stopped);
+        }
+        for &tid in running {
+            debugger.inferior.threads.add(tid, ThreadState::Running);
+        }
+        debugger
+    }
+
+    #[test]
+    fn test_continue_stopped_threads() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let (stopped, running) = (Pid::from_raw(4243), Pid::from_raw(4244));
+        let mut debugger = non_stop_debugger(&process, 0x500, &[stopped], &[running]);
+        let state = |debugger: &Debugger, tid| debugger.inferior.threads.get(tid).unwrap().state;
+
+        // only the selected thread
+        debugger.handle_command("continue");
+        assert_eq!(process.lock().unwrap().resumed, 1);
+        assert_eq!(state(&debugger, PID), ThreadState::Running);
+        assert_eq!(state(&debugger, stopped), ThreadState::Stopped);
+
+        // every stopped thread, the running ones are left alone
+        debugger.handle_command("continue -a");
+        assert_eq!(process.lock().unwrap().resumed, 2);
+        assert_eq!(state(&debugger, stopped), ThreadState::Running);
+        assert_eq!(debugger.inferior.threads.running().len(), 3);
+
+        debugger.handle_command("continue -a");
+        assert_eq!(process.lock().unwrap().resumed, 2);
+    }
+
+    #[test]
+    fn test_interrupt_running_threads() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let (running, stopped) = (Pid::from_raw(4243), Pid::from_raw(4244));
+        let mut debugger = non_stop_debugger(&process, 0x500, &[stopped], &[running]);
+        debugger.inferior.threads.get_mut(PID).unwrap().state = ThreadState::Running;
+
+        // only the selected thread
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGSTOP), 0x510));
+        debugger.handle_command("interrupt");
+        assert_eq!(
+            process.lock().unwrap().signalled,
+            vec![(PID, Signal::SIGSTOP)]
+        );
+        assert_eq!(debugger.inferior.threads.running(), vec![running]);
+
+        // every running thread, not the stopped ones
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(running, Signal::SIGSTOP), 0x520));
+        debugger.handle_command("interrupt -a");
+        assert_eq!(
+            process.lock().unwrap().signalled,
+            vec![(PID, Signal::SIGSTOP), (running, Signal::SIGSTOP)]
+        );
+        assert!(debugger.inferior.threads.running().is_empty());
+        assert!(debugger
+            .inferior
+            .threads
+            .get(stopped)
+            .unwrap()
+            .pending_signal
+            .is_none());
+    }
+
+    #[test]
+    fn test_displaced_step_of_call() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let (entry, stack) = (0x2000, 0x3000);
+        {
+            let mut process = process.lock().unwrap();
+            // call 0x1105, and the entry point where it is copied to be stepped
+            process
+                .memory
+                .extend((0x1000..).zip([0xe8, 0, 1, 0, 0, 0x90, 0x90, 0x90]));
+            process
+                .memory
+                .extend((entry..entry + 8).map(|address| (address, 0xf4)));
+        }
+        let other = Pid::from_raw(4243);
+        let mut debugger = non_stop_debugger(&process, 0x500, &[], &[other]);
+        let trap = debugger.arch.breakpoint_instruction()[0];
+        debugger.handle_command("break 0x1000");
+        let mut program = ObjectFile::default();
+        program.entry = entry;
+        debugger.inferior.modules = vec![Module::new(program, 0)];
+
+        {
+            let mut process = process.lock().unwrap();
+            let (pc, sp) = (
+                FakeProcess::pc_index(),
+                FakeProcess::index(arch::native().sp_reg()),
+            );
+            process.regs[pc] = 0x1000 + arch::native().trap_pc_adjustment();
+            process.regs[sp] = stack;
+            // the step pushes the address following the copy
+            process
+                .memory
+                .extend((stack..).zip((entry + 5).to_ne_bytes()));
+            process
+                .stops
+                .push_back((WaitStatus::Stopped(PID, Signal::SIGTRAP), 0x1105));
+        }
+        debugger.inferior.threads.get_mut(PID).unwrap().hit_trap = true;
+        debugger.handle_command("continue");
+
+        let process = process.lock().unwrap();
+        // the copy was stepped while the other thread ran over the trap still in place
+        assert_eq!(process.stepped, vec![0xe8]);
+        assert!(process.signalled.is_empty());
+        assert_eq!(process.memory[&0x1000], trap);
+        assert!((entry..entry + 8).all(|address| process.memory[&address] == 0xf4));
+        // the call returns after the original instruction
+        let return_address: Vec<u8> = (stack..stack + 8).map(|a| process.memory[&a]).collect();
+        assert_eq!(return_address, 0x1005u64.to_ne_bytes());
+        assert_eq!(process.regs[FakeProcess::pc_index()], 0x1105);
+        assert_eq!(process.resumed, 1);
+    }
+
+    #[test]
+    fn test_signal_redelivered_if_passed() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x500);
+
+        // SIGINT is not passed by default, it is dropped when the thread goes on
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Stopped(PID, Signal::SIGINT), 0x510),
+            (WaitStatus::Stopped(PID, Signal::SIGUSR1), 0x520),
+        ]);
+        debugger.handle_command("continue");
+        debugger.handle_command("continue");
+        assert!(process.lock().unwrap().delivered.is_empty());
+
+        debugger.handle_command("handle SIGINT pass");
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Stopped(PID, Signal::SIGINT), 0x530),
+            (WaitStatus::Exited(PID, 0), 0),
+        ]);
+        // SIGUSR1 is passed, so is SIGINT now
+        debugger.handle_command("continue");
+        debugger.handle_command("continue");
+        assert_eq!(debugger.inferior.pid, None);
+        let process = process.lock().unwrap();
+        assert_eq!(process.resumed, 4);
+        assert_eq!(process.delivered, vec![Signal::SIGUSR1, Signal::SIGINT]);
+    }
+
+    #[test]
+    fn test_signal_command() {
+        // SIGINT is not passed by default, given explicitly it is delivered all the same
+        for (command, signal) in [
+            ("signal SIGUSR1", Some(Signal::SIGUSR1)),
+            ("signal SIGINT", Some(Signal::SIGINT)),
+            ("signal 0", None),
+        ] {
+            let process = Arc::new(Mutex::new(FakeProcess::default()));
+            let mut debugger = stopped_debugger(&process, 0x500);
+            process
+                .lock()
+                .unwrap()
+                .stops
+                .push_back((WaitStatus::Exited(PID, 0), 0));
+
+            debugger.handle_command(command);
+            assert_eq!(debugger.inferior.pid, None, "{command}");
+            let process = process.lock().unwrap();
+            assert_eq!(process.resumed, 1, "{command}");
+            assert_eq!(process.delivered, Vec::from_iter(signal), "{command}");
+        }
+    }
+
+    /// Makes the debuggee fork `child` on the next continue
+    fn fork(process: &Arc<Mutex<FakeProcess>>, child: Pid) {
+        let mut process = process.lock().unwrap();
+        process.messages.push_back(child.as_raw() as i64);
+        process.stops.extend([
+            (
+                WaitStatus::PtraceEvent(PID, Signal::SIGTRAP, libc::PTRACE_EVENT_FORK),
+                0x510,
+            ),
+            (WaitStatus::Stopped(child, Signal::SIGSTOP), 0x510),
+        ]);
+    }
+
+    #[test]
+    fn test_follow_fork_child() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let child = Pid::from_raw(4300);
+        debugger.handle_command("set follow-fork-mode child");
+        debugger.handle_command("catch fork");
+
+        fork(&process, child);
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(
+                child,
+                StopReason::Event {
+                    catchpoint: 1,
+                    event: ProcessEvent::Fork(child)
+                }
+            ))
+        );
+        // the child took the place of its parent, with its breakpoints
+        assert_eq!(debugger.pid(), Some(child));
+        assert_eq!(debugger.threads(), vec![child]);
+        assert_eq!(debugger.inferior.id, 1);
+        assert!(debugger.inferiors.is_empty());
+        assert_eq!(debugger.breakpoints(), vec![0x1000]);
+        assert_eq!(process.lock().unwrap().detached, vec![(PID, None)]);
+    }
+
+    #[test]
+    fn test_forked_child_becomes_inferior() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let (first, second) = (Pid::from_raw(4300), Pid::from_raw(4301));
+
+        // by default the child is let go
+        fork(&process, first);
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGINT), 0x520));
+        debugger.handle_command("continue");
+        assert_eq!(process.lock().unwrap().detached, vec![(first, None)]);
+        assert!(debugger.inferiors.is_empty());
+
+        // it becomes a new inferior, stopped with the breakpoints of its parent
+        debugger.handle_command("set detach-on-fork off");
+        fork(&process, second);
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGINT), 0x530));
+        debugger.handle_command("continue");
+        assert_eq!(debugger.pid(), Some(PID));
+        assert_eq!(debugger.inferiors.len(), 1);
+        let inferior = &debugger.inferiors[0];
+        assert_eq!((inferior.id, inferior.pid), (2, Some(second)));
+        assert_eq!(
+            inferior.breakpoints.keys().collect::<Vec<_>>(),
+            vec![&0x1000]
+        );
+        assert_eq!(
+            inferior.threads.get(second).map(|thread| thread.state),
+            Some(ThreadState::Stopped)
+        );
+        assert_eq!(process.lock().unwrap().detached, vec![(first, None)]);
+    }
+
+    /// A debugger with the breakpoint of `debugger_with_breakpoint` whose debuggee forked
+    /// `child`, kept as the stopped inferior 2 while inferior 1 stays the current one
+    fn debugger_with_child(process: &Arc<Mutex<FakeProcess>>, child: Pid) -> Debugger {
+        let mut debugger = debugger_with_breakpoint(process, 0x500);
+        debugger.handle_command("set detach-on-fork off");
+        fork(process, child);
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Stopped(PID, Signal::SIGINT), 0x520));
+        debugger.handle_command("continue");
+        debugger
+    }
+
+    #[test]
+    fn test_switch_inferior() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let child = Pid::from_raw(4300);
+        let mut debugger = debugger_with_child(&process, child);
+        assert_eq!(debugger.inferior.id, 1);
+
+        debugger.handle_command("inferior 2");
+        assert_eq!(debugger.inferior.id, 2);
+        assert_eq!(debugger.pid(), Some(child));
+        assert_eq!(debugger.threads(), vec![child]);
+        assert_eq!(debugger.current_thread(), Some(child));
+        assert_eq!(debugger.inferiors.len(), 1);
+        assert_eq!(debugger.inferiors[0].pid, Some(PID));
+
+        debugger.handle_command("inferior 1");
+        assert_eq!(debugger.pid(), Some(PID));
+        assert_eq!(debugger.threads(), vec![PID]);
+        assert_eq!(debugger.current_thread(), Some(PID));
+
+        debugger.handle_command("inferior 3");
+        assert_eq!(debugger.pid(), Some(PID));
+    }
+
+    #[test]
+    fn test_breakpoint_in_every_inferior() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_child(&process, Pid::from_raw(4300));
+        process
+            .lock()
+            .unwrap()
+            .memory
+            .extend((0x1008..0x1010).map(|address| (address, 0x90)));
+
+        debugger.handle_command("break 0x1004");
+        assert_eq!(debugger.breakpoints(), vec![0x1000, 0x1004]);
+        let background = &debugger.inferiors[0].breakpoints;
+        assert!(background.get(&0x1004).is_some_and(|bp| bp.enabled));
+    }
+
+    #[test]
+    fn test_background_inferior_exit() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let child = Pid::from_raw(4300);
+        let mut debugger = debugger_with_child(&process, child);
+
+        // the child ends while the current inferior runs, which goes on until its own stop
+        process.lock().unwrap().stops.extend([
+            (WaitStatus::Exited(child, 0), 0),
+            (WaitStatus::Stopped(PID, Signal::SIGUSR1), 0x530),
+        ]);
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(PID, StopReason::Signal(Signal::SIGUSR1)))
+        );
+        assert_eq!(debugger.pid(), Some(PID));
+        assert_eq!(debugger.inferior.id, 1);
+        assert_eq!(debugger.inferiors[0].pid, None);
+        assert!(debugger.inferiors[0].threads.tids().is_empty());
+
+        // nothing is left to switch to
+        debugger.handle_command("inferior 2");
+        assert_eq!(debugger.pid(), None);
+        assert!(debugger.threads().is_empty());
+    }
+
+    #[test]
+    fn test_exit_ends_every_inferior() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let child = Pid::from_raw(4300);
+        let mut debugger = debugger_with_child(&process, child);
+
+        // the prompt returns instead of the whole program exiting
+        debugger.handle_command("exit");
+        assert!(debugger.exiting);
+        assert_eq!(debugger.pid(), None);
+        assert_eq!(debugger.inferiors[0].pid, None);
+        let signalled = &process.lock().unwrap().signalled;
+        assert!(signalled.contains(&(PID, Signal::SIGKILL)));
+        assert!(signalled.contains(&(child, Signal::SIGKILL)));
+    }
+
+    #[test]
+    fn test_exec_reloads_program() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = debugger_with_breakpoint(&process, 0x500);
+        let trap = debugger.arch.breakpoint_instruction()[0];
+        debugger
+            .inferior
+            .threads
+            .add(Pid::from_raw(4243), ThreadState::Stopped);
+        debugger.handle_command("catch exec");
+
+        {
+            let mut process = process.lock().unwrap();
+            // the new program has no trap yet
+            process.memory.insert(0x1000, 0x90);
+            process.stops.push_back((
+                WaitStatus::PtraceEvent(PID, Signal::SIGTRAP, libc::PTRACE_EVENT_EXEC),
+                0x400,
+            ));
+        }
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(
+                PID,
+                StopReason::Event {
+                    catchpoint: 1,
+                    event: ProcessEvent::Exec("/bin/prog".to_string())
+                }
+            ))
+        );
+        // the next run starts the new program
+        assert_eq!(debugger.launch.program, "/bin/prog");
+        assert_eq!(debugger.threads(), vec![PID]);
+        assert_eq!(debugger.breakpoints(), vec![0x1000]);
+        assert_eq!(process.lock().unwrap().memory[&0x1000], trap);
+    }
+
+    #[test]
+    fn test_relocate_position_independent_program() {
+        let program = compile("pie", "int main(void) { return 0; }", "-pie");
+        let object = ObjectFile::load(Path::new(&program)).unwrap();
+        let bias = 0x5555_0000_0000;
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        {
+            let mut process = process.lock().unwrap();
+            process.regs = vec![0; arch::native().regset_len()];
+            process.program = Some(PathBuf::from(&program));
+            process.auxv = vec![
+                (procfs::AT_PHDR, object.phdr_vaddr.unwrap() + bias),
+                (procfs::AT_ENTRY, object.entry + bias),
+            ];
+            process.children.push_back(PID);
+        }
+        let launch = LaunchConfig::new(program, vec![]);
+        let mut debugger = Debugger::with_target(launch, Rc::new(mock_target(&process)));
+        let main: Location = "main".parse().unwrap();
+        let link_main = debugger.resolve_location(&main)[0];
+        debugger.handle_command("break main");
+        process
+            .lock()
+            .unwrap()
+            .image
+            .extend((link_main + bias..).zip([0x90; 8]));
+
+        process.lock().unwrap().stops.extend([
+            (
+                WaitStatus::Stopped(PID, Signal::SIGTRAP),
+                object.entry + bias,
+            ),
+            (
+                WaitStatus::Stopped(PID, Signal::SIGINT),
+                object.entry + bias,
+            ),
+        ]);
+        debugger.handle_command("run");
+        assert_eq!(debugger.pid(), Some(PID));
+        assert_eq!(debugger.inferior.modules[0].bias, bias);
+        assert_eq!(debugger.resolve_location(&main), vec![link_main + bias]);
+        assert_eq!(debugger.breakpoints(), vec![link_main + bias]);
+    }
+
+    #[test]
+    fn test_dump_process() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let debugger = debugger_with_breakpoint(&process, 0x500);
+        process.lock().unwrap().auxv = vec![(procfs::AT_ENTRY, 0x400)];
+
+        let dump = debugger.dump_process(PID, PID).unwrap();
+        assert_eq!(
+            (dump.pid, dump.ppid, dump.pgrp, dump.sid),
+            (4242, 1, 4242, 4242)
+        );
+        assert_eq!((dump.uid, dump.gid), (1000, 100));
+        assert_eq!(dump.name, "prog");
+        assert_eq!(dump.command, "prog --verbose");
+        assert_eq!(
+            procfs::parse_auxv(&dump.auxv),
+            vec![(procfs::AT_ENTRY, 0x400)]
+        );
+        assert_eq!(dump.auxv.len(), 32);
+        let tids: Vec<Pid> = dump.threads.iter().map(|thread| thread.tid).collect();
+        assert_eq!(tids, vec![PID]);
+        // the instruction under the trap is saved
+        let [(map, contents)] = &dump.memory[..] else {
+            panic!("one mapping is dumped");
+        };
+        assert_eq!((map.start, contents.len()), (0, 0x10000));
+        assert_eq!(contents[0x1000..0x1008], [0x90; 8]);
+        assert_eq!(
+            process.lock().unwrap().memory[&0x1000],
+            debugger.arch.breakpoint_instruction()[0]
+        );
+    }
+
+    #[test]
+    fn test_return_from_skipped_syscall() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x500);
+        let return_reg = debugger.arch.syscall_return_reg();
+        debugger.handle_command("catch syscall");
+
+        let stop = (WaitStatus::PtraceSyscall(PID), 0x510);
+        process.lock().unwrap().stops.push_back(stop);
+        debugger.handle_command("continue");
+        debugger.handle_command("syscall skip");
+        // the number register holds -1, the syscall is still the one entered
+        debugger.handle_command("syscall return 5");
+        assert_eq!(debugger.syscall_number(PID), 0);
+        assert_eq!(
+            debugger.inferior.threads.get(PID).unwrap().skipped_syscall,
+            Some((0, 5))
+        );
+
+        process.lock().unwrap().stops.push_back(stop);
+        debugger.handle_command("continue");
+        assert_eq!(
+            debugger.last_stop(),
+            Some(&(
+                PID,
+                StopReason::Syscall {
+                    catchpoint: 1,
+                    number: 0,
+                    entry: false
+                }
+            ))
+        );
+        assert_eq!(debugger.register_value(PID, return_reg), Ok(5));
+        debugger.handle_command("syscall return 7");
+        assert_eq!(debugger.syscall_number(PID), 0);
+        assert_eq!(debugger.register_value(PID, return_reg), Ok(7));
+
+        // the next syscall is reported with its own number
+        process.lock().unwrap().stops.push_back(stop);
+        debugger.handle_command("continue");
+        assert_eq!(debugger.syscall_number(PID), u64::MAX);
+        assert_eq!(
+            debugger.inferior.threads.get(PID).unwrap().skipped_syscall,
+            None
+        );
+    }
+
+    #[test]
+    fn test_entry_point_errors() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x500);
+        assert_eq!(
+            debugger.attach(Pid::from_raw(4300)),
+            Err(DebuggerError::AlreadyDebugging(PID))
+        );
+        process.lock().unwrap().memory.insert(0x2000, 0x90);
+        assert_eq!(debugger.read_memory(0x2000, 1), Ok(vec![0x90]));
+        assert_eq!(
+            debugger.read_memory(0x2000, 2),
+            Err(DebuggerError::UnreadableMemory(0x2001))
+        );
+        let nowhere: Location = "nowhere".parse().unwrap();
+        assert_eq!(
+            debugger.set_breakpoint(nowhere.clone()),
+            Err(DebuggerError::UnresolvedLocation(nowhere))
+        );
+        assert_eq!(
+            debugger.interrupt(false),
+            Err(DebuggerError::NoRunningThread)
+        );
+
+        // in non-stop mode, with every thread running
+        debugger.non_stop = true;
+        debugger.inferior.threads.get_mut(PID).unwrap().state = ThreadState::Running;
+        assert_eq!(
+            debugger.continue_execution(true),
+            Err(DebuggerError::NoStoppedThread)
+        );
+        assert_eq!(
+            debugger.set_breakpoint("0x2000".parse().unwrap()),
+            Err(DebuggerError::NoStoppedThread)
+        );
+        debugger.non_stop = false;
+        debugger.inferior.threads.get_mut(PID).unwrap().state = ThreadState::Stopped;
+
+        process
+            .lock()
+            .unwrap()
+            .stops
+            .push_back((WaitStatus::Signaled(PID, Signal::SIGKILL, false), 0));
+        // the process is killed, the new one can't be started
+        assert_eq!(
+            debugger.start_debuggee(),
+            Err(DebuggerError::Launch(
+                LaunchError::InvalidArgument.to_string()
+            ))
+        );
+        assert_eq!(debugger.pid(), None);
+        assert_eq!(
+            debugger.read_register(debugger.arch.pc_reg()),
+            Err(DebuggerError::NoThread)
+        );
+        assert_eq!(
+            debugger.read_memory(0x2000, 1),
+            Err(DebuggerError::NoThread)
+        );
+        assert_eq!(debugger.resume(), Err(DebuggerError::NoThread));
+        assert_eq!(debugger.interrupt(true), Err(DebuggerError::NoThread));
+        assert_eq!(debugger.detach(), Err(DebuggerError::NoThread));
+        assert_eq!(debugger.kill_debuggee(), Err(DebuggerError::NoThread));
+    }
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(str_to_addr("0x401000"), Ok(0x401000));
+        assert_eq!(str_to_addr("ff"), Ok(0xff));
+        assert_eq!(
+            str_to_addr("0x40z000"),
+            Err(DebuggerError::InvalidNumber("0x40z000".to_string()))
+        );
+        assert_eq!(str_to_reg_value("0xffffffffffffffff"), Ok(u64::MAX));
+        assert_eq!(
+            str_to_reg_value(""),
+            Err(DebuggerError::InvalidNumber(String::new()))
+        );
+    }
+
+    #[test]
+    fn test_command_errors() {
+        let process = Arc::new(Mutex::new(FakeProcess::default()));
+        let mut debugger = stopped_debugger(&process, 0x1000);
+        let pc_name = format!("{:?}", debugger.arch.pc_reg());
+
+        // a typo is reported, nothing is written
+        debugger.handle_command("memory write 0x30g0 0x1122");
+        debugger.handle_command(&format!("register write {pc_name} 0xzz"));
+        assert!(process.lock().unwrap().memory.is_empty());
+        assert_eq!(debugger.get_pc(PID), Ok(0x1000));
+
+        process.lock().unwrap().gone = true;
+        debugger.handle_command("register dump");
+        debugger.handle_command(&format!("register read {pc_name}"));
+        assert_eq!(
+            debugger.read_register(debugger.arch.pc_reg()),
+            Err(DebuggerError::Register {
+                tid: PID,
+                reg: debugger.arch.pc_reg(),
+                errno: Errno::ESRCH
+            })
+        );
+        assert_eq!(debugger.inferior.pid, Some(PID));
     }
 }
@@ -0,0 +1,104 @@
+use std::fmt;
+
+use nix::{errno::Errno, unistd::Pid};
+
+use crate::{location::Location, register::Reg};
+
+/// Why a command failed. The command reports it and the prompt comes back, the debuggee is
+/// left as it was.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebuggerError {
+    /// A number typed by the user that isn't hexadecimal
+    InvalidNumber(String),
+    /// No thread to act on, the program is not being run
+    NoThread,
+    /// The selected thread is running, ptrace can't reach it
+    ThreadRunning,
+    /// The program couldn't be started or didn't stop at its exec
+    Launch(String),
+    /// Already debugging the process `pid`, only one process is debugged at a time
+    AlreadyDebugging(Pid),
+    /// The process `pid` couldn't be traced and stopped
+    Attach { pid: Pid, errno: Errno },
+    /// The core file couldn't be read
+    Core(String),
+    /// A register of `tid` couldn't be read or written, the thread may have ended
+    Register { tid: Pid, reg: Reg, errno: Errno },
+    /// The memory at `address` couldn't be read or written
+    Memory { address: u64, errno: Errno },
+    /// `tid` couldn't be stepped or waited for
+    Thread { tid: Pid, errno: Errno },
+    /// No breakpoint at `address` to step over, it was deleted while the thread stopped on it
+    NoBreakpoint(u64),
+    /// No loaded module defines the location, its breakpoint waits for a shared library
+    UnresolvedLocation(Location),
+    /// Every thread is running, one must be stopped to be resumed or reach memory
+    NoStoppedThread,
+    /// Every thread is stopped already
+    NoRunningThread,
+    /// Nothing is mapped at `address`, the first byte that couldn't be read
+    UnreadableMemory(u64),
+    /// The prompt couldn't be set up on the terminal
+    Terminal(String),
+}
+
+impl DebuggerError {
+    /// `tid` left the thread list, it ended while the command ran
+    pub(crate) fn thread_gone(tid: Pid) -> Self {
+        DebuggerError::Thread {
+            tid,
+            errno: Errno::ESRCH,
+        }
+    }
+}
+
+impl fmt::Display for DebuggerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DebuggerError::InvalidNumber(s) => write!(f, "{s} is not a hexadecimal number"),
+            DebuggerError::NoThread => write!(
+                f,
+                "The program is not being run, start it with the run command"
+            ),
+            DebuggerError::ThreadRunning => {
+                write!(f, "Selected thread is running, interrupt it first")
+            }
+            DebuggerError::Launch(reason) | DebuggerError::Core(reason) => write!(f, "{reason}"),
+            DebuggerError::AlreadyDebugging(pid) => write!(
+                f,
+                "Already debugging process {pid}, detach or kill it first"
+            ),
+            DebuggerError::Attach { pid, errno } => {
+                write!(f, "Cannot attach to process {pid}: {}", errno.desc())
+            }
+            DebuggerError::Register { tid, reg, errno } => write!(
+                f,
+                "Cannot access register {reg:?} of thread {tid}: {}",
+                errno.desc()
+            ),
+            DebuggerError::Memory { address, errno } => {
+                write!(f, "Cannot access memory at 0x{address:x}: {}", errno.desc())
+            }
+            DebuggerError::Thread { tid, errno } => {
+                write!(f, "Cannot control thread {tid}: {}", errno.desc())
+            }
+            DebuggerError::NoBreakpoint(address) => {
+                write!(f, "No breakpoint at 0x{address:x}")
+            }
+            DebuggerError::UnresolvedLocation(location) => write!(
+                f,
+                "Breakpoint at {location} pending until a shared library defines it"
+            ),
+            DebuggerError::NoStoppedThread => {
+                write!(f, "Every thread is running, interrupt one first")
+            }
+            DebuggerError::NoRunningThread => write!(f, "No running thread to interrupt"),
+            DebuggerError::UnreadableMemory(address) => {
+                write!(f, "Cannot read memory at 0x{address:x}")
+            }
+            DebuggerError::Terminal(reason) => write!(f, "Cannot use the terminal: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for DebuggerError {}
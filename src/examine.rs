@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+/// How the `x` command prints memory, written `/NFU` after the command like in gdb
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExamineFormat {
+    /// Number of units to print
+    pub count: usize,
+    /// One of `x` (hexadecimal), `d` (signed), `u` (unsigned), `o` (octal) or `c` (character)
+    pub format: char,
+    /// Size of a unit in bytes
+    pub unit: usize,
+}
+
+impl Default for ExamineFormat {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            format: 'x',
+            unit: 8,
+        }
+    }
+}
+
+impl FromStr for ExamineFormat {
+    type Err = String;
+
+    /// Parses the part after the slash, every piece is optional: `4xg`, `16c`, `w`...
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut examine = ExamineFormat::default();
+        let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        if digits > 0 {
+            examine.count = s[..digits]
+                .parse()
+                .map_err(|_| format!("Invalid count in /{s}"))?;
+        }
+        for letter in s[digits..].chars() {
+            match letter {
+                'x' | 'd' | 'u' | 'o' => examine.format = letter,
+                'c' => {
+                    examine.format = letter;
+                    examine.unit = 1;
+                }
+                'b' => examine.unit = 1,
+                'h' => examine.unit = 2,
+                'w' => examine.unit = 4,
+                'g' => examine.unit = 8,
+                _ => return Err(format!("Invalid format letter '{letter}' in /{s}")),
+            }
+        }
+        Ok(examine)
+    }
+}
+
+impl ExamineFormat {
+    /// Total number of bytes to read from the tracee
+    pub fn byte_len(&self) -> usize {
+        self.count * self.unit
+    }
+
+    /// Units printed on a line, keeping lines around 16 bytes long
+    pub fn units_per_line(&self) -> usize {
+        (16 / self.unit).min(8)
+    }
+
+    /// Formats one unit, `bytes` being exactly `self.unit` little endian bytes
+    pub fn format_unit(&self, bytes: &[u8]) -> String {
+        let mut buffer = [0u8; 8];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        let value = u64::from_le_bytes(buffer);
+        let bits = self.unit * 8;
+        match self.format {
+            'x' => format!("0x{value:0width$x}", width = self.unit * 2),
+            'o' => format!("0{value:o}"),
+            'u' => value.to_string(),
+            'd' => {
+                // sign extend from the unit size
+                let shift = 64 - bits;
+                (((value << shift) as i64) >> shift).to_string()
+            }
+            'c' => {
+                let c = bytes[0];
+                if c.is_ascii_graphic() || c == b' ' {
+                    format!("{c} '{}'", c as char)
+                } else {
+                    format!("{c} '\\x{c:02x}'")
+                }
+            }
+            _ => unreachable!("checked when parsing the format"),
+        }
+    }
+}
+
+/// Formats a variable the way `print` shows it: a signed integer when it has the size of one,
+/// its bytes in hexadecimal otherwise
+pub fn format_value(bytes: &[u8]) -> String {
+    if let 1 | 2 | 4 | 8 = bytes.len() {
+        let format = ExamineFormat {
+            count: 1,
+            format: 'd',
+            unit: bytes.len(),
+        };
+        return format.format_unit(bytes);
+    }
+    let shown: Vec<String> = bytes
+        .iter()
+        .take(64)
+        .map(|b| format!("0x{b:02x}"))
+        .collect();
+    let more = if bytes.len() > 64 { "..." } else { "" };
+    format!("{{{}{more}}}", shown.join(", "))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_examine_format() {
+        assert_eq!("".parse(), Ok(ExamineFormat::default()));
+        assert_eq!(
+            "4xw".parse(),
+            Ok(ExamineFormat {
+                count: 4,
+                format: 'x',
+                unit: 4
+            })
+        );
+        assert_eq!(
+            "16c".parse(),
+            Ok(ExamineFormat {
+                count: 16,
+                format: 'c',
+                unit: 1
+            })
+        );
+        assert!("2z".parse::<ExamineFormat>().is_err());
+    }
+
+    #[test]
+    fn test_format_unit() {
+        let word: ExamineFormat = "xw".parse().unwrap();
+        assert_eq!(word.format_unit(&[0x78, 0x56, 0x34, 0x12]), "0x12345678");
+        let signed: ExamineFormat = "dh".parse().unwrap();
+        assert_eq!(signed.format_unit(&[0xfe, 0xff]), "-2");
+        let chars: ExamineFormat = "c".parse().unwrap();
+        assert_eq!(chars.format_unit(b"A"), "65 'A'");
+        assert_eq!(chars.format_unit(&[0]), "0 '\\x00'");
+    }
+
+    #[test]
+    fn test_format_value() {
+        assert_eq!(format_value(&(-42i32).to_le_bytes()), "-42");
+        assert_eq!(format_value(&[1, 2, 3]), "{0x01, 0x02, 0x03}");
+        assert!(format_value(&[0; 100]).ends_with("0x00...}"));
+    }
+}
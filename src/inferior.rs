@@ -0,0 +1,122 @@
+use std::{collections::HashMap, rc::Rc};
+
+use nix::unistd::Pid;
+
+use crate::{
+    breakpoint::Breakpoint,
+    location::Location,
+    symbols::Module,
+    target::Target,
+    thread::{ThreadList, ThreadState},
+};
+
+/// A process under the debugger, or the place for one before the program is run or once it
+/// has ended. Every inferior has its own threads, breakpoints and symbols.
+pub struct Inferior {
+    /// Number shown to the user, the first inferior is 1
+    pub id: usize,
+    /// The traced process, `None` until the program is run or once it has ended
+    pub pid: Option<Pid>,
+    /// Whether the traced process was attached to rather than started by the debugger
+    pub attached: bool,
+    /// Threads of the traced process, all of them are stopped while commands are typed
+    pub threads: ThreadList,
+    /// Thread the register and memory commands act on, the last one to report a stop
+    pub current_thread: Option<Pid>,
+    /// Traps inserted in the process, by address
+    pub breakpoints: HashMap<u64, Breakpoint<Rc<dyn Target>>>,
+    /// Locations that no loaded module defines yet, inserted when a library defining them is loaded
+    pub pending: Vec<(Location, Breakpoint<Rc<dyn Target>>)>,
+    /// The program followed by the shared libraries, at their load address once it runs
+    pub modules: Vec<Module>,
+    /// Address of the dynamic loader's `_r_debug`, once it has filled `DT_DEBUG` in
+    pub r_debug: Option<u64>,
+    /// The function the dynamic loader calls around every library change, a trap there keeps `modules` up to date
+    pub solib_event: Option<u64>,
+}
+
+impl Inferior {
+    pub fn new(id: usize) -> Self {
+        Self {
+            id,
+            pid: None,
+            attached: false,
+            threads: ThreadList::default(),
+            current_thread: None,
+            breakpoints: HashMap::new(),
+            pending: vec![],
+            modules: vec![],
+            r_debug: None,
+            solib_event: None,
+        }
+    }
+
+    /// The inferior of a child forked by this one, stopped on its first instruction. It is a
+    /// copy of the parent: same program and libraries, and the traps are at the same place.
+    pub fn forked(&self, id: usize, child: Pid) -> Self {
+        let mut threads = ThreadList::default();
+        threads.add(child, ThreadState::Stopped);
+        Self {
+            id,
+            pid: Some(child),
+            attached: self.attached,
+            threads,
+            current_thread: Some(child),
+            breakpoints: self.breakpoints.clone(),
+            pending: self.pending.clone(),
+            modules: self.modules.clone(),
+            r_debug: self.r_debug,
+            solib_event: self.solib_event,
+        }
+    }
+
+    /// Drops everything tied to the process once it has ended, the symbols stay for the next run
+    pub fn forget(&mut self) {
+        self.pid = None;
+        self.attached = false;
+        self.threads.clear();
+        self.current_thread = None;
+        self.breakpoints.clear();
+        self.pending.clear();
+        self.r_debug = None;
+        self.solib_event = None;
+    }
+
+    /// `process 1234`, or `<null>` when no process is running
+    pub fn describe(&self) -> String {
+        match self.pid {
+            Some(pid) => format!("process {pid}"),
+            None => "<null>".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{arch::X86_64, target::LiveTarget};
+    use std::ffi::c_void;
+
+    #[test]
+    fn test_forked_inferior() {
+        let mut parent = Inferior::new(1);
+        parent.pid = Some(Pid::from_raw(100));
+        parent.threads.add(Pid::from_raw(100), ThreadState::Stopped);
+        parent.threads.add(Pid::from_raw(101), ThreadState::Running);
+        parent.breakpoints.insert(
+            0x401146,
+            Breakpoint::new(0x401146 as *mut c_void, &X86_64, Rc::new(LiveTarget)),
+        );
+        parent.solib_event = Some(0x7ffff7fd0100);
+
+        let child = parent.forked(2, Pid::from_raw(200));
+        assert_eq!(child.id, 2);
+        assert_eq!(child.describe(), "process 200");
+        // only the thread that forked is copied
+        assert_eq!(child.threads.tids(), vec![Pid::from_raw(200)]);
+        assert_eq!(child.current_thread, Some(Pid::from_raw(200)));
+        assert!(child.breakpoints.contains_key(&0x401146));
+        assert_eq!(child.solib_event, parent.solib_event);
+        assert_eq!(Inferior::new(3).describe(), "<null>");
+    }
+}
@@ -0,0 +1,634 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::CString,
+    fmt,
+    os::{
+        fd::{AsRawFd, IntoRawFd, OwnedFd, RawFd},
+        unix::ffi::OsStringExt,
+    },
+    path::{Path, PathBuf},
+};
+
+use nix::{
+    errno::Errno,
+    fcntl::{open, OFlag},
+    libc,
+    pty::{openpty, Winsize},
+    sys::{
+        personality::{self, Persona},
+        ptrace::traceme,
+        stat::Mode,
+        termios::{tcgetattr, tcsetattr, LocalFlags, SetArg},
+        wait::waitpid,
+    },
+    unistd::{
+        access, chdir, dup2, execve, fork, isatty, pipe2, read, setsid, write, AccessFlags,
+        ForkResult, Pid,
+    },
+};
+
+/// Search path used when the debuggee's environment has no `PATH`, same as glibc's execvp
+static DEFAULT_PATH: &str = "/bin:/usr/bin";
+
+/// Where one of the debuggee's output streams goes
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputTarget {
+    File {
+        path: PathBuf,
+        append: bool,
+    },
+    /// `2>&1`, only meaningful for stderr
+    Stdout,
+}
+
+/// Files replacing the debuggee's standard streams, as written after `run` or `set args`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Redirections {
+    pub stdin: Option<PathBuf>,
+    pub stdout: Option<OutputTarget>,
+    pub stderr: Option<OutputTarget>,
+}
+
+impl fmt::Display for Redirections {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.stdin {
+            write!(f, " < {}", path.display())?;
+        }
+        for (fd, target) in [("", &self.stdout), ("2", &self.stderr)] {
+            match target {
+                Some(OutputTarget::File { path, append }) => {
+                    let op = if *append { ">>" } else { ">" };
+                    write!(f, " {fd}{op} {}", path.display())?;
+                }
+                Some(OutputTarget::Stdout) => write!(f, " {fd}>&1")?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The terminal the debuggee's standard streams are attached to
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum TerminalMode {
+    /// A new pseudo-terminal whose output is forwarded by the debugger
+    #[default]
+    Pty,
+    /// The debugger's own terminal
+    Inherit,
+    /// An existing terminal such as `/dev/pts/3`
+    Tty(PathBuf),
+}
+
+/// How the debuggee is started: the program, its arguments, its environment
+/// and the directory it runs in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaunchConfig {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    pub redirections: Redirections,
+    pub terminal: TerminalMode,
+    /// Runs the debuggee with `ADDR_NO_RANDOMIZE` so addresses are the same on every run
+    pub disable_randomization: bool,
+}
+
+/// Splits shell-like redirections (`< in`, `> out`, `>> out`, `2> err`, `2>> err`,
+/// `2>&1`) from the program arguments
+pub fn parse_redirections(words: &[String]) -> Result<(Vec<String>, Redirections), String> {
+    let mut args = vec![];
+    let mut redirections = Redirections::default();
+    let mut words = words.iter();
+
+    while let Some(word) = words.next() {
+        if word == "2>&1" {
+            redirections.stderr = Some(OutputTarget::Stdout);
+            continue;
+        }
+        let Some((op, rest)) = ["2>>", "2>", ">>", ">", "<"]
+            .into_iter()
+            .find_map(|op| word.strip_prefix(op).map(|rest| (op, rest)))
+        else {
+            args.push(word.clone());
+            continue;
+        };
+        let path = if rest.is_empty() {
+            let Some(next) = words.next() else {
+                return Err(format!("Missing file name after {op}"));
+            };
+            PathBuf::from(next)
+        } else {
+            PathBuf::from(rest)
+        };
+
+        match op {
+            "<" => redirections.stdin = Some(path),
+            ">" | ">>" => {
+                redirections.stdout = Some(OutputTarget::File {
+                    path,
+                    append: op == ">>",
+                })
+            }
+            _ => {
+                redirections.stderr = Some(OutputTarget::File {
+                    path,
+                    append: op == "2>>",
+                })
+            }
+        }
+    }
+
+    Ok((args, redirections))
+}
+
+impl LaunchConfig {
+    /// A configuration inheriting the debugger's environment and working directory
+    pub fn new(program: String, args: Vec<String>) -> Self {
+        Self {
+            program,
+            args,
+            env: env::vars().collect(),
+            cwd: None,
+            redirections: Redirections::default(),
+            terminal: TerminalMode::default(),
+            disable_randomization: true,
+        }
+    }
+
+    /// A configuration running `program` with `words`: its arguments, then the redirections
+    /// of its input and output written like in a shell
+    pub fn parse(program: String, words: &[String]) -> Result<Self, String> {
+        let (args, redirections) = parse_redirections(words)?;
+        let mut config = Self::new(program, args);
+        config.redirections = redirections;
+        Ok(config)
+    }
+
+    pub fn set_env<'a>(&mut self, assignment: &'a str) -> Option<(&'a str, &'a str)> {
+        let (key, value) = assignment.split_once('=')?;
+        if key.is_empty() {
+            return None;
+        }
+        self.env.insert(key.to_string(), value.to_string());
+        Some((key, value))
+    }
+
+    /// Removes `key` from the environment, or every variable when no key is given
+    pub fn unset_env(&mut self, key: Option<&str>) {
+        match key {
+            Some(key) => {
+                self.env.remove(key);
+            }
+            None => self.env.clear(),
+        }
+    }
+
+    fn argv(&self) -> Result<Vec<CString>, nix::Error> {
+        std::iter::once(&self.program)
+            .chain(self.args.iter())
+            .map(|arg| CString::new(arg.as_str()).map_err(|_| nix::Error::EINVAL))
+            .collect()
+    }
+
+    fn envp(&self) -> Result<Vec<CString>, nix::Error> {
+        self.env
+            .iter()
+            .map(|(key, value)| {
+                CString::new(format!("{key}={value}")).map_err(|_| nix::Error::EINVAL)
+            })
+            .collect()
+    }
+}
+
+/// Step of the child's setup that failed, sent back to the debugger with the errno
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LaunchStage {
+    Personality = 1,
+    Chdir = 2,
+    Traceme = 3,
+    Exec = 4,
+    Redirect = 5,
+    Terminal = 6,
+}
+
+impl LaunchStage {
+    fn from_raw(raw: i32) -> Option<Self> {
+        match raw {
+            1 => Some(Self::Personality),
+            2 => Some(Self::Chdir),
+            3 => Some(Self::Traceme),
+            4 => Some(Self::Exec),
+            5 => Some(Self::Redirect),
+            6 => Some(Self::Terminal),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum LaunchError {
+    /// The program name, an argument or an environment variable contains a nul byte
+    InvalidArgument,
+    /// The error pipe or the child process couldn't be created
+    Fork(nix::Error),
+    /// The child failed before or while executing the program
+    Child {
+        program: String,
+        stage: LaunchStage,
+        errno: Errno,
+    },
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LaunchError::InvalidArgument => {
+                write!(
+                    f,
+                    "The program arguments and environment cannot contain nul bytes"
+                )
+            }
+            LaunchError::Fork(errno) => write!(f, "Cannot create the debuggee process: {errno}"),
+            LaunchError::Child {
+                program,
+                stage,
+                errno,
+            } => match stage {
+                LaunchStage::Personality => write!(
+                    f,
+                    "Cannot remove address randomization from the child process: {}",
+                    errno.desc()
+                ),
+                LaunchStage::Chdir => write!(
+                    f,
+                    "Cannot change the child process' working directory: {}",
+                    errno.desc()
+                ),
+                LaunchStage::Traceme => {
+                    write!(f, "Cannot trace the child process: {}", errno.desc())
+                }
+                LaunchStage::Redirect => write!(
+                    f,
+                    "Cannot redirect the child process' standard streams: {}",
+                    errno.desc()
+                ),
+                LaunchStage::Terminal => write!(
+                    f,
+                    "Cannot attach the child process to its terminal: {}",
+                    errno.desc()
+                ),
+                LaunchStage::Exec => match errno {
+                    Errno::ENOENT => write!(f, "{program}: No such file or directory"),
+                    Errno::EACCES => write!(f, "{program}: Permission denied"),
+                    Errno::ENOEXEC => write!(f, "{program}: Exec format error, not an executable"),
+                    _ => write!(f, "Cannot execute {program}: {}", errno.desc()),
+                },
+            },
+        }
+    }
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.is_file() && access(path, AccessFlags::X_OK).is_ok()
+}
+
+/// Finds `program` the way a shell would: names containing a slash are used as
+/// is, bare names are looked up in the `PATH` the debuggee will run with.
+pub fn resolve_program(program: &str, path_var: Option<&str>) -> PathBuf {
+    if program.contains('/') {
+        return PathBuf::from(program);
+    }
+
+    let candidates: Vec<PathBuf> = path_var
+        .unwrap_or(DEFAULT_PATH)
+        .split(':')
+        .map(|dir| if dir.is_empty() { "." } else { dir })
+        .map(|dir| Path::new(dir).join(program))
+        .collect();
+
+    // like execvp, prefer an executable match but still report EACCES for a non executable one
+    candidates
+        .iter()
+        .find(|candidate| is_executable(candidate))
+        .or_else(|| candidates.iter().find(|candidate| candidate.is_file()))
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from(program))
+}
+
+/// Sends the failing stage and errno to the debugger then leaves without
+/// running any of the destructors or exit handlers inherited from the parent
+fn report_child_failure(pipe: &OwnedFd, stage: LaunchStage, errno: Errno) -> ! {
+    let mut report = [0u8; 8];
+    report[..4].copy_from_slice(&(stage as i32).to_ne_bytes());
+    report[4..].copy_from_slice(&(errno as i32).to_ne_bytes());
+    let _ = write(pipe, &report);
+    unsafe { libc::_exit(127) }
+}
+
+/// Waits for the child to either exec, which closes the close-on-exec pipe,
+/// or send back the reason it couldn't
+fn read_child_failure(pipe: &OwnedFd) -> Option<(LaunchStage, Errno)> {
+    let mut report = [0u8; 8];
+    let mut received = 0;
+    while received < report.len() {
+        match read(pipe.as_raw_fd(), &mut report[received..]) {
+            Ok(0) => break,
+            Ok(n) => received += n,
+            Err(Errno::EINTR) => continue,
+            Err(_) => break,
+        }
+    }
+    if received < report.len() {
+        return None;
+    }
+
+    let stage = i32::from_ne_bytes(report[..4].try_into().unwrap());
+    let errno = i32::from_ne_bytes(report[4..].try_into().unwrap());
+    Some((LaunchStage::from_raw(stage)?, Errno::from_raw(errno)))
+}
+
+/// A freshly executed debuggee, stopped right after its exec
+pub struct Launched {
+    pub pid: Pid,
+    /// Master side of the debuggee's pseudo-terminal in [`TerminalMode::Pty`]
+    pub pty: Option<OwnedFd>,
+}
+
+/// A file to open in the child and the standard stream it replaces
+struct ChildRedirect {
+    fd: RawFd,
+    path: CString,
+    flags: OFlag,
+}
+
+fn path_to_cstring(path: &Path) -> Result<CString, LaunchError> {
+    CString::new(path.as_os_str().to_owned().into_vec()).map_err(|_| LaunchError::InvalidArgument)
+}
+
+fn child_redirects(redirections: &Redirections) -> Result<Vec<ChildRedirect>, LaunchError> {
+    let mut redirects = vec![];
+    if let Some(path) = &redirections.stdin {
+        redirects.push(ChildRedirect {
+            fd: libc::STDIN_FILENO,
+            path: path_to_cstring(path)?,
+            flags: OFlag::O_RDONLY,
+        });
+    }
+    for (fd, target) in [
+        (libc::STDOUT_FILENO, &redirections.stdout),
+        (libc::STDERR_FILENO, &redirections.stderr),
+    ] {
+        if let Some(OutputTarget::File { path, append }) = target {
+            let mode = if *append {
+                OFlag::O_APPEND
+            } else {
+                OFlag::O_TRUNC
+            };
+            redirects.push(ChildRedirect {
+                fd,
+                path: path_to_cstring(path)?,
+                flags: OFlag::O_WRONLY | OFlag::O_CREAT | mode,
+            });
+        }
+    }
+    Ok(redirects)
+}
+
+/// Opens a pseudo-terminal with the debugger's window size and without local
+/// echo, the debugger's terminal already echoes what is typed for the debuggee
+fn open_pty() -> Result<(OwnedFd, OwnedFd), LaunchError> {
+    let mut winsize = Winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    if isatty(libc::STDOUT_FILENO).unwrap_or(false) {
+        unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut winsize) };
+    }
+
+    let pty = openpty(&winsize, None).map_err(LaunchError::Fork)?;
+    if let Ok(mut termios) = tcgetattr(&pty.slave) {
+        termios.local_flags.remove(LocalFlags::ECHO);
+        let _ = tcsetattr(&pty.slave, SetArg::TCSANOW, &termios);
+    }
+    Ok((pty.master, pty.slave))
+}
+
+/// Makes `terminal` the controlling terminal of a new session and the target of
+/// the three standard streams
+fn attach_child_terminal(terminal: RawFd) -> Result<(), Errno> {
+    setsid()?;
+    // an existing terminal may already control another session, the streams still work without it
+    unsafe { libc::ioctl(terminal, libc::TIOCSCTTY, 0) };
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        dup2(terminal, fd)?;
+    }
+    Ok(())
+}
+
+fn apply_child_redirects(redirects: &[ChildRedirect], stderr_to_stdout: bool) -> Result<(), Errno> {
+    for redirect in redirects {
+        let fd = open(
+            redirect.path.as_c_str(),
+            redirect.flags,
+            Mode::from_bits_truncate(0o644),
+        )?;
+        dup2(fd, redirect.fd)?;
+        unsafe { libc::close(fd) };
+    }
+    if stderr_to_stdout {
+        dup2(libc::STDOUT_FILENO, libc::STDERR_FILENO)?;
+    }
+    Ok(())
+}
+
+/// Forks and executes the debuggee described by `config` under ptrace.
+/// The child stops with a SIGTRAP right after the exec.
+pub fn spawn(config: &LaunchConfig) -> Result<Launched, LaunchError> {
+    // everything is allocated before the fork, the child only calls async-signal-safe functions
+    let argv = config.argv().map_err(|_| LaunchError::InvalidArgument)?;
+    let envp = config.envp().map_err(|_| LaunchError::InvalidArgument)?;
+    let path = resolve_program(&config.program, config.env.get("PATH").map(String::as_str));
+    let c_path = path_to_cstring(&path)?;
+    let cwd = config.cwd.clone();
+    let redirects = child_redirects(&config.redirections)?;
+    let stderr_to_stdout = config.redirections.stderr == Some(OutputTarget::Stdout);
+    let disable_randomization = config.disable_randomization;
+
+    let (pty, tty_path) = match &config.terminal {
+        TerminalMode::Pty => (Some(open_pty()?), None),
+        TerminalMode::Inherit => (None, None),
+        TerminalMode::Tty(path) => (None, Some(path_to_cstring(path)?)),
+    };
+
+    let (err_read, err_write) = pipe2(OFlag::O_CLOEXEC).map_err(LaunchError::Fork)?;
+
+    match unsafe { fork() }.map_err(LaunchError::Fork)? {
+        ForkResult::Parent { child, .. } => {
+            drop(err_write);
+            let pty = pty.map(|(master, _slave)| master);
+            let Some((stage, errno)) = read_child_failure(&err_read) else {
+                return Ok(Launched { pid: child, pty });
+            };
+
+            // reap the child that exited after reporting its failure
+            let _ = waitpid(child, None);
+            Err(LaunchError::Child {
+                program: config.program.clone(),
+                stage,
+                errno,
+            })
+        }
+        ForkResult::Child => {
+            // execute the debugee in the child
+            drop(err_read);
+            write(
+                std::io::stdout(),
+                "It's unsafe for me, the child to write directly on the stdout\n".as_bytes(),
+            )
+            .ok();
+
+            if let Some(cwd) = cwd {
+                if let Err(errno) = chdir(&cwd) {
+                    report_child_failure(&err_write, LaunchStage::Chdir, errno);
+                }
+            }
+
+            let terminal = match (pty, tty_path) {
+                (Some((master, slave)), _) => {
+                    drop(master);
+                    Some(slave.into_raw_fd())
+                }
+                (None, Some(tty_path)) => {
+                    match open(tty_path.as_c_str(), OFlag::O_RDWR, Mode::empty()) {
+                        Ok(fd) => Some(fd),
+                        Err(errno) => {
+                            report_child_failure(&err_write, LaunchStage::Terminal, errno)
+                        }
+                    }
+                }
+                (None, None) => None,
+            };
+            if let Some(terminal) = terminal {
+                if let Err(errno) = attach_child_terminal(terminal) {
+                    report_child_failure(&err_write, LaunchStage::Terminal, errno);
+                }
+                unsafe { libc::close(terminal) };
+            }
+
+            if let Err(errno) = apply_child_redirects(&redirects, stderr_to_stdout) {
+                report_child_failure(&err_write, LaunchStage::Redirect, errno);
+            }
+
+            // Disable address space randomization
+            if disable_randomization {
+                if let Err(errno) = personality::set(Persona::ADDR_NO_RANDOMIZE) {
+                    report_child_failure(&err_write, LaunchStage::Personality, errno);
+                }
+            }
+
+            if let Err(errno) = traceme() {
+                report_child_failure(&err_write, LaunchStage::Traceme, errno);
+            }
+
+            // only returns on failure
+            let Err(errno) = execve(&c_path, &argv, &envp);
+            report_child_failure(&err_write, LaunchStage::Exec, errno);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_env_edition() {
+        let mut config = LaunchConfig::new("prog".to_string(), vec![]);
+        config.env.clear();
+
+        assert_eq!(config.set_env("FOO=bar=baz"), Some(("FOO", "bar=baz")));
+        assert_eq!(config.set_env("EMPTY="), Some(("EMPTY", "")));
+        assert_eq!(config.set_env("=oops"), None);
+        assert_eq!(config.set_env("NOVALUE"), None);
+        assert_eq!(
+            config.envp().unwrap(),
+            vec![
+                CString::new("EMPTY=").unwrap(),
+                CString::new("FOO=bar=baz").unwrap()
+            ]
+        );
+
+        config.unset_env(Some("FOO"));
+        assert_eq!(config.env.len(), 1);
+        config.unset_env(None);
+        assert!(config.env.is_empty());
+    }
+
+    #[test]
+    fn test_argv_starts_with_program() {
+        let config = LaunchConfig::new("prog".to_string(), vec!["-v".to_string(), "x".to_string()]);
+        let argv = config.argv().unwrap();
+
+        assert_eq!(argv.len(), 3);
+        assert_eq!(argv[0].to_str().unwrap(), "prog");
+        assert_eq!(argv[2].to_str().unwrap(), "x");
+    }
+
+    #[test]
+    fn test_resolve_program() {
+        assert_eq!(
+            resolve_program("./prog", Some("/bin")),
+            PathBuf::from("./prog")
+        );
+        assert_eq!(
+            resolve_program("sh", Some("/nonexistent:/bin")),
+            PathBuf::from("/bin/sh")
+        );
+        assert_eq!(
+            resolve_program("surely-not-a-program", Some("/bin")),
+            PathBuf::from("surely-not-a-program")
+        );
+    }
+
+    #[test]
+    fn test_exec_failure_is_reported() {
+        let mut config = LaunchConfig::new("/nonexistent/prog".to_string(), vec![]);
+        config.terminal = TerminalMode::Inherit;
+
+        assert_eq!(
+            spawn(&config).err(),
+            Some(LaunchError::Child {
+                program: "/nonexistent/prog".to_string(),
+                stage: LaunchStage::Exec,
+                errno: Errno::ENOENT
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_redirections() {
+        let words: Vec<String> = ["-v", "<", "in.txt", ">>out.txt", "x", "2>&1"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let (args, redirections) = parse_redirections(&words).unwrap();
+
+        assert_eq!(args, vec!["-v".to_string(), "x".to_string()]);
+        assert_eq!(redirections.stdin, Some(PathBuf::from("in.txt")));
+        assert_eq!(
+            redirections.stdout,
+            Some(OutputTarget::File {
+                path: PathBuf::from("out.txt"),
+                append: true
+            })
+        );
+        assert_eq!(redirections.stderr, Some(OutputTarget::Stdout));
+        assert_eq!(redirections.to_string(), " < in.txt >> out.txt 2>&1");
+
+        assert!(parse_redirections(&["2>".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,57 @@
+//! A debugger for Linux programs, built on ptrace. The `vdebugger` command gives it a prompt;
+//! the [`Debugger`] behind it can also be driven from Rust, to run a
+//! program to a breakpoint and check its state:
+//!
+//! ```no_run
+//! use vdebugger::{Debugger, DebuggerError, LaunchConfig, Reg, StopReason, TerminalMode};
+//! use nix::sys::signal::Signal;
+//!
+//! fn main() -> Result<(), DebuggerError> {
+//!     let mut config = LaunchConfig::new("./prog".to_string(), vec![]);
+//!     config.terminal = TerminalMode::Inherit;
+//!     let mut debugger = Debugger::new(config);
+//!     debugger.set_breakpoint("work".parse().unwrap())?;
+//!     debugger.start_debuggee()?;
+//!
+//!     let (_, reason) = debugger.last_stop().unwrap();
+//!     assert_eq!(*reason, StopReason::Signal(Signal::SIGTRAP));
+//!     let pc = debugger.read_register(Reg::Rip)?;
+//!     assert_eq!(debugger.breakpoints(), vec![pc]);
+//!     let counter = debugger.resolve_location(&"counter".parse().unwrap())[0];
+//!     let counter = debugger.read_memory(counter, 4)?;
+//!
+//!     debugger.resume()?;
+//!     Ok(())
+//! }
+//! ```
+
+pub use crate::{
+    catchpoint::ProcessEvent,
+    debugger::{Debugger, StopReason},
+    error::DebuggerError,
+    launch::{LaunchConfig, TerminalMode},
+    location::Location,
+    register::Reg,
+    strace::SyscallFilter,
+};
+
+pub(crate) mod arch;
+pub(crate) mod breakpoint;
+pub(crate) mod catchpoint;
+pub(crate) mod console;
+pub(crate) mod corefile;
+pub(crate) mod debugger;
+pub(crate) mod error;
+pub(crate) mod examine;
+pub(crate) mod inferior;
+pub(crate) mod launch;
+pub(crate) mod location;
+pub(crate) mod procfs;
+pub(crate) mod register;
+pub(crate) mod signals;
+pub(crate) mod solib;
+pub(crate) mod strace;
+pub(crate) mod symbols;
+pub(crate) mod target;
+pub(crate) mod thread;
+pub(crate) mod unwind;
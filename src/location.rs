@@ -0,0 +1,75 @@
+use std::{fmt, str::FromStr};
+
+/// Where the user wants to stop, as written after `break`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Location {
+    /// An absolute address in the tracee, never relocated
+    Address(u64),
+    /// A function or variable, relocated with the module defining it
+    Symbol(String),
+    /// `file:line`, the file only needs to match the end of the compiled path
+    Line { file: String, line: u64 },
+}
+
+impl FromStr for Location {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_start_matches('*');
+        if let Some(hex) = s.strip_prefix("0x") {
+            return u64::from_str_radix(hex, 16)
+                .map(Location::Address)
+                .map_err(|_| format!("Invalid address {s}"));
+        }
+        if let Some((file, line)) = s.rsplit_once(':') {
+            let Ok(line) = line.parse() else {
+                return Err(format!("Invalid line number in {s}"));
+            };
+            return Ok(Location::Line {
+                file: file.to_string(),
+                line,
+            });
+        }
+        if s.starts_with(|c: char| c.is_ascii_digit()) {
+            // the original break command only took hexadecimal addresses, 0x optional
+            return u64::from_str_radix(s, 16)
+                .map(Location::Address)
+                .map_err(|_| format!("Invalid address {s}"));
+        }
+        Ok(Location::Symbol(s.to_string()))
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Address(address) => write!(f, "0x{address:x}"),
+            Location::Symbol(name) => write!(f, "{name}"),
+            Location::Line { file, line } => write!(f, "{file}:{line}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_location() {
+        assert_eq!("0x401146".parse(), Ok(Location::Address(0x401146)));
+        assert_eq!("*0x10".parse(), Ok(Location::Address(0x10)));
+        assert_eq!("401146".parse(), Ok(Location::Address(0x401146)));
+        assert_eq!("main".parse(), Ok(Location::Symbol("main".to_string())));
+        assert_eq!("add".parse(), Ok(Location::Symbol("add".to_string())));
+        assert_eq!(
+            "src/main.c:12".parse(),
+            Ok(Location::Line {
+                file: "src/main.c".to_string(),
+                line: 12
+            })
+        );
+        assert!("main.c:twelve".parse::<Location>().is_err());
+        assert!("0xzz".parse::<Location>().is_err());
+        assert!("12z".parse::<Location>().is_err());
+    }
+}
@@ -1,61 +1,115 @@
-use debugger::Debugger;
+use std::{env, path::PathBuf};
 
-use nix::{
-    sys::{personality::{self, Persona}, ptrace::traceme},
-    unistd::{execv, fork, write, ForkResult},
-};
-use std::{env, ffi::CString};
+use nix::unistd::Pid;
+use vdebugger::{Debugger, LaunchConfig, SyscallFilter, TerminalMode};
 
-pub mod debugger;
-pub mod breakpoint;
-pub mod register;
+static USAGE: &str = "Usage: vdebugger [--pid <pid> | --trace-syscalls[=NAME,%CLASS,...]] <program> [--core <core file>] [-- <program arguments>... [< IN] [> OUT]]";
+
+struct CliArgs {
+    config: LaunchConfig,
+    /// Process to attach to instead of waiting for the run command
+    attach: Option<Pid>,
+    /// Syscalls to log while the program runs to its end, without a prompt
+    trace: Option<Vec<String>>,
+    /// Core file to look at instead of running the program
+    core: Option<PathBuf>,
+}
+
+fn parse_args(args: impl Iterator<Item = String>) -> Option<CliArgs> {
+    let mut args = args.peekable();
+    let mut attach = None;
+    let mut trace = None;
+    let mut core = None;
+
+    while let Some(option) = args.next_if(|arg| arg.starts_with("--") && arg != "--") {
+        match option.as_str() {
+            "--pid" => attach = Some(Pid::from_raw(args.next()?.parse().ok()?)),
+            "--trace-syscalls" => trace = Some(vec![]),
+            "--core" => core = Some(PathBuf::from(args.next()?)),
+            option => {
+                let filter = option.strip_prefix("--trace-syscalls=")?;
+                trace = Some(filter.split(',').map(str::to_string).collect());
+            }
+        }
+    }
+    // the program can be found from the process when attaching, or from the core file
+    let program = match args.next_if(|arg| arg != "--") {
+        Some(program) => program,
+        None if attach.is_some() || core.is_some() => String::new(),
+        None => return None,
+    };
+    if args.next_if(|arg| arg == "--core").is_some() {
+        core = Some(PathBuf::from(args.next()?));
+    }
+    let modes = [attach.is_some(), trace.is_some(), core.is_some()];
+    if modes.into_iter().filter(|&mode| mode).count() > 1 {
+        return None;
+    }
+    let words: Vec<String> = match args.next() {
+        Some(separator) if separator == "--" => args.collect(),
+        Some(_) => return None,
+        None => vec![],
+    };
+    let config = LaunchConfig::parse(program, &words).ok()?;
+    Some(CliArgs {
+        config,
+        attach,
+        trace,
+        core,
+    })
+}
 
 fn main() {
     if env::args().len() < 2 {
         eprintln!("No program name were given");
+        eprintln!("{USAGE}");
         std::process::exit(-1);
     }
 
-    let Some(program) = env::args().nth(1) else {
-        eprintln!("Error the program name should be given as program arg");
+    let Some(CliArgs {
+        mut config,
+        attach,
+        trace,
+        core,
+    }) = parse_args(env::args().skip(1))
+    else {
+        eprintln!("{USAGE}");
         std::process::exit(-1);
     };
 
-    let Ok( c_prog ) = CString::new(program.clone()) else {
-        eprintln!("Could not use the program name given");
-        std::process::exit(-1);
-    };
+    if let Some(filter) = trace {
+        let filter: Vec<&str> = filter.iter().map(String::as_str).collect();
+        let filter = match SyscallFilter::parse_native(&filter) {
+            Ok(filter) => filter,
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(-1);
+            }
+        };
+        // like strace, the program keeps the terminal
+        config.terminal = TerminalMode::Inherit;
+        if let Err(err) = Debugger::new(config).trace_syscalls(filter) {
+            eprintln!("{err}");
+            std::process::exit(-1);
+        }
+        return;
+    }
 
-    let c_prog = c_prog.as_c_str();
-    match unsafe { fork() } {
-        Ok(ForkResult::Parent { child, .. }) => {
-            // execute the debugger in the parent
-            println!("Starting the debugging process {child}");
-            let mut dbg = Debugger::new(program, child);
-            dbg.run();
+    let mut dbg = Debugger::new(config);
+    if let Some(pid) = attach {
+        if let Err(err) = dbg.attach(pid) {
+            eprintln!("{err}");
+            std::process::exit(-1);
         }
-        Ok(ForkResult::Child) => {
-            // execute the debugee in the child
-            write(
-                std::io::stdout(),
-                "It's unsafe for me, the child to write directly on the stdout\n".as_bytes(),
-            )
-                .ok();
-            
-            // Disable address space randomization
-            personality::set(Persona::ADDR_NO_RANDOMIZE).unwrap_or_else(|_| {
-                eprintln!("Cannot remove address randomization from the child process");
-                std::process::exit(-1);
-            });
-            
-            traceme().unwrap_or_else(|_| {
-                eprintln!("Cannot trace the child process");
-                std::process::exit(-1);
-            });
-            
-            let _ = execv(c_prog, &[c_prog]);
-            std::process::exit(0);
+    }
+    if let Some(core) = core {
+        if let Err(err) = dbg.load_core(&core) {
+            eprintln!("{err}");
+            std::process::exit(-1);
         }
-        Err(_) => println!("Fork failed"),
+    }
+    if let Err(err) = dbg.run() {
+        eprintln!("{err}");
+        std::process::exit(-1);
     }
 }
@@ -0,0 +1,214 @@
+use std::{fs, io, os::unix::fs::FileExt};
+
+use nix::unistd::Pid;
+
+/// Auxiliary vector entries the debugger relies on, from `<elf.h>`
+pub const AT_PHDR: u64 = 3;
+pub const AT_BASE: u64 = 7;
+pub const AT_ENTRY: u64 = 9;
+
+/// A line of `/proc/<pid>/maps`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryMap {
+    pub start: u64,
+    pub end: u64,
+    pub perms: String,
+    pub offset: u64,
+    pub device: String,
+    pub inode: u64,
+    /// Backing file or pseudo name such as `[stack]`, `None` for anonymous mappings
+    pub pathname: Option<String>,
+}
+
+impl MemoryMap {
+    pub fn contains(&self, address: u64) -> bool {
+        self.start <= address && address < self.end
+    }
+
+    /// `0x7ffd1000-0x7ffd2000 rw-p [stack]`, the way addresses are explained to the user
+    pub fn describe(&self) -> String {
+        let mut description = format!("0x{:x}-0x{:x} {}", self.start, self.end, self.perms);
+        if let Some(pathname) = &self.pathname {
+            description.push(' ');
+            description.push_str(pathname);
+        }
+        description
+    }
+}
+
+pub fn find_map(maps: &[MemoryMap], address: u64) -> Option<&MemoryMap> {
+    maps.iter().find(|map| map.contains(address))
+}
+
+fn parse_maps_line(line: &str) -> Option<MemoryMap> {
+    let mut fields = line.split_whitespace();
+    let (start, end) = fields.next()?.split_once('-')?;
+    let perms = fields.next()?.to_string();
+    let offset = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let device = fields.next()?.to_string();
+    let inode = fields.next()?.parse().ok()?;
+    // the pathname may contain spaces
+    let pathname = fields.collect::<Vec<_>>().join(" ");
+
+    Some(MemoryMap {
+        start: u64::from_str_radix(start, 16).ok()?,
+        end: u64::from_str_radix(end, 16).ok()?,
+        perms,
+        offset,
+        device,
+        inode,
+        pathname: (!pathname.is_empty()).then_some(pathname),
+    })
+}
+
+pub fn parse_maps(text: &str) -> Vec<MemoryMap> {
+    text.lines().filter_map(parse_maps_line).collect()
+}
+
+pub fn read_maps(pid: Pid) -> io::Result<Vec<MemoryMap>> {
+    Ok(parse_maps(&fs::read_to_string(format!(
+        "/proc/{pid}/maps"
+    ))?))
+}
+
+/// Reads `len` bytes of the memory of `pid` through `/proc/<pid>/mem`, much faster than a
+/// word at a time for large ranges. Stops at the first byte that can't be read.
+pub fn read_mem(pid: Pid, address: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mem = fs::File::open(format!("/proc/{pid}/mem"))?;
+    let mut bytes = vec![0u8; len];
+    let mut read = 0;
+    while read < len {
+        match mem.read_at(&mut bytes[read..], address + read as u64) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => read += n,
+        }
+    }
+    bytes.truncate(read);
+    Ok(bytes)
+}
+
+/// The parent, process group and session of a process from the text of `/proc/<pid>/stat`
+pub fn parse_stat_ids(text: &str) -> Option<[i32; 3]> {
+    // the command name in parentheses may itself contain spaces and parentheses
+    let (_, fields) = text.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(1);
+    Some([
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+        fields.next()?.parse().ok()?,
+    ])
+}
+
+pub fn read_stat_ids(pid: Pid) -> io::Result<[i32; 3]> {
+    parse_stat_ids(&fs::read_to_string(format!("/proc/{pid}/stat"))?)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed stat file"))
+}
+
+/// The command line of the process, its arguments separated by spaces
+pub fn read_cmdline(pid: Pid) -> io::Result<String> {
+    let cmdline = fs::read(format!("/proc/{pid}/cmdline"))?;
+    let args: Vec<_> = cmdline
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect();
+    Ok(args.join(" "))
+}
+
+/// Thread ids of the process, from `/proc/<pid>/task`
+pub fn read_tasks(pid: Pid) -> io::Result<Vec<Pid>> {
+    let mut tasks: Vec<Pid> = fs::read_dir(format!("/proc/{pid}/task"))?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .map(Pid::from_raw)
+        .collect();
+    tasks.sort();
+    Ok(tasks)
+}
+
+/// Splits a 64 bits auxiliary vector into `(type, value)` pairs, up to `AT_NULL`
+pub fn parse_auxv(data: &[u8]) -> Vec<(u64, u64)> {
+    data.chunks_exact(16)
+        .map(|entry| {
+            (
+                u64::from_ne_bytes(entry[..8].try_into().unwrap()),
+                u64::from_ne_bytes(entry[8..].try_into().unwrap()),
+            )
+        })
+        .take_while(|&(key, _)| key != 0)
+        .collect()
+}
+
+pub fn read_auxv(pid: Pid) -> io::Result<Vec<(u64, u64)>> {
+    Ok(parse_auxv(&fs::read(format!("/proc/{pid}/auxv"))?))
+}
+
+pub fn auxv_value(auxv: &[(u64, u64)], key: u64) -> Option<u64> {
+    auxv.iter()
+        .find(|&&(k, _)| k == key)
+        .map(|&(_, value)| value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_maps() {
+        let maps = parse_maps(
+            "555555554000-555555555000 r--p 00000000 08:01 1234  /tmp/my prog\n\
+             7ffff7fc1000-7ffff7fc5000 r--p 00000000 00:00 0                          [vvar]\n\
+             7ffff7ff0000-7ffff7ff1000 rw-p 00000000 00:00 0\n",
+        );
+
+        assert_eq!(maps.len(), 3);
+        assert_eq!(maps[0].start, 0x555555554000);
+        assert_eq!(maps[0].inode, 1234);
+        assert_eq!(maps[0].pathname.as_deref(), Some("/tmp/my prog"));
+        assert_eq!(maps[1].pathname.as_deref(), Some("[vvar]"));
+        assert_eq!(maps[2].pathname, None);
+        assert!(maps[2].contains(0x7ffff7ff0fff));
+        assert!(!maps[2].contains(0x7ffff7ff1000));
+        assert_eq!(
+            find_map(&maps, 0x7ffff7fc2000).map(MemoryMap::describe),
+            Some("0x7ffff7fc1000-0x7ffff7fc5000 r--p [vvar]".to_string())
+        );
+        assert_eq!(find_map(&maps, 0x1000), None);
+    }
+
+    #[test]
+    fn test_parse_stat_ids() {
+        assert_eq!(
+            parse_stat_ids("1234 (my (odd) prog) t 1200 1234 1100 34816 1234 4194560"),
+            Some([1200, 1234, 1100])
+        );
+        assert_eq!(parse_stat_ids("1234 (prog) t"), None);
+    }
+
+    #[test]
+    fn test_read_tasks() {
+        let pid = Pid::this();
+        assert!(read_tasks(pid).unwrap().contains(&pid));
+    }
+
+    #[test]
+    fn test_parse_auxv() {
+        let mut data = vec![];
+        for value in [
+            AT_PHDR,
+            0x555555554040,
+            AT_ENTRY,
+            0x555555555060,
+            0,
+            0,
+            42,
+            42,
+        ] {
+            data.extend_from_slice(&value.to_ne_bytes());
+        }
+        let auxv = parse_auxv(&data);
+
+        assert_eq!(auxv.len(), 2);
+        assert_eq!(auxv_value(&auxv, AT_ENTRY), Some(0x555555555060));
+        assert_eq!(auxv_value(&auxv, AT_BASE), None);
+    }
+}
@@ -0,0 +1,334 @@
+use std::{collections::HashMap, str::FromStr};
+
+use nix::{libc, sys::signal::Signal};
+
+/// What happens when the debuggee receives a signal, set with the `handle` command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalPolicy {
+    /// The debuggee stops and the prompt comes back
+    pub stop: bool,
+    /// The debugger tells the signal arrived
+    pub print: bool,
+    /// The signal is delivered to the debuggee when it is resumed, otherwise it is discarded
+    pub pass: bool,
+}
+
+impl SignalPolicy {
+    const STOP: SignalPolicy = SignalPolicy {
+        stop: true,
+        print: true,
+        pass: true,
+    };
+    const SILENT: SignalPolicy = SignalPolicy {
+        stop: false,
+        print: false,
+        pass: true,
+    };
+}
+
+/// The policy of every signal, the defaults being those of gdb
+#[derive(Debug, Clone)]
+pub struct SignalTable {
+    policies: HashMap<Signal, SignalPolicy>,
+}
+
+impl Default for SignalTable {
+    fn default() -> Self {
+        let policies = Signal::iterator()
+            .map(|signal| {
+                let policy = match signal {
+                    // part of the normal life of a program, stopping on them gets in the way
+                    Signal::SIGALRM
+                    | Signal::SIGURG
+                    | Signal::SIGCHLD
+                    | Signal::SIGWINCH
+                    | Signal::SIGIO
+                    | Signal::SIGVTALRM
+                    | Signal::SIGPROF => SignalPolicy::SILENT,
+                    // raised by the debugger itself, or typed to interrupt the program
+                    Signal::SIGTRAP | Signal::SIGINT => SignalPolicy {
+                        pass: false,
+                        ..SignalPolicy::STOP
+                    },
+                    _ => SignalPolicy::STOP,
+                };
+                (signal, policy)
+            })
+            .collect();
+        Self { policies }
+    }
+}
+
+impl SignalTable {
+    pub fn get(&self, signal: Signal) -> SignalPolicy {
+        self.policies
+            .get(&signal)
+            .copied()
+            .unwrap_or(SignalPolicy::STOP)
+    }
+
+    /// Applies the `handle` keywords in order. Like in gdb, stopping implies printing and
+    /// not printing implies not stopping. SIGTRAP is left alone, the breakpoints depend on it.
+    pub fn handle(&mut self, signal: Signal, keywords: &[&str]) -> Result<SignalPolicy, String> {
+        if signal == Signal::SIGTRAP && !keywords.is_empty() {
+            return Err(format!(
+                "{signal} is used by the debugger, it can't be changed"
+            ));
+        }
+        let mut policy = self.get(signal);
+        for keyword in keywords {
+            match *keyword {
+                "stop" => {
+                    policy.stop = true;
+                    policy.print = true;
+                }
+                "nostop" => policy.stop = false,
+                "print" => policy.print = true,
+                "noprint" => {
+                    policy.print = false;
+                    policy.stop = false;
+                }
+                "pass" | "noignore" => policy.pass = true,
+                "nopass" | "ignore" => policy.pass = false,
+                other => return Err(format!("Unrecognized or ambiguous flag word: \"{other}\"")),
+            }
+        }
+        self.policies.insert(signal, policy);
+        Ok(policy)
+    }
+
+    /// Every signal with its policy, in signal number order
+    pub fn iter(&self) -> impl Iterator<Item = (Signal, SignalPolicy)> + '_ {
+        Signal::iterator().map(|signal| (signal, self.get(signal)))
+    }
+}
+
+/// `SIGUSR1`, `usr1` or `10`
+pub fn parse_signal(name: &str) -> Result<Signal, String> {
+    if let Ok(number) = name.parse::<i32>() {
+        return Signal::try_from(number)
+            .map_err(|_| format!("Only signals 1-31 are valid, not {number}"));
+    }
+    let upper = name.to_ascii_uppercase();
+    let full = if upper.starts_with("SIG") {
+        upper
+    } else {
+        format!("SIG{upper}")
+    };
+    Signal::from_str(&full).map_err(|_| format!("Unknown signal {name}"))
+}
+
+/// The header of the table printed by `handle` and `info signals`
+pub const TABLE_HEADER: &str = "Signal        Stop\tPrint\tPass to program";
+
+/// A line of the table printed by `handle` and `info signals`
+pub fn describe_policy(signal: Signal, policy: SignalPolicy) -> String {
+    let yes_no = |value: bool| if value { "Yes" } else { "No" };
+    format!(
+        "{:<14}{}\t{}\t{}",
+        signal.as_str(),
+        yes_no(policy.stop),
+        yes_no(policy.print),
+        yes_no(policy.pass)
+    )
+}
+
+/// The parts of a `siginfo_t` worth showing, which ones are set depends on the signal and its code
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SigInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    /// Faulting address of SIGSEGV, SIGBUS, SIGILL and SIGFPE
+    pub addr: Option<u64>,
+    /// Process and user that sent the signal, or of the child for SIGCHLD
+    pub sender: Option<(i32, u32)>,
+    /// Exit code or signal of the child for SIGCHLD
+    pub status: Option<i32>,
+}
+
+impl SigInfo {
+    pub fn decode(info: &libc::siginfo_t) -> Self {
+        let mut decoded = SigInfo {
+            signo: info.si_signo,
+            errno: info.si_errno,
+            code: info.si_code,
+            ..Default::default()
+        };
+        // the union member to read is chosen like the kernel does when filling it
+        unsafe {
+            match info.si_signo {
+                libc::SIGSEGV | libc::SIGBUS | libc::SIGILL | libc::SIGFPE if info.si_code > 0 => {
+                    decoded.addr = Some(info.si_addr() as u64);
+                }
+                libc::SIGCHLD => {
+                    decoded.sender = Some((info.si_pid(), info.si_uid()));
+                    decoded.status = Some(info.si_status());
+                }
+                _ if info.si_code <= 0 => {
+                    decoded.sender = Some((info.si_pid(), info.si_uid()));
+                }
+                _ => {}
+            }
+        }
+        decoded
+    }
+
+    /// One `field = value` line per field, like `info siginfo` prints them
+    pub fn describe(&self) -> Vec<String> {
+        let signal = Signal::try_from(self.signo)
+            .map(|signal| signal.as_str())
+            .unwrap_or("?");
+        let mut lines = vec![
+            format!("si_signo = {} ({signal})", self.signo),
+            format!("si_errno = {}", self.errno),
+            format!(
+                "si_code = {} ({})",
+                self.code,
+                si_code_name(self.signo, self.code)
+            ),
+        ];
+        if let Some(addr) = self.addr {
+            lines.push(format!("si_addr = 0x{addr:x}"));
+        }
+        if let Some((pid, uid)) = self.sender {
+            lines.push(format!("si_pid = {pid}"));
+            lines.push(format!("si_uid = {uid}"));
+        }
+        if let Some(status) = self.status {
+            lines.push(format!("si_status = {status}"));
+        }
+        lines
+    }
+}
+
+/// Name of the `si_code` constant of `<siginfo.h>`, the positive ones depend on the signal
+pub fn si_code_name(signo: i32, code: i32) -> &'static str {
+    let names: &[&str] = match signo {
+        libc::SIGILL => &[
+            "ILL_ILLOPC",
+            "ILL_ILLOPN",
+            "ILL_ILLADR",
+            "ILL_ILLTRP",
+            "ILL_PRVOPC",
+            "ILL_PRVREG",
+            "ILL_COPROC",
+            "ILL_BADSTK",
+        ],
+        libc::SIGFPE => &[
+            "FPE_INTDIV",
+            "FPE_INTOVF",
+            "FPE_FLTDIV",
+            "FPE_FLTOVF",
+            "FPE_FLTUND",
+            "FPE_FLTRES",
+            "FPE_FLTINV",
+            "FPE_FLTSUB",
+        ],
+        libc::SIGSEGV => &["SEGV_MAPERR", "SEGV_ACCERR", "SEGV_BNDERR", "SEGV_PKUERR"],
+        libc::SIGBUS => &[
+            "BUS_ADRALN",
+            "BUS_ADRERR",
+            "BUS_OBJERR",
+            "BUS_MCEERR_AR",
+            "BUS_MCEERR_AO",
+        ],
+        libc::SIGTRAP => &["TRAP_BRKPT", "TRAP_TRACE", "TRAP_BRANCH", "TRAP_HWBKPT"],
+        libc::SIGCHLD => &[
+            "CLD_EXITED",
+            "CLD_KILLED",
+            "CLD_DUMPED",
+            "CLD_TRAPPED",
+            "CLD_STOPPED",
+            "CLD_CONTINUED",
+        ],
+        _ => &[],
+    };
+    match code {
+        0 => "SI_USER",
+        0x80 => "SI_KERNEL",
+        -1 => "SI_QUEUE",
+        -2 => "SI_TIMER",
+        -3 => "SI_MESGQ",
+        -4 => "SI_ASYNCIO",
+        -5 => "SI_SIGIO",
+        -6 => "SI_TKILL",
+        code if code > 0 => names.get(code as usize - 1).copied().unwrap_or("?"),
+        _ => "?",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_default_policies() {
+        let table = SignalTable::default();
+        assert_eq!(table.get(Signal::SIGCHLD), SignalPolicy::SILENT);
+        assert_eq!(table.get(Signal::SIGSEGV), SignalPolicy::STOP);
+        assert!(!table.get(Signal::SIGTRAP).pass);
+        assert_eq!(
+            describe_policy(Signal::SIGALRM, table.get(Signal::SIGALRM)),
+            "SIGALRM       No\tNo\tYes"
+        );
+    }
+
+    #[test]
+    fn test_handle_keywords() {
+        let mut table = SignalTable::default();
+        let policy = table
+            .handle(Signal::SIGUSR1, &["noprint", "nopass"])
+            .unwrap();
+        assert_eq!(
+            policy,
+            SignalPolicy {
+                stop: false,
+                print: false,
+                pass: false
+            }
+        );
+        assert_eq!(table.get(Signal::SIGUSR1), policy);
+        assert!(table.handle(Signal::SIGALRM, &["stop"]).unwrap().print);
+        assert!(table.handle(Signal::SIGUSR2, &["sometimes"]).is_err());
+        assert!(table.handle(Signal::SIGTRAP, &["pass"]).is_err());
+        assert!(!table.get(Signal::SIGINT).pass);
+        assert!(table.handle(Signal::SIGINT, &["pass"]).unwrap().pass);
+
+        assert_eq!(parse_signal("SIGUSR1"), Ok(Signal::SIGUSR1));
+        assert_eq!(parse_signal("alrm"), Ok(Signal::SIGALRM));
+        assert_eq!(parse_signal("15"), Ok(Signal::SIGTERM));
+        assert!(parse_signal("SIGFOO").is_err());
+    }
+
+    #[test]
+    fn test_describe_siginfo() {
+        assert_eq!(si_code_name(libc::SIGBUS, 2), "BUS_ADRERR");
+        assert_eq!(si_code_name(libc::SIGTRAP, 0x80), "SI_KERNEL");
+        assert_eq!(si_code_name(libc::SIGUSR1, 5), "?");
+
+        let segv = SigInfo {
+            signo: libc::SIGSEGV,
+            code: 1,
+            addr: Some(0x10),
+            ..Default::default()
+        };
+        assert_eq!(
+            segv.describe(),
+            vec![
+                "si_signo = 11 (SIGSEGV)",
+                "si_errno = 0",
+                "si_code = 1 (SEGV_MAPERR)",
+                "si_addr = 0x10",
+            ]
+        );
+        let sent = SigInfo {
+            signo: libc::SIGUSR1,
+            code: -6,
+            sender: Some((1234, 1000)),
+            ..Default::default()
+        };
+        assert_eq!(sent.describe()[2], "si_code = -6 (SI_TKILL)");
+        assert_eq!(sent.describe()[3], "si_pid = 1234");
+    }
+}
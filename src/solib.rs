@@ -0,0 +1,181 @@
+use std::ffi::c_void;
+
+use nix::unistd::Pid;
+
+use crate::breakpoint::PtraceOps;
+
+/// Dynamic section tags, from `<elf.h>`
+const DT_NULL: u64 = 0;
+const DT_DEBUG: u64 = 21;
+
+/// `r_state` of `struct r_debug` when the link map isn't being changed
+pub const RT_CONSISTENT: u64 = 0;
+
+/// Longest library path read from the tracee
+const MAX_PATH_LEN: usize = 4096;
+
+/// `struct r_debug` of `<link.h>`, through which the dynamic loader publishes the loaded libraries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RDebug {
+    pub version: u64,
+    /// Address of the first `struct link_map`
+    pub map: u64,
+    /// Address of the function the loader calls before and after changing the link map
+    pub brk: u64,
+    pub state: u64,
+    /// Load address of the dynamic loader
+    pub ldbase: u64,
+}
+
+/// An entry of the `struct link_map` list
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkMap {
+    /// Difference between the addresses in the file and in memory
+    pub addr: u64,
+    /// Path of the object, empty for the main program
+    pub name: String,
+    /// Runtime address of the object's dynamic section
+    pub ld: u64,
+}
+
+fn read_word<T: PtraceOps>(ops: &T, pid: Pid, address: u64) -> Option<u64> {
+    ops.read(pid, address as *mut c_void)
+        .ok()
+        .map(|word| word as u64)
+}
+
+pub fn read_c_string<T: PtraceOps>(ops: &T, pid: Pid, address: u64) -> Option<String> {
+    let mut bytes = vec![];
+    while bytes.len() < MAX_PATH_LEN {
+        let word = read_word(ops, pid, address + bytes.len() as u64)?;
+        for byte in word.to_ne_bytes() {
+            if byte == 0 {
+                return Some(String::from_utf8_lossy(&bytes).into_owned());
+            }
+            bytes.push(byte);
+        }
+    }
+    None
+}
+
+/// Address of `_r_debug`, from the `DT_DEBUG` entry of the program's dynamic section.
+/// It is `None` until the dynamic loader fills the entry in.
+pub fn find_r_debug<T: PtraceOps>(ops: &T, pid: Pid, dynamic: u64) -> Option<u64> {
+    let mut entry = dynamic;
+    loop {
+        let tag = read_word(ops, pid, entry)?;
+        match tag {
+            DT_NULL => return None,
+            DT_DEBUG => return read_word(ops, pid, entry + 8).filter(|&value| value != 0),
+            _ => entry += 16,
+        }
+    }
+}
+
+pub fn read_r_debug<T: PtraceOps>(ops: &T, pid: Pid, address: u64) -> Option<RDebug> {
+    Some(RDebug {
+        // r_version is an int followed by padding
+        version: read_word(ops, pid, address)? & 0xffff_ffff,
+        map: read_word(ops, pid, address + 8)?,
+        brk: read_word(ops, pid, address + 16)?,
+        state: read_word(ops, pid, address + 24)? & 0xffff_ffff,
+        ldbase: read_word(ops, pid, address + 32)?,
+    })
+}
+
+/// Follows the `l_next` pointers from `head`, stopping at the first unreadable entry
+pub fn read_link_map<T: PtraceOps>(ops: &T, pid: Pid, head: u64) -> Vec<LinkMap> {
+    let mut entries = vec![];
+    let mut node = head;
+    while node != 0 && entries.len() < 4096 {
+        let (Some(addr), Some(name), Some(ld), Some(next)) = (
+            read_word(ops, pid, node),
+            read_word(ops, pid, node + 8),
+            read_word(ops, pid, node + 16),
+            read_word(ops, pid, node + 24),
+        ) else {
+            break;
+        };
+        let name = match name {
+            0 => String::new(),
+            name => read_c_string(ops, pid, name).unwrap_or_default(),
+        };
+        entries.push(LinkMap { addr, name, ld });
+        node = next;
+    }
+    entries
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::breakpoint::MockPtraceOps;
+    use std::collections::HashMap;
+
+    fn memory(words: &[(u64, u64)]) -> MockPtraceOps {
+        let words: HashMap<u64, u64> = words.iter().copied().collect();
+        let mut ops = MockPtraceOps::new();
+        ops.expect_read().returning(move |_, addr| {
+            words
+                .get(&(addr as u64))
+                .map(|&word| word as i64)
+                .ok_or(nix::Error::EIO)
+        });
+        ops
+    }
+
+    #[test]
+    fn test_read_link_map() {
+        let pid = Pid::from_raw(1234);
+        let ops = memory(&[
+            // dynamic section: DT_NEEDED, DT_DEBUG, DT_NULL
+            (0x3000, 1),
+            (0x3008, 0x10),
+            (0x3010, DT_DEBUG),
+            (0x3018, 0x5000),
+            (0x3020, DT_NULL),
+            // _r_debug
+            (0x5000, 1),
+            (0x5008, 0x6000),
+            (0x5010, 0x7ffff7fd0100),
+            (0x5018, RT_CONSISTENT),
+            (0x5020, 0x7ffff7fc3000),
+            // the program then libc
+            (0x6000, 0),
+            (0x6008, 0),
+            (0x6010, 0x3000),
+            (0x6018, 0x6100),
+            (0x6100, 0x7ffff7dc3000),
+            (0x6108, 0x6200),
+            (0x6110, 0x7ffff7fa8000),
+            (0x6118, 0),
+            // "/lib/libc.so.6"
+            (0x6200, u64::from_ne_bytes(*b"/lib/lib")),
+            (0x6208, u64::from_ne_bytes(*b"c.so.6\0\0")),
+        ]);
+
+        let r_debug_addr = find_r_debug(&ops, pid, 0x3000).unwrap();
+        let r_debug = read_r_debug(&ops, pid, r_debug_addr).unwrap();
+        assert_eq!(r_debug.brk, 0x7ffff7fd0100);
+        assert_eq!(r_debug.state, RT_CONSISTENT);
+
+        let entries = read_link_map(&ops, pid, r_debug.map);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].name, "");
+        assert_eq!(
+            entries[1],
+            LinkMap {
+                addr: 0x7ffff7dc3000,
+                name: "/lib/libc.so.6".to_string(),
+                ld: 0x7ffff7fa8000,
+            }
+        );
+    }
+
+    #[test]
+    fn test_r_debug_not_filled_in() {
+        let pid = Pid::from_raw(1234);
+        let ops = memory(&[(0x3000, DT_DEBUG), (0x3008, 0)]);
+        assert_eq!(find_r_debug(&ops, pid, 0x3000), None);
+    }
+}
@@ -0,0 +1,610 @@
+use std::ffi::c_void;
+
+use nix::{errno::Errno, libc, sys::signal::Signal, unistd::Pid};
+use strum_macros::EnumString;
+
+use crate::{
+    arch::{self, Arch},
+    breakpoint::PtraceOps,
+    solib,
+};
+
+/// Most bytes of a buffer shown, like strace's default `-s 32`
+const MAX_SHOWN_BYTES: usize = 32;
+
+/// Most entries of a string array shown
+const MAX_SHOWN_STRINGS: usize = 32;
+
+/// Groups of syscalls a trace can be restricted to, written `%file` and so on like in strace
+#[derive(Debug, Clone, Copy, PartialEq, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum SyscallClass {
+    /// Takes a file name
+    File,
+    /// Takes or returns a file descriptor
+    Desc,
+    /// Creates, ends or waits for processes
+    Process,
+    /// Maps or unmaps memory
+    Memory,
+    Signal,
+    #[strum(serialize = "network", serialize = "net")]
+    Network,
+    /// System V IPC
+    Ipc,
+}
+
+/// How an argument is decoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arg {
+    Int,
+    Hex,
+    /// `NULL` or the address
+    Ptr,
+    Fd,
+    /// A file descriptor or `AT_FDCWD`
+    DirFd,
+    /// A NUL-terminated string
+    Path,
+    /// Bytes the syscall reads, their count is the argument at this index
+    InBuf(usize),
+    /// Bytes the syscall fills in, as many as it returns
+    OutBuf,
+    /// `O_RDONLY|O_CLOEXEC`, the mode that follows is only shown with `O_CREAT`
+    OpenFlags,
+    /// Permissions, in octal
+    Mode,
+    Prot,
+    MapFlags,
+    Signal,
+    /// A `struct timespec`
+    Timespec,
+    /// The two descriptors `pipe` fills in
+    PipeFds,
+    /// A NULL-terminated array of strings, like `argv`
+    Strings,
+}
+
+impl Arg {
+    /// Whether the argument is only known once the syscall returns
+    fn is_output(self) -> bool {
+        matches!(self, Arg::OutBuf | Arg::PipeFds)
+    }
+}
+
+use Arg::*;
+use SyscallClass::{Desc, File, Ipc, Memory, Network, Process};
+
+/// Arguments and classes of the common syscalls, the others have their raw arguments shown
+static SIGNATURES: &[(&str, &[Arg], &[SyscallClass])] = &[
+    ("read", &[Fd, OutBuf, Int], &[Desc]),
+    ("write", &[Fd, InBuf(2), Int], &[Desc]),
+    ("pread64", &[Fd, OutBuf, Int, Int], &[Desc]),
+    ("pwrite64", &[Fd, InBuf(2), Int, Int], &[Desc]),
+    ("open", &[Path, OpenFlags, Mode], &[File, Desc]),
+    ("openat", &[DirFd, Path, OpenFlags, Mode], &[File, Desc]),
+    ("creat", &[Path, Mode], &[File, Desc]),
+    ("close", &[Fd], &[Desc]),
+    ("stat", &[Path, Ptr], &[File]),
+    ("lstat", &[Path, Ptr], &[File]),
+    ("fstat", &[Fd, Ptr], &[Desc]),
+    ("newfstatat", &[DirFd, Path, Ptr, Hex], &[File, Desc]),
+    ("statx", &[DirFd, Path, Hex, Hex, Ptr], &[File, Desc]),
+    ("lseek", &[Fd, Int, Int], &[Desc]),
+    ("access", &[Path, Int], &[File]),
+    ("faccessat", &[DirFd, Path, Int], &[File, Desc]),
+    ("faccessat2", &[DirFd, Path, Int, Hex], &[File, Desc]),
+    ("readlink", &[Path, OutBuf, Int], &[File]),
+    ("readlinkat", &[DirFd, Path, OutBuf, Int], &[File, Desc]),
+    ("unlink", &[Path], &[File]),
+    ("unlinkat", &[DirFd, Path, Hex], &[File, Desc]),
+    ("mkdir", &[Path, Mode], &[File]),
+    ("mkdirat", &[DirFd, Path, Mode], &[File, Desc]),
+    ("chdir", &[Path], &[File]),
+    ("getcwd", &[OutBuf, Int], &[File]),
+    ("execve", &[Path, Strings, Ptr], &[File, Process]),
+    ("ioctl", &[Fd, Hex, Hex], &[Desc]),
+    ("fcntl", &[Fd, Int, Hex], &[Desc]),
+    ("dup", &[Fd], &[Desc]),
+    ("dup2", &[Fd, Fd], &[Desc]),
+    ("dup3", &[Fd, Fd, Hex], &[Desc]),
+    ("pipe", &[PipeFds], &[Desc]),
+    ("pipe2", &[PipeFds, Hex], &[Desc]),
+    ("getdents64", &[Fd, Ptr, Int], &[Desc]),
+    (
+        "mmap",
+        &[Ptr, Int, Prot, MapFlags, Fd, Hex],
+        &[Memory, Desc],
+    ),
+    ("mprotect", &[Ptr, Int, Prot], &[Memory]),
+    ("munmap", &[Ptr, Int], &[Memory]),
+    ("mremap", &[Ptr, Int, Int, Hex, Ptr], &[Memory]),
+    ("brk", &[Ptr], &[Memory]),
+    ("madvise", &[Ptr, Int, Int], &[Memory]),
+    ("clone", &[Hex, Ptr, Ptr, Ptr, Hex], &[Process]),
+    ("clone3", &[Ptr, Int], &[Process]),
+    ("fork", &[], &[Process]),
+    ("vfork", &[], &[Process]),
+    ("exit", &[Int], &[Process]),
+    ("exit_group", &[Int], &[Process]),
+    ("wait4", &[Int, Ptr, Hex, Ptr], &[Process]),
+    ("kill", &[Int, Signal], &[Process, SyscallClass::Signal]),
+    ("tkill", &[Int, Signal], &[Process, SyscallClass::Signal]),
+    (
+        "tgkill",
+        &[Int, Int, Signal],
+        &[Process, SyscallClass::Signal],
+    ),
+    (
+        "rt_sigaction",
+        &[Signal, Ptr, Ptr, Int],
+        &[SyscallClass::Signal],
+    ),
+    (
+        "rt_sigprocmask",
+        &[Int, Ptr, Ptr, Int],
+        &[SyscallClass::Signal],
+    ),
+    ("rt_sigreturn", &[], &[SyscallClass::Signal]),
+    ("pause", &[], &[SyscallClass::Signal]),
+    ("nanosleep", &[Timespec, Ptr], &[]),
+    ("clock_nanosleep", &[Int, Hex, Timespec, Ptr], &[]),
+    ("socket", &[Int, Int, Int], &[Network, Desc]),
+    ("connect", &[Fd, Ptr, Int], &[Network, Desc]),
+    ("bind", &[Fd, Ptr, Int], &[Network, Desc]),
+    ("listen", &[Fd, Int], &[Network, Desc]),
+    ("accept", &[Fd, Ptr, Ptr], &[Network, Desc]),
+    ("accept4", &[Fd, Ptr, Ptr, Hex], &[Network, Desc]),
+    (
+        "sendto",
+        &[Fd, InBuf(2), Int, Hex, Ptr, Int],
+        &[Network, Desc],
+    ),
+    (
+        "recvfrom",
+        &[Fd, OutBuf, Int, Hex, Ptr, Ptr],
+        &[Network, Desc],
+    ),
+    ("shutdown", &[Fd, Int], &[Network, Desc]),
+    ("shmget", &[Hex, Int, Hex], &[Ipc]),
+    ("shmat", &[Int, Ptr, Hex], &[Ipc, Memory]),
+    ("shmdt", &[Ptr], &[Ipc, Memory]),
+    ("semget", &[Hex, Int, Hex], &[Ipc]),
+    ("msgget", &[Hex, Hex], &[Ipc]),
+    ("getrandom", &[OutBuf, Int, Hex], &[]),
+    ("set_tid_address", &[Ptr], &[]),
+    ("set_robust_list", &[Ptr, Int], &[]),
+    ("rseq", &[Ptr, Int, Hex, Hex], &[]),
+    ("prlimit64", &[Int, Int, Ptr, Ptr], &[]),
+    ("arch_prctl", &[Hex, Ptr], &[]),
+    ("futex", &[Ptr, Int, Int, Ptr, Ptr, Int], &[]),
+];
+
+/// Syscalls returning an address rather than a number
+const ADDRESS_RETURNING: &[&str] = &["brk", "mmap", "mremap", "shmat"];
+
+/// Syscalls whose return is never seen
+const NEVER_RETURNING: &[&str] = &["exit", "exit_group"];
+
+fn signature(name: &str) -> Option<(&'static [Arg], &'static [SyscallClass])> {
+    SIGNATURES
+        .iter()
+        .find(|(known, ..)| *known == name)
+        .map(|&(_, args, classes)| (args, classes))
+}
+
+/// The syscalls a trace shows, by name or class, every syscall when empty
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyscallFilter {
+    names: Vec<String>,
+    classes: Vec<SyscallClass>,
+}
+
+impl SyscallFilter {
+    /// Names of the syscalls of the machine the debugger runs on and classes written `%file`
+    pub fn parse_native(words: &[&str]) -> Result<Self, String> {
+        Self::parse(arch::native(), words)
+    }
+
+    /// Names of the architecture's syscalls and classes written `%file`
+    pub(crate) fn parse(arch: &dyn Arch, words: &[&str]) -> Result<Self, String> {
+        let mut filter = SyscallFilter::default();
+        for word in words {
+            if let Some(class) = word.strip_prefix('%') {
+                let class = class
+                    .parse()
+                    .map_err(|_| format!("Unknown syscall class '{word}'"))?;
+                filter.classes.push(class);
+            } else if arch.syscalls().iter().any(|(_, name)| name == word) {
+                filter.names.push(word.to_string());
+            } else {
+                return Err(format!("Unknown syscall name '{word}'"));
+            }
+        }
+        Ok(filter)
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        if self.names.is_empty() && self.classes.is_empty() {
+            return true;
+        }
+        self.names.iter().any(|known| known == name)
+            || signature(name).is_some_and(|(_, classes)| {
+                classes.iter().any(|class| self.classes.contains(class))
+            })
+    }
+
+    /// `every syscall`, or `openat, %network`
+    pub fn describe(&self) -> String {
+        if self.names.is_empty() && self.classes.is_empty() {
+            return "every syscall".to_string();
+        }
+        let classes = self
+            .classes
+            .iter()
+            .map(|class| format!("%{}", format!("{class:?}").to_lowercase()));
+        self.names
+            .iter()
+            .cloned()
+            .chain(classes)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// A traced syscall between its entry and its return. The arguments it reads are decoded
+/// on entry, those it fills in on return.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedCall {
+    pub name: String,
+    args: Vec<u64>,
+    kinds: Vec<Arg>,
+    decoded: Vec<Option<String>>,
+}
+
+impl TracedCall {
+    pub fn enter<T: PtraceOps>(ops: &T, pid: Pid, name: String, args: &[u64]) -> Self {
+        let mut kinds = match signature(&name) {
+            Some((kinds, _)) => kinds.to_vec(),
+            None => vec![Hex; args.len()],
+        };
+        // like in strace, the mode of open is left out when no file is created
+        if let Some(index) = kinds.iter().position(|&kind| kind == OpenFlags) {
+            if args[index] as i32 & (libc::O_CREAT | libc::O_TMPFILE) == 0 {
+                kinds.truncate(index + 1);
+            }
+        }
+        let decoded = kinds
+            .iter()
+            .zip(args)
+            .map(|(&kind, &value)| {
+                (!kind.is_output()).then(|| decode_input(ops, pid, kind, value, args))
+            })
+            .collect();
+        Self {
+            name,
+            args: args.to_vec(),
+            kinds,
+            decoded,
+        }
+    }
+
+    /// Whether the process or thread ends in the syscall
+    pub fn never_returns(&self) -> bool {
+        NEVER_RETURNING.contains(&self.name.as_str())
+    }
+
+    /// `exit_group(0) = ?`, for the syscalls that don't return
+    pub fn describe_unfinished(&self) -> String {
+        let args: Vec<String> = self
+            .decoded
+            .iter()
+            .map(|decoded| decoded.clone().unwrap_or_else(|| "...".to_string()))
+            .collect();
+        format!("{}({}) = ?", self.name, args.join(", "))
+    }
+
+    /// `read(3, "\x7fELF\x02\x01\x01"..., 832) = 832`
+    pub fn describe_return<T: PtraceOps>(&self, ops: &T, pid: Pid, value: u64) -> String {
+        let failed = is_error(value);
+        let args: Vec<String> = self
+            .decoded
+            .iter()
+            .zip(&self.kinds)
+            .zip(&self.args)
+            .map(|((decoded, &kind), &address)| match decoded {
+                Some(decoded) => decoded.clone(),
+                None if failed => describe_pointer(address),
+                None => decode_output(ops, pid, kind, address, value),
+            })
+            .collect();
+        format!(
+            "{}({}) = {}",
+            self.name,
+            args.join(", "),
+            describe_return_value(&self.name, value)
+        )
+    }
+}
+
+fn is_error(value: u64) -> bool {
+    // the kernel returns errors as -errno, in the last page of the address space
+    (-4095..0).contains(&(value as i64))
+}
+
+/// `3`, `0x7ffff7fc1000` or `-1 ENOENT (No such file or directory)`
+pub fn describe_return_value(name: &str, value: u64) -> String {
+    if is_error(value) {
+        let errno = Errno::from_raw(-(value as i64) as i32);
+        format!("-1 {errno:?} ({})", errno.desc())
+    } else if ADDRESS_RETURNING.contains(&name) {
+        format!("0x{value:x}")
+    } else {
+        (value as i64).to_string()
+    }
+}
+
+fn describe_pointer(address: u64) -> String {
+    match address {
+        0 => "NULL".to_string(),
+        address => format!("0x{address:x}"),
+    }
+}
+
+fn decode_input<T: PtraceOps>(ops: &T, pid: Pid, kind: Arg, value: u64, args: &[u64]) -> String {
+    let unreadable = || describe_pointer(value);
+    match kind {
+        Int => (value as i64).to_string(),
+        Hex => format!("0x{value:x}"),
+        Ptr | OutBuf | PipeFds => describe_pointer(value),
+        Fd => (value as i32).to_string(),
+        DirFd if value as i32 == libc::AT_FDCWD => "AT_FDCWD".to_string(),
+        DirFd => (value as i32).to_string(),
+        Path => read_string(ops, pid, value).unwrap_or_else(unreadable),
+        InBuf(len) => read_buffer(ops, pid, value, args[len] as usize).unwrap_or_else(unreadable),
+        OpenFlags => describe_open_flags(value as i32),
+        Mode => format!("0{:o}", value as u32),
+        Prot if value == 0 => "PROT_NONE".to_string(),
+        Prot => describe_flags(
+            value,
+            &[
+                (libc::PROT_READ, "PROT_READ"),
+                (libc::PROT_WRITE, "PROT_WRITE"),
+                (libc::PROT_EXEC, "PROT_EXEC"),
+            ],
+        ),
+        MapFlags => describe_flags(
+            value,
+            &[
+                (libc::MAP_SHARED, "MAP_SHARED"),
+                (libc::MAP_PRIVATE, "MAP_PRIVATE"),
+                (libc::MAP_FIXED, "MAP_FIXED"),
+                (libc::MAP_ANONYMOUS, "MAP_ANONYMOUS"),
+                (libc::MAP_DENYWRITE, "MAP_DENYWRITE"),
+                (libc::MAP_NORESERVE, "MAP_NORESERVE"),
+                (libc::MAP_POPULATE, "MAP_POPULATE"),
+                (libc::MAP_STACK, "MAP_STACK"),
+                (libc::MAP_FIXED_NOREPLACE, "MAP_FIXED_NOREPLACE"),
+            ],
+        ),
+        Signal => match Signal::try_from(value as i32) {
+            Ok(signal) => signal.as_str().to_string(),
+            Err(_) => (value as i64).to_string(),
+        },
+        Timespec => match read_bytes(ops, pid, value, 16) {
+            Some(bytes) => format!(
+                "{{tv_sec={}, tv_nsec={}}}",
+                i64::from_ne_bytes(bytes[..8].try_into().unwrap()),
+                i64::from_ne_bytes(bytes[8..].try_into().unwrap())
+            ),
+            None => unreadable(),
+        },
+        Strings => read_strings(ops, pid, value).unwrap_or_else(unreadable),
+    }
+}
+
+/// An argument the syscall filled in, `value` being what it returned
+fn decode_output<T: PtraceOps>(ops: &T, pid: Pid, kind: Arg, address: u64, value: u64) -> String {
+    let decoded = match kind {
+        OutBuf => read_buffer(ops, pid, address, value as usize),
+        PipeFds => read_bytes(ops, pid, address, 8).map(|bytes| {
+            format!(
+                "[{}, {}]",
+                i32::from_ne_bytes(bytes[..4].try_into().unwrap()),
+                i32::from_ne_bytes(bytes[4..].try_into().unwrap())
+            )
+        }),
+        _ => None,
+    };
+    decoded.unwrap_or_else(|| describe_pointer(address))
+}
+
+/// The names of the bits set in `value`, followed by those without a name
+fn describe_flags(value: u64, names: &[(i32, &str)]) -> String {
+    let mut rest = value;
+    let mut set: Vec<String> = vec![];
+    for &(flag, name) in names {
+        let flag = flag as u64;
+        if flag != 0 && value & flag == flag {
+            set.push(name.to_string());
+            rest &= !flag;
+        }
+    }
+    if rest != 0 || set.is_empty() {
+        set.push(format!("0x{rest:x}"));
+    }
+    set.join("|")
+}
+
+fn describe_open_flags(flags: i32) -> String {
+    let access = match flags & libc::O_ACCMODE {
+        libc::O_WRONLY => "O_WRONLY",
+        libc::O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    };
+    let others = (flags & !libc::O_ACCMODE) as u64;
+    if others == 0 {
+        return access.to_string();
+    }
+    let others = describe_flags(
+        others,
+        &[
+            (libc::O_CREAT, "O_CREAT"),
+            (libc::O_EXCL, "O_EXCL"),
+            (libc::O_NOCTTY, "O_NOCTTY"),
+            (libc::O_TRUNC, "O_TRUNC"),
+            (libc::O_APPEND, "O_APPEND"),
+            (libc::O_NONBLOCK, "O_NONBLOCK"),
+            (libc::O_DIRECTORY, "O_DIRECTORY"),
+            (libc::O_NOFOLLOW, "O_NOFOLLOW"),
+            (libc::O_CLOEXEC, "O_CLOEXEC"),
+            (libc::O_PATH, "O_PATH"),
+        ],
+    );
+    format!("{access}|{others}")
+}
+
+fn read_bytes<T: PtraceOps>(ops: &T, pid: Pid, address: u64, len: usize) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(len + 8);
+    while bytes.len() < len {
+        let word = ops
+            .read(pid, (address + bytes.len() as u64) as *mut c_void)
+            .ok()?;
+        bytes.extend_from_slice(&word.to_ne_bytes());
+    }
+    bytes.truncate(len);
+    Some(bytes)
+}
+
+/// The first bytes of a buffer of `len` bytes, quoted
+fn read_buffer<T: PtraceOps>(ops: &T, pid: Pid, address: u64, len: usize) -> Option<String> {
+    let bytes = read_bytes(ops, pid, address, len.min(MAX_SHOWN_BYTES))?;
+    let ellipsis = if len > MAX_SHOWN_BYTES { "..." } else { "" };
+    Some(format!("{}{ellipsis}", quote(&bytes)))
+}
+
+fn read_string<T: PtraceOps>(ops: &T, pid: Pid, address: u64) -> Option<String> {
+    solib::read_c_string(ops, pid, address).map(|string| quote(string.as_bytes()))
+}
+
+/// `["ls", "-l"]`
+fn read_strings<T: PtraceOps>(ops: &T, pid: Pid, address: u64) -> Option<String> {
+    let mut strings = vec![];
+    loop {
+        let pointer = read_bytes(ops, pid, address + 8 * strings.len() as u64, 8)?;
+        let pointer = u64::from_ne_bytes(pointer.try_into().unwrap());
+        if pointer == 0 {
+            break;
+        }
+        if strings.len() == MAX_SHOWN_STRINGS {
+            strings.push("...".to_string());
+            break;
+        }
+        strings.push(read_string(ops, pid, pointer)?);
+    }
+    Some(format!("[{}]", strings.join(", ")))
+}
+
+/// Quotes `bytes` as a C string, escaping what isn't printable
+fn quote(bytes: &[u8]) -> String {
+    let mut quoted = String::from('"');
+    for &byte in bytes {
+        match byte {
+            b'\n' => quoted.push_str("\\n"),
+            b'\t' => quoted.push_str("\\t"),
+            b'\r' => quoted.push_str("\\r"),
+            b'"' => quoted.push_str("\\\""),
+            b'\\' => quoted.push_str("\\\\"),
+            b' '..=b'~' => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\x{byte:02x}")),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{arch::X86_64, breakpoint::MockPtraceOps};
+    use std::collections::HashMap;
+
+    fn memory(words: &[(u64, u64)]) -> MockPtraceOps {
+        let words: HashMap<u64, u64> = words.iter().copied().collect();
+        let mut ops = MockPtraceOps::new();
+        ops.expect_read().returning(move |_, addr| {
+            words
+                .get(&(addr as u64))
+                .map(|&word| word as i64)
+                .ok_or(nix::Error::EIO)
+        });
+        ops
+    }
+
+    #[test]
+    fn test_decode_arguments() {
+        let pid = Pid::from_raw(1234);
+        let ops = memory(&[
+            (0x1000, u64::from_ne_bytes(*b"/etc/pas")),
+            (0x1008, u64::from_ne_bytes(*b"swd\0\0\0\0\0")),
+            (0x2000, u64::from_ne_bytes(*b"hi\n\0\0\0\0\0")),
+            (0x3000, 0x0000_0004_0000_0003),
+        ]);
+
+        let open = TracedCall::enter(
+            &ops,
+            pid,
+            "openat".to_string(),
+            &[libc::AT_FDCWD as u64, 0x1000, 0o2000000, 0, 0, 0],
+        );
+        assert_eq!(
+            open.describe_return(&ops, pid, 3),
+            "openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY|O_CLOEXEC) = 3"
+        );
+        assert_eq!(
+            open.describe_return(&ops, pid, -2i64 as u64),
+            "openat(AT_FDCWD, \"/etc/passwd\", O_RDONLY|O_CLOEXEC) = -1 ENOENT (No such file or directory)"
+        );
+
+        let write = TracedCall::enter(&ops, pid, "write".to_string(), &[1, 0x2000, 3, 0, 0, 0]);
+        assert_eq!(
+            write.describe_return(&ops, pid, 3),
+            "write(1, \"hi\\n\", 3) = 3"
+        );
+
+        // the descriptors are only read once pipe has filled them in
+        let pipe = TracedCall::enter(&ops, pid, "pipe".to_string(), &[0x3000, 0, 0, 0, 0, 0]);
+        assert_eq!(pipe.describe_return(&ops, pid, 0), "pipe([3, 4]) = 0");
+
+        let mmap = TracedCall::enter(
+            &ops,
+            pid,
+            "mmap".to_string(),
+            &[0, 0x1000, 3, 0x22, -1i64 as u64, 0],
+        );
+        assert_eq!(
+            mmap.describe_return(&ops, pid, 0x7ffff7fc1000),
+            "mmap(NULL, 4096, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0x0) = 0x7ffff7fc1000"
+        );
+
+        let exit = TracedCall::enter(&ops, pid, "exit_group".to_string(), &[0; 6]);
+        assert!(exit.never_returns());
+        assert_eq!(exit.describe_unfinished(), "exit_group(0) = ?");
+    }
+
+    #[test]
+    fn test_syscall_filter() {
+        let filter = SyscallFilter::parse(&X86_64, &["write", "%file"]).unwrap();
+        assert!(filter.matches("write"));
+        assert!(filter.matches("openat"));
+        assert!(!filter.matches("mmap"));
+        assert_eq!(filter.describe(), "write, %file");
+
+        assert!(SyscallFilter::parse(&X86_64, &["%net"])
+            .unwrap()
+            .matches("connect"));
+        assert!(SyscallFilter::parse(&X86_64, &["%disk"]).is_err());
+        assert!(SyscallFilter::parse(&X86_64, &["wirte"]).is_err());
+        assert!(SyscallFilter::default().matches("mmap"));
+    }
+}
@@ -0,0 +1,517 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    procfs::{auxv_value, MemoryMap, AT_ENTRY, AT_PHDR},
+    unwind::FrameRule,
+};
+
+use object::{
+    elf,
+    read::elf::{ElfFile64, ProgramHeader},
+    Endianness, Object, ObjectSection, ObjectSymbol, SymbolKind,
+};
+
+/// A function or variable from the symbol tables, at its link-time address
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: u64,
+    pub size: u64,
+    pub is_function: bool,
+}
+
+/// A row of the DWARF line table
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LineRow {
+    address: u64,
+    /// Index in `ObjectFile::files`
+    file: usize,
+    line: u64,
+    is_stmt: bool,
+    /// First address after a sequence of rows, it doesn't belong to any line
+    end_sequence: bool,
+}
+
+/// A source line found at an address
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine<'a> {
+    pub file: &'a Path,
+    pub line: u64,
+    /// Address of the start of the row containing the looked up address
+    pub address: u64,
+}
+
+/// A `PT_LOAD` segment, where part of the file is mapped in memory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadSegment {
+    pub vaddr: u64,
+    pub memsz: u64,
+    pub offset: u64,
+    pub filesz: u64,
+    pub flags: u32,
+}
+
+/// What the debugger knows about an ELF file: its layout, symbols and
+/// line table, all at link-time addresses.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectFile {
+    pub path: PathBuf,
+    /// `ET_DYN` files (PIE executables and shared libraries) are loaded at a random base
+    pub is_position_independent: bool,
+    pub entry: u64,
+    /// Address of the program headers (`PT_PHDR`), used with `AT_PHDR` to find the load bias
+    pub phdr_vaddr: Option<u64>,
+    pub segments: Vec<LoadSegment>,
+    /// Address of the dynamic section, where `DT_DEBUG` lives
+    pub dynamic_vaddr: Option<u64>,
+    /// Sorted by address
+    symbols: Vec<Symbol>,
+    files: Vec<PathBuf>,
+    /// Sorted by address
+    lines: Vec<LineRow>,
+    /// Call frame information telling how to unwind the stack, and its link-time address
+    eh_frame: Vec<u8>,
+    eh_frame_address: u64,
+}
+
+impl ObjectFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data =
+            fs::read(path).map_err(|err| format!("Cannot read {}: {err}", path.display()))?;
+        Self::parse(path, &data)
+    }
+
+    pub fn parse(path: &Path, data: &[u8]) -> Result<Self, String> {
+        let file = ElfFile64::<Endianness>::parse(data)
+            .map_err(|err| format!("{} is not a 64 bits ELF file: {err}", path.display()))?;
+        let endian = file.endian();
+
+        let mut object = ObjectFile {
+            path: path.to_path_buf(),
+            is_position_independent: file.elf_header().e_type.get(endian) == elf::ET_DYN,
+            entry: file.entry(),
+            ..Default::default()
+        };
+
+        for header in file.elf_program_headers() {
+            match header.p_type(endian) {
+                elf::PT_PHDR => object.phdr_vaddr = Some(header.p_vaddr(endian)),
+                elf::PT_DYNAMIC => object.dynamic_vaddr = Some(header.p_vaddr(endian)),
+                elf::PT_LOAD => object.segments.push(LoadSegment {
+                    vaddr: header.p_vaddr(endian),
+                    memsz: header.p_memsz(endian),
+                    offset: header.p_offset(endian),
+                    filesz: header.p_filesz(endian),
+                    flags: header.p_flags(endian),
+                }),
+                _ => {}
+            }
+        }
+        // without PT_PHDR, the headers are usually mapped with the first segment
+        if object.phdr_vaddr.is_none() {
+            let phoff = file.elf_header().e_phoff.get(endian);
+            object.phdr_vaddr = object
+                .segments
+                .iter()
+                .find(|seg| seg.offset <= phoff && phoff < seg.offset + seg.filesz)
+                .map(|seg| seg.vaddr + phoff - seg.offset);
+        }
+
+        if let Some(section) = file.section_by_name(".eh_frame") {
+            object.eh_frame_address = section.address();
+            object.eh_frame = section.data().map(<[u8]>::to_vec).unwrap_or_default();
+        }
+        object.load_symbols(&file);
+        // a stripped or non-DWARF file still has its symbols
+        let _ = object.load_lines(&file);
+        Ok(object)
+    }
+
+    fn load_symbols(&mut self, file: &ElfFile64<Endianness>) {
+        let symbols = file.symbols().chain(file.dynamic_symbols());
+        for sym in symbols {
+            if sym.is_undefined() || sym.address() == 0 {
+                continue;
+            }
+            let is_function = sym.kind() == SymbolKind::Text;
+            if !is_function && sym.kind() != SymbolKind::Data {
+                continue;
+            }
+            let Ok(name) = sym.name() else {
+                continue;
+            };
+            if name.is_empty() {
+                continue;
+            }
+            self.symbols.push(Symbol {
+                name: name.to_string(),
+                address: sym.address(),
+                size: sym.size(),
+                is_function,
+            });
+        }
+        self.symbols
+            .sort_by(|a, b| a.address.cmp(&b.address).then(a.name.cmp(&b.name)));
+        self.symbols
+            .dedup_by(|a, b| a.address == b.address && a.name == b.name);
+    }
+
+    fn load_lines(&mut self, file: &ElfFile64<Endianness>) -> Result<(), gimli::Error> {
+        let load_section = |id: gimli::SectionId| -> Result<Cow<[u8]>, gimli::Error> {
+            Ok(file
+                .section_by_name(id.name())
+                .and_then(|section| section.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[])))
+        };
+        let sections = gimli::DwarfSections::load(load_section)?;
+        let dwarf =
+            sections.borrow(|section| gimli::EndianSlice::new(section, gimli::LittleEndian));
+
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            let unit = dwarf.unit(header)?;
+            let Some(program) = unit.line_program.clone() else {
+                continue;
+            };
+            let comp_dir = unit
+                .comp_dir
+                .map(|dir| PathBuf::from(dir.to_string_lossy().into_owned()))
+                .unwrap_or_default();
+
+            let mut rows = program.rows();
+            while let Some((header, row)) = rows.next_row()? {
+                let Some(entry) = row.file(header) else {
+                    continue;
+                };
+                let mut path = comp_dir.clone();
+                if let Some(dir) = entry.directory(header) {
+                    path.push(dwarf.attr_string(&unit, dir)?.to_string_lossy().as_ref());
+                }
+                path.push(
+                    dwarf
+                        .attr_string(&unit, entry.path_name())?
+                        .to_string_lossy()
+                        .as_ref(),
+                );
+
+                let file = match self.files.iter().position(|known| *known == path) {
+                    Some(index) => index,
+                    None => {
+                        self.files.push(path);
+                        self.files.len() - 1
+                    }
+                };
+                self.lines.push(LineRow {
+                    address: row.address(),
+                    file,
+                    line: row.line().map(|line| line.get()).unwrap_or(0),
+                    is_stmt: row.is_stmt(),
+                    end_sequence: row.end_sequence(),
+                });
+            }
+        }
+        self.lines
+            .sort_by_key(|row| (row.address, !row.end_sequence));
+        Ok(())
+    }
+
+    pub fn find_symbol(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .filter(|sym| sym.name == name)
+            .max_by_key(|sym| sym.is_function)
+    }
+
+    /// The symbol containing `address` and the offset of `address` inside it
+    pub fn symbol_at(&self, address: u64) -> Option<(&Symbol, u64)> {
+        let index = self.symbols.partition_point(|sym| sym.address <= address);
+        self.symbols[..index]
+            .iter()
+            .rev()
+            .find(|sym| address < sym.address + sym.size.max(1))
+            .map(|sym| (sym, address - sym.address))
+    }
+
+    /// How to find the caller's frame from `address`, `None` without call frame information
+    pub fn frame_rule(&self, address: u64) -> Option<FrameRule> {
+        use gimli::UnwindSection;
+
+        let eh_frame = gimli::EhFrame::new(&self.eh_frame, gimli::LittleEndian);
+        let bases = gimli::BaseAddresses::default().set_eh_frame(self.eh_frame_address);
+        let fde = eh_frame
+            .fde_for_address(&bases, address, gimli::EhFrame::cie_from_offset)
+            .ok()?;
+        let mut context = gimli::UnwindContext::new();
+        let row = fde
+            .unwind_info_for_address(&eh_frame, &bases, &mut context, address)
+            .ok()?;
+        let gimli::CfaRule::RegisterAndOffset { register, offset } = row.cfa() else {
+            return None;
+        };
+        let saved = row
+            .registers()
+            .filter_map(|(reg, rule)| match rule {
+                gimli::RegisterRule::Offset(offset) => Some((reg.0, *offset)),
+                _ => None,
+            })
+            .collect();
+        Some(FrameRule {
+            cfa_register: register.0,
+            cfa_offset: *offset,
+            saved,
+            return_address_register: fde.cie().return_address_register().0,
+        })
+    }
+
+    pub fn has_line_info(&self) -> bool {
+        !self.lines.is_empty()
+    }
+
+    /// Addresses of the code generated for `line` of the source file ending with `file`.
+    /// Like gdb, a line without code moves to the next line having some.
+    pub fn line_addresses(&self, file: &str, line: u64) -> Vec<u64> {
+        let matching_file = |row: &LineRow| self.files[row.file].ends_with(file);
+        let Some(best_line) = self
+            .lines
+            .iter()
+            .filter(|row| !row.end_sequence && row.is_stmt && row.line >= line)
+            .filter(|row| matching_file(row))
+            .map(|row| row.line)
+            .min()
+        else {
+            return vec![];
+        };
+
+        let mut addresses: Vec<u64> = vec![];
+        for (i, row) in self.lines.iter().enumerate() {
+            if row.end_sequence || !row.is_stmt || row.line != best_line || !matching_file(row) {
+                continue;
+            }
+            // only the first row of a block of rows for that line
+            let follows_same_line = i > 0
+                && self.lines[i - 1].line == best_line
+                && self.lines[i - 1].file == row.file
+                && !self.lines[i - 1].end_sequence;
+            if !follows_same_line {
+                addresses.push(row.address);
+            }
+        }
+        addresses
+    }
+
+    pub fn line_at(&self, address: u64) -> Option<SourceLine<'_>> {
+        let index = self.lines.partition_point(|row| row.address <= address);
+        let row = self.lines[..index].last()?;
+        if row.end_sequence {
+            return None;
+        }
+        Some(SourceLine {
+            file: &self.files[row.file],
+            line: row.line,
+            address: row.address,
+        })
+    }
+}
+
+/// An object file loaded in the tracee, `bias` bytes away from its link-time addresses
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub object: ObjectFile,
+    pub bias: u64,
+}
+
+impl Module {
+    pub fn new(object: ObjectFile, bias: u64) -> Self {
+        Self { object, bias }
+    }
+
+    pub fn to_runtime(&self, address: u64) -> u64 {
+        address.wrapping_add(self.bias)
+    }
+
+    pub fn to_link(&self, address: u64) -> u64 {
+        address.wrapping_sub(self.bias)
+    }
+
+    pub fn contains(&self, address: u64) -> bool {
+        let address = self.to_link(address);
+        self.object
+            .segments
+            .iter()
+            .any(|seg| seg.vaddr <= address && address < seg.vaddr + seg.memsz)
+    }
+
+    /// Runtime range covered by the executable segments
+    pub fn code_range(&self) -> Option<(u64, u64)> {
+        let code = self
+            .object
+            .segments
+            .iter()
+            .filter(|seg| seg.flags & elf::PF_X != 0);
+        let start = code.clone().map(|seg| seg.vaddr).min()?;
+        let end = code.map(|seg| seg.vaddr + seg.memsz).max()?;
+        Some((self.to_runtime(start), self.to_runtime(end)))
+    }
+
+    pub fn symbol_address(&self, name: &str) -> Option<u64> {
+        self.object
+            .find_symbol(name)
+            .map(|sym| self.to_runtime(sym.address))
+    }
+
+    pub fn symbol_at(&self, address: u64) -> Option<(&Symbol, u64)> {
+        self.object.symbol_at(self.to_link(address))
+    }
+
+    pub fn line_addresses(&self, file: &str, line: u64) -> Vec<u64> {
+        self.object
+            .line_addresses(file, line)
+            .into_iter()
+            .map(|address| self.to_runtime(address))
+            .collect()
+    }
+
+    pub fn frame_rule(&self, address: u64) -> Option<FrameRule> {
+        self.object.frame_rule(self.to_link(address))
+    }
+
+    pub fn line_at(&self, address: u64) -> Option<SourceLine<'_>> {
+        let mut line = self.object.line_at(self.to_link(address))?;
+        line.address = self.to_runtime(line.address);
+        Some(line)
+    }
+}
+
+/// Load bias of the main executable, from where the kernel says it put the
+/// program headers (`AT_PHDR`) or the entry point (`AT_ENTRY`)
+pub fn executable_bias(object: &ObjectFile, auxv: &[(u64, u64)]) -> u64 {
+    if !object.is_position_independent {
+        return 0;
+    }
+    if let (Some(phdr), Some(at_phdr)) = (object.phdr_vaddr, auxv_value(auxv, AT_PHDR)) {
+        return at_phdr.wrapping_sub(phdr);
+    }
+    auxv_value(auxv, AT_ENTRY)
+        .map(|entry| entry.wrapping_sub(object.entry))
+        .unwrap_or(0)
+}
+
+const PAGE_MASK: u64 = !0xfff;
+
+/// Load bias of an object file, from the mapping of its first segment in `/proc/<pid>/maps`
+pub fn mapped_bias(object: &ObjectFile, maps: &[MemoryMap], path: &str) -> Option<u64> {
+    let first = object.segments.first()?;
+    if !object.is_position_independent {
+        return Some(0);
+    }
+    maps.iter()
+        .find(|map| map.pathname.as_deref() == Some(path) && map.offset == first.offset & PAGE_MASK)
+        .map(|map| map.start.wrapping_sub(first.vaddr & PAGE_MASK))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn object_with_symbols(symbols: Vec<Symbol>) -> ObjectFile {
+        ObjectFile {
+            symbols,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_symbol_lookup() {
+        let object = object_with_symbols(vec![
+            Symbol {
+                name: "main".to_string(),
+                address: 0x1130,
+                size: 0x20,
+                is_function: true,
+            },
+            Symbol {
+                name: "add".to_string(),
+                address: 0x1150,
+                size: 0x10,
+                is_function: true,
+            },
+        ]);
+
+        assert_eq!(
+            object.find_symbol("add").map(|sym| sym.address),
+            Some(0x1150)
+        );
+        assert_eq!(
+            object
+                .symbol_at(0x1138)
+                .map(|(sym, off)| (sym.name.as_str(), off)),
+            Some(("main", 8))
+        );
+        assert_eq!(object.symbol_at(0x1160), None);
+        assert_eq!(object.symbol_at(0x1000), None);
+    }
+
+    #[test]
+    fn test_line_lookup() {
+        let row = |address, line, end_sequence| LineRow {
+            address,
+            file: 0,
+            line,
+            is_stmt: true,
+            end_sequence,
+        };
+        let object = ObjectFile {
+            files: vec![PathBuf::from("/src/prog/main.c")],
+            lines: vec![
+                row(0x1130, 3, false),
+                row(0x1138, 5, false),
+                row(0x1140, 5, false),
+                row(0x1148, 8, false),
+                row(0x1150, 8, true),
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(object.line_addresses("main.c", 5), vec![0x1138]);
+        // no code on line 6, the next line with code is used
+        assert_eq!(object.line_addresses("prog/main.c", 6), vec![0x1148]);
+        assert_eq!(object.line_addresses("other.c", 5), Vec::<u64>::new());
+        assert_eq!(object.line_at(0x1144).map(|line| line.line), Some(5));
+        assert_eq!(object.line_at(0x1150), None);
+    }
+
+    #[test]
+    fn test_load_bias() {
+        let object = ObjectFile {
+            is_position_independent: true,
+            entry: 0x1060,
+            phdr_vaddr: Some(0x40),
+            segments: vec![LoadSegment {
+                vaddr: 0,
+                memsz: 0x2000,
+                offset: 0,
+                filesz: 0x2000,
+                flags: 5,
+            }],
+            ..Default::default()
+        };
+        let auxv = [(AT_PHDR, 0x555555554040), (AT_ENTRY, 0x555555555060)];
+        assert_eq!(executable_bias(&object, &auxv), 0x555555554000);
+        assert_eq!(executable_bias(&object, &auxv[1..]), 0x555555554000);
+
+        let maps = crate::procfs::parse_maps(
+            "7ffff7dc3000-7ffff7de9000 r--p 00000000 08:01 42 /usr/lib/libc.so.6\n",
+        );
+        let module = Module::new(
+            object.clone(),
+            mapped_bias(&object, &maps, "/usr/lib/libc.so.6").unwrap(),
+        );
+        assert_eq!(module.bias, 0x7ffff7dc3000);
+        assert!(module.contains(0x7ffff7dc4000));
+        assert!(!module.contains(0x7ffff7dc5000));
+    }
+}
@@ -0,0 +1,427 @@
+use std::{ffi::c_void, fs, io, os::unix::fs::MetadataExt, path::PathBuf, rc::Rc};
+
+use nix::{
+    libc::{self, iovec},
+    sys::{
+        ptrace::{self, Options},
+        signal::{self, Signal},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+
+use crate::{
+    arch,
+    breakpoint::PtraceOps,
+    launch::{self, LaunchConfig, LaunchError, Launched},
+    procfs::{self, MemoryMap},
+    register,
+};
+
+#[cfg(test)]
+use mockall::mock;
+
+/// What the debuggee is reached through: a live process traced with ptrace, a core file it
+/// left behind, or a mock in the tests
+pub trait Target {
+    /// The first `len` words of the general purpose register set (`NT_PRSTATUS`) of `tid`
+    fn get_regs(&self, tid: Pid, len: usize) -> nix::Result<Vec<u64>>;
+
+    /// Changes the general purpose register set of `tid`
+    fn set_regs(&self, tid: Pid, regs: &[u64]) -> nix::Result<()>;
+
+    /// Reads `len` bytes at `address`, stopping at the first one that can't be read
+    fn read_memory(&self, tid: Pid, address: u64, len: usize) -> Vec<u8>;
+
+    /// Writes `bytes` at `address`, failing if part of them couldn't be written
+    fn write_memory(&self, tid: Pid, address: u64, bytes: &[u8]) -> nix::Result<()>;
+
+    /// The memory mappings of the process
+    fn maps(&self, pid: Pid) -> io::Result<Vec<MemoryMap>>;
+
+    /// Resumes `tid`, delivering `signal`
+    fn resume(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+
+    /// Resumes `tid` until its next syscall entry or exit
+    fn resume_to_syscall(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+
+    /// Executes a single instruction of `tid`
+    fn step(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+
+    /// Starts the program of `config` traced, it stops on the SIGTRAP of its exec
+    fn spawn(&self, config: &LaunchConfig) -> Result<Launched, LaunchError>;
+
+    /// Waits for `tid`, or any thread with `None`, to report an event, like `waitpid`
+    fn wait(&self, tid: Option<Pid>, flags: Option<WaitPidFlag>) -> nix::Result<WaitStatus>;
+
+    /// Starts tracing `tid`, a thread of a running process, without stopping it
+    fn seize(&self, tid: Pid, options: Options) -> nix::Result<()>;
+
+    /// Changes what `tid` reports besides signals
+    fn set_options(&self, tid: Pid, options: Options) -> nix::Result<()>;
+
+    /// Stops tracing `tid`, which goes on delivering `signal`
+    fn detach(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+
+    /// Stops `tid`, a seized thread, without sending it a signal
+    fn interrupt(&self, tid: Pid) -> nix::Result<()>;
+
+    /// Sends `signal` to the process `pid`
+    fn kill(&self, pid: Pid, signal: Signal) -> nix::Result<()>;
+
+    /// Sends `signal` to the thread `tid` of the process `pid`
+    fn kill_thread(&self, pid: Pid, tid: Pid, signal: Signal) -> nix::Result<()>;
+
+    /// What comes with the ptrace event `tid` stopped on: the new thread or process of a
+    /// clone or fork, the exit status of an exit
+    fn event_message(&self, tid: Pid) -> nix::Result<i64>;
+
+    /// The signal `tid` stopped on
+    fn siginfo(&self, tid: Pid) -> nix::Result<libc::siginfo_t>;
+
+    /// Whether `tid`, stopped at a syscall, is entering it, `None` when the kernel can't tell
+    /// (before Linux 5.3)
+    fn syscall_entry(&self, tid: Pid) -> Option<bool>;
+
+    /// The raw contents of the register set `n_type` of `tid`, such as `NT_PRFPREG`
+    fn read_regset(&self, tid: Pid, n_type: u32) -> nix::Result<Vec<u8>>;
+
+    /// Changes the syscall `tid` is stopped at the entry of, -1 skipping it
+    fn set_syscall_number(&self, tid: Pid, number: u64) -> nix::Result<()>;
+
+    /// The threads of the process `pid`
+    fn tasks(&self, pid: Pid) -> io::Result<Vec<Pid>>;
+
+    /// The program the process `pid` runs
+    fn executable(&self, pid: Pid) -> io::Result<PathBuf>;
+
+    /// The auxiliary vector the kernel gave the process `pid`, up to `AT_NULL`
+    fn auxv(&self, pid: Pid) -> io::Result<Vec<(u64, u64)>>;
+
+    /// The name of the thread `tid` of the process `pid`, as in `/proc/<pid>/task/<tid>/comm`
+    fn thread_name(&self, pid: Pid, tid: Pid) -> io::Result<String>;
+
+    /// The parent, process group and session of the process `pid`
+    fn stat_ids(&self, pid: Pid) -> io::Result<[i32; 3]>;
+
+    /// The user and group the process `pid` runs as
+    fn owner(&self, pid: Pid) -> io::Result<(u32, u32)>;
+
+    /// The command line of the process `pid`, its arguments separated by spaces
+    fn command_line(&self, pid: Pid) -> io::Result<String>;
+
+    /// The working directory of the process `pid`
+    fn cwd(&self, pid: Pid) -> io::Result<PathBuf>;
+
+    /// The contents of `map` at once, as much as can be read of it
+    fn read_mapping(&self, pid: Pid, map: &MemoryMap) -> io::Result<Vec<u8>>;
+}
+
+/// A traced process, reached through ptrace and `/proc`
+#[derive(Clone)]
+pub struct LiveTarget;
+
+/// `PTRACE_GETREGSET` or `PTRACE_SETREGSET` of the register set `n_type` of `tid`, through
+/// `iov`. A get shortens `iov` to the length the kernel filled.
+fn ptrace_regset(request: u32, tid: Pid, n_type: u32, iov: &mut iovec) -> nix::Result<()> {
+    let res = unsafe {
+        libc::ptrace(
+            request,
+            tid.as_raw(),
+            n_type as usize as *mut c_void,
+            iov as *mut iovec as *mut c_void,
+        )
+    };
+    nix::Error::result(res).map(drop)
+}
+
+impl Target for LiveTarget {
+    fn get_regs(&self, tid: Pid, len: usize) -> nix::Result<Vec<u64>> {
+        let mut regs = vec![0u64; len];
+        let mut iov = iovec {
+            iov_base: regs.as_mut_ptr() as *mut c_void,
+            iov_len: len * size_of::<u64>(),
+        };
+        ptrace_regset(
+            libc::PTRACE_GETREGSET,
+            tid,
+            libc::NT_PRSTATUS as u32,
+            &mut iov,
+        )?;
+        Ok(regs)
+    }
+
+    fn set_regs(&self, tid: Pid, regs: &[u64]) -> nix::Result<()> {
+        let mut iov = iovec {
+            iov_base: regs.as_ptr() as *mut c_void,
+            iov_len: std::mem::size_of_val(regs),
+        };
+        ptrace_regset(
+            libc::PTRACE_SETREGSET,
+            tid,
+            libc::NT_PRSTATUS as u32,
+            &mut iov,
+        )
+    }
+
+    fn read_memory(&self, tid: Pid, address: u64, len: usize) -> Vec<u8> {
+        // ptrace reads a word at a time
+        let mut bytes = Vec::with_capacity(len);
+        let mut word_address = address;
+        while bytes.len() < len {
+            let Ok(word) = ptrace::read(tid, word_address as *mut c_void) else {
+                break;
+            };
+            bytes.extend_from_slice(&word.to_ne_bytes());
+            word_address += 8;
+        }
+        bytes.truncate(len);
+        bytes
+    }
+
+    fn write_memory(&self, tid: Pid, address: u64, bytes: &[u8]) -> nix::Result<()> {
+        for (i, chunk) in bytes.chunks(8).enumerate() {
+            let word_address = (address + (i * 8) as u64) as *mut c_void;
+            // the bytes of a partial word that are not written are kept
+            let mut word = ptrace::read(tid, word_address)?.to_ne_bytes();
+            word[..chunk.len()].copy_from_slice(chunk);
+            ptrace::write(tid, word_address, i64::from_ne_bytes(word))?;
+        }
+        Ok(())
+    }
+
+    fn maps(&self, pid: Pid) -> io::Result<Vec<MemoryMap>> {
+        procfs::read_maps(pid)
+    }
+
+    fn resume(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()> {
+        ptrace::cont(tid, signal)
+    }
+
+    fn resume_to_syscall(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()> {
+        ptrace::syscall(tid, signal)
+    }
+
+    fn step(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()> {
+        ptrace::step(tid, signal)
+    }
+
+    fn spawn(&self, config: &LaunchConfig) -> Result<Launched, LaunchError> {
+        launch::spawn(config)
+    }
+
+    fn wait(&self, tid: Option<Pid>, flags: Option<WaitPidFlag>) -> nix::Result<WaitStatus> {
+        waitpid(tid, flags)
+    }
+
+    fn seize(&self, tid: Pid, options: Options) -> nix::Result<()> {
+        ptrace::seize(tid, options)
+    }
+
+    fn set_options(&self, tid: Pid, options: Options) -> nix::Result<()> {
+        ptrace::setoptions(tid, options)
+    }
+
+    fn detach(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()> {
+        ptrace::detach(tid, signal)
+    }
+
+    fn interrupt(&self, tid: Pid) -> nix::Result<()> {
+        ptrace::interrupt(tid)
+    }
+
+    fn kill(&self, pid: Pid, signal: Signal) -> nix::Result<()> {
+        signal::kill(pid, signal)
+    }
+
+    fn kill_thread(&self, pid: Pid, tid: Pid, signal: Signal) -> nix::Result<()> {
+        let res = unsafe {
+            libc::syscall(
+                libc::SYS_tgkill,
+                pid.as_raw(),
+                tid.as_raw(),
+                signal as libc::c_int,
+            )
+        };
+        nix::Error::result(res).map(drop)
+    }
+
+    fn event_message(&self, tid: Pid) -> nix::Result<i64> {
+        ptrace::getevent(tid)
+    }
+
+    fn siginfo(&self, tid: Pid) -> nix::Result<libc::siginfo_t> {
+        ptrace::getsiginfo(tid)
+    }
+
+    fn syscall_entry(&self, tid: Pid) -> Option<bool> {
+        // struct ptrace_syscall_info starts with the kind of stop
+        let mut info = [0u64; 11];
+        let size = unsafe {
+            libc::ptrace(
+                libc::PTRACE_GET_SYSCALL_INFO,
+                tid.as_raw(),
+                std::mem::size_of_val(&info),
+                info.as_mut_ptr(),
+            )
+        };
+        (size > 0).then(|| info[0] as u8 == libc::PTRACE_SYSCALL_INFO_ENTRY)
+    }
+
+    fn read_regset(&self, tid: Pid, n_type: u32) -> nix::Result<Vec<u8>> {
+        // larger than the biggest XSAVE area, the kernel gives the length it filled
+        let mut buffer = vec![0u8; 16 * 1024];
+        let mut iov = iovec {
+            iov_base: buffer.as_mut_ptr() as *mut c_void,
+            iov_len: buffer.len(),
+        };
+        ptrace_regset(libc::PTRACE_GETREGSET, tid, n_type, &mut iov)?;
+        buffer.truncate(iov.iov_len);
+        Ok(buffer)
+    }
+
+    fn set_syscall_number(&self, tid: Pid, number: u64) -> nix::Result<()> {
+        let arch = arch::native();
+        let Some(regset) = arch.syscall_number_regset() else {
+            return register::write_register(self, arch, tid, arch.syscall_number_reg(), number);
+        };
+        let mut number = number as i32;
+        let mut iov = iovec {
+            iov_base: &mut number as *mut i32 as *mut c_void,
+            iov_len: size_of::<i32>(),
+        };
+        ptrace_regset(libc::PTRACE_SETREGSET, tid, regset, &mut iov)
+    }
+
+    fn tasks(&self, pid: Pid) -> io::Result<Vec<Pid>> {
+        procfs::read_tasks(pid)
+    }
+
+    fn executable(&self, pid: Pid) -> io::Result<PathBuf> {
+        fs::read_link(format!("/proc/{pid}/exe"))
+    }
+
+    fn auxv(&self, pid: Pid) -> io::Result<Vec<(u64, u64)>> {
+        procfs::read_auxv(pid)
+    }
+
+    fn thread_name(&self, pid: Pid, tid: Pid) -> io::Result<String> {
+        let comm = fs::read_to_string(format!("/proc/{pid}/task/{tid}/comm"))?;
+        Ok(comm.trim_end().to_string())
+    }
+
+    fn stat_ids(&self, pid: Pid) -> io::Result<[i32; 3]> {
+        procfs::read_stat_ids(pid)
+    }
+
+    fn owner(&self, pid: Pid) -> io::Result<(u32, u32)> {
+        let metadata = fs::metadata(format!("/proc/{pid}"))?;
+        Ok((metadata.uid(), metadata.gid()))
+    }
+
+    fn command_line(&self, pid: Pid) -> io::Result<String> {
+        procfs::read_cmdline(pid)
+    }
+
+    fn cwd(&self, pid: Pid) -> io::Result<PathBuf> {
+        fs::read_link(format!("/proc/{pid}/cwd"))
+    }
+
+    fn read_mapping(&self, pid: Pid, map: &MemoryMap) -> io::Result<Vec<u8>> {
+        // much faster than ptrace for a whole mapping
+        procfs::read_mem(pid, map.start, (map.end - map.start) as usize)
+    }
+}
+
+/// Breakpoints insert their trap through the target the debugger uses
+impl PtraceOps for Rc<dyn Target> {
+    fn read(&self, pid: Pid, addr: *mut c_void) -> Result<i64, nix::Error> {
+        read_word(self.as_ref(), pid, addr as u64)
+            .map(|word| word as i64)
+            .ok_or(nix::Error::EIO)
+    }
+
+    fn write(&self, pid: Pid, addr: *mut c_void, data: i64) -> Result<(), nix::Error> {
+        self.write_memory(pid, addr as u64, &data.to_ne_bytes())
+    }
+}
+
+#[cfg(test)]
+mock! {
+    pub Target {}
+
+    impl Target for Target {
+        fn get_regs(&self, tid: Pid, len: usize) -> nix::Result<Vec<u64>>;
+        fn set_regs(&self, tid: Pid, regs: &[u64]) -> nix::Result<()>;
+        fn read_memory(&self, tid: Pid, address: u64, len: usize) -> Vec<u8>;
+        fn write_memory(&self, tid: Pid, address: u64, bytes: &[u8]) -> nix::Result<()>;
+        fn maps(&self, pid: Pid) -> io::Result<Vec<MemoryMap>>;
+        fn resume(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+        fn resume_to_syscall(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+        fn step(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+        fn spawn(&self, config: &LaunchConfig) -> Result<Launched, LaunchError>;
+        fn wait(&self, tid: Option<Pid>, flags: Option<WaitPidFlag>) -> nix::Result<WaitStatus>;
+        fn seize(&self, tid: Pid, options: Options) -> nix::Result<()>;
+        fn set_options(&self, tid: Pid, options: Options) -> nix::Result<()>;
+        fn detach(&self, tid: Pid, signal: Option<Signal>) -> nix::Result<()>;
+        fn interrupt(&self, tid: Pid) -> nix::Result<()>;
+        fn kill(&self, pid: Pid, signal: Signal) -> nix::Result<()>;
+        fn kill_thread(&self, pid: Pid, tid: Pid, signal: Signal) -> nix::Result<()>;
+        fn event_message(&self, tid: Pid) -> nix::Result<i64>;
+        fn siginfo(&self, tid: Pid) -> nix::Result<libc::siginfo_t>;
+        fn syscall_entry(&self, tid: Pid) -> Option<bool>;
+        fn read_regset(&self, tid: Pid, n_type: u32) -> nix::Result<Vec<u8>>;
+        fn set_syscall_number(&self, tid: Pid, number: u64) -> nix::Result<()>;
+        fn tasks(&self, pid: Pid) -> io::Result<Vec<Pid>>;
+        fn executable(&self, pid: Pid) -> io::Result<PathBuf>;
+        fn auxv(&self, pid: Pid) -> io::Result<Vec<(u64, u64)>>;
+        fn thread_name(&self, pid: Pid, tid: Pid) -> io::Result<String>;
+        fn stat_ids(&self, pid: Pid) -> io::Result<[i32; 3]>;
+        fn owner(&self, pid: Pid) -> io::Result<(u32, u32)>;
+        fn command_line(&self, pid: Pid) -> io::Result<String>;
+        fn cwd(&self, pid: Pid) -> io::Result<PathBuf>;
+        fn read_mapping(&self, pid: Pid, map: &MemoryMap) -> io::Result<Vec<u8>>;
+    }
+}
+
+/// Reads a word of the target, `None` when part of it can't be read
+pub fn read_word(target: &dyn Target, tid: Pid, address: u64) -> Option<u64> {
+    let bytes = target.read_memory(tid, address, 8);
+    Some(u64::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_breakpoint_ops() {
+        let pid = Pid::from_raw(1234);
+        let mut target = MockTarget::new();
+        target
+            .expect_read_memory()
+            .withf(|_, &address, &len| address == 0x1000 && len == 8)
+            .return_const(0x1122334455667788u64.to_ne_bytes().to_vec());
+        target
+            .expect_read_memory()
+            .withf(|_, &address, _| address == 0x2000)
+            .return_const(vec![0x88, 0x77]);
+        target
+            .expect_write_memory()
+            .withf(|_, &address, bytes| address == 0x1000 && bytes == 0xccu64.to_ne_bytes())
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let target: Rc<dyn Target> = Rc::new(target);
+        assert_eq!(
+            target.read(pid, 0x1000 as *mut c_void),
+            Ok(0x1122334455667788)
+        );
+        // a word only partly mapped can't be read
+        assert_eq!(
+            target.read(pid, 0x2000 as *mut c_void),
+            Err(nix::Error::EIO)
+        );
+        assert_eq!(target.write(pid, 0x1000 as *mut c_void, 0xcc), Ok(()));
+    }
+}
@@ -0,0 +1,128 @@
+use nix::{sys::signal::Signal, unistd::Pid};
+
+use crate::strace::TracedCall;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThreadState {
+    Running,
+    Stopped,
+}
+
+/// A traced thread of the debuggee
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thread {
+    /// Number shown to the user, given in creation order and never reused during a run
+    pub id: usize,
+    pub tid: Pid,
+    pub state: ThreadState,
+    /// A `SIGSTOP` is on its way to the thread, either sent by the debugger or the one
+    /// new threads start with. It is swallowed when reported.
+    pub stop_expected: bool,
+    /// Signal the thread received, delivered when it is resumed
+    pub pending_signal: Option<Signal>,
+    /// Stopped at the entry to a syscall, its next syscall stop is the exit
+    pub in_syscall: bool,
+    /// The syscall the thread is in while syscalls are traced, shown when it returns
+    pub traced_call: Option<TracedCall>,
+    /// Stopped by a syscall catchpoint, its syscall can be changed
+    pub at_syscall_stop: bool,
+    /// Number of the syscall skipped on entry and the value it returns instead, kept until
+    /// the thread enters its next syscall
+    pub skipped_syscall: Option<(u64, u64)>,
+    /// Reported hitting a trap of the debugger and not resumed since: its program counter may
+    /// be past the trap, and the instruction the trap replaced is still to be executed
+    pub hit_trap: bool,
+}
+
+/// The threads of the debuggee, in creation order
+#[derive(Debug, Default)]
+pub struct ThreadList {
+    threads: Vec<Thread>,
+    next_id: usize,
+}
+
+impl ThreadList {
+    pub fn add(&mut self, tid: Pid, state: ThreadState) -> &mut Thread {
+        self.next_id += 1;
+        self.threads.push(Thread {
+            id: self.next_id,
+            tid,
+            state,
+            stop_expected: false,
+            pending_signal: None,
+            in_syscall: false,
+            traced_call: None,
+            at_syscall_stop: false,
+            skipped_syscall: None,
+            hit_trap: false,
+        });
+        self.threads.last_mut().unwrap()
+    }
+
+    pub fn remove(&mut self, tid: Pid) -> Option<Thread> {
+        let index = self.threads.iter().position(|thread| thread.tid == tid)?;
+        Some(self.threads.remove(index))
+    }
+
+    /// Forgets every thread, numbering starts again from 1
+    pub fn clear(&mut self) {
+        self.threads.clear();
+        self.next_id = 0;
+    }
+
+    pub fn get(&self, tid: Pid) -> Option<&Thread> {
+        self.threads.iter().find(|thread| thread.tid == tid)
+    }
+
+    pub fn get_mut(&mut self, tid: Pid) -> Option<&mut Thread> {
+        self.threads.iter_mut().find(|thread| thread.tid == tid)
+    }
+
+    pub fn by_id(&self, id: usize) -> Option<&Thread> {
+        self.threads.iter().find(|thread| thread.id == id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Thread> {
+        self.threads.iter()
+    }
+
+    pub fn tids(&self) -> Vec<Pid> {
+        self.threads.iter().map(|thread| thread.tid).collect()
+    }
+
+    /// Threads the debugger resumed and that haven't reported a stop since
+    pub fn running(&self) -> Vec<Pid> {
+        self.threads
+            .iter()
+            .filter(|thread| thread.state == ThreadState::Running)
+            .map(|thread| thread.tid)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_thread_numbering() {
+        let mut threads = ThreadList::default();
+        threads.add(Pid::from_raw(100), ThreadState::Stopped);
+        threads.add(Pid::from_raw(101), ThreadState::Running);
+        threads.add(Pid::from_raw(102), ThreadState::Running);
+
+        assert_eq!(threads.remove(Pid::from_raw(101)).map(|t| t.id), Some(2));
+        // numbers of exited threads aren't given again
+        assert_eq!(threads.add(Pid::from_raw(103), ThreadState::Running).id, 4);
+        assert_eq!(threads.by_id(3).map(|t| t.tid), Some(Pid::from_raw(102)));
+        assert_eq!(threads.by_id(2), None);
+        assert_eq!(
+            threads.running(),
+            vec![Pid::from_raw(102), Pid::from_raw(103)]
+        );
+
+        threads.clear();
+        assert!(threads.tids().is_empty());
+        assert_eq!(threads.add(Pid::from_raw(200), ThreadState::Stopped).id, 1);
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use crate::arch::Arch;
+
+/// Most frames a backtrace shows, a corrupted stack could otherwise loop forever
+pub const MAX_FRAMES: usize = 256;
+
+/// How the caller's registers are found at an address, from the call frame information
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameRule {
+    /// The canonical frame address (CFA) is this DWARF register plus `cfa_offset`
+    pub cfa_register: u16,
+    pub cfa_offset: i64,
+    /// DWARF registers saved by the function and where, as offsets from the CFA
+    pub saved: Vec<(u16, i64)>,
+    /// DWARF register holding the return address
+    pub return_address_register: u16,
+}
+
+/// Walks the stack from the registers of the innermost frame (laid out like `NT_PRSTATUS`),
+/// giving the program counter of every frame. `rule_at` tells how to unwind a frame from
+/// an address; without a rule, the frame pointer is followed. `read_word` reads the stack.
+pub fn backtrace(
+    arch: &dyn Arch,
+    regset: &[u64],
+    rule_at: impl Fn(u64) -> Option<FrameRule>,
+    read_word: impl Fn(u64) -> Option<u64>,
+) -> Vec<u64> {
+    let dwarf_number = |r| {
+        arch.registers()
+            .iter()
+            .find(|desc| desc.r == r && desc.dwarf_r >= 0)
+            .map(|desc| desc.dwarf_r as u16)
+    };
+    let (Some(sp_reg), Some(fp_reg)) = (dwarf_number(arch.sp_reg()), dwarf_number(arch.fp_reg()))
+    else {
+        return vec![];
+    };
+    let index_of = |r| {
+        arch.registers()
+            .iter()
+            .find(|desc| desc.r == r)
+            .map(|desc| desc.index)
+    };
+    let Some(mut pc) = index_of(arch.pc_reg()).and_then(|index| regset.get(index).copied()) else {
+        return vec![];
+    };
+    let mut regs: HashMap<u16, u64> = arch
+        .registers()
+        .iter()
+        .filter(|desc| desc.dwarf_r >= 0)
+        .filter_map(|desc| Some((desc.dwarf_r as u16, *regset.get(desc.index)?)))
+        .collect();
+
+    let mut frames = vec![pc];
+    while frames.len() < MAX_FRAMES {
+        // a return address follows the call, which may be the last instruction of its function
+        let lookup = if frames.len() == 1 { pc } else { pc - 1 };
+        let (cfa, caller_pc) = match rule_at(lookup) {
+            Some(rule) => {
+                let Some(base) = regs.get(&rule.cfa_register) else {
+                    break;
+                };
+                let cfa = base.wrapping_add_signed(rule.cfa_offset);
+                let mut caller = regs.clone();
+                for &(reg, offset) in &rule.saved {
+                    match read_word(cfa.wrapping_add_signed(offset)) {
+                        Some(value) => caller.insert(reg, value),
+                        None => caller.remove(&reg),
+                    };
+                }
+                // a leaf function may leave the return address in its register (aarch64 lr)
+                let caller_pc = caller.remove(&rule.return_address_register);
+                regs = caller;
+                (cfa, caller_pc)
+            }
+            None => {
+                // the frame pointer points at the saved frame pointer, the return address follows
+                let Some(&fp) = regs.get(&fp_reg) else {
+                    break;
+                };
+                let caller_pc = read_word(fp.wrapping_add(8));
+                match read_word(fp) {
+                    Some(saved_fp) => regs.insert(fp_reg, saved_fp),
+                    None => regs.remove(&fp_reg),
+                };
+                (fp.wrapping_add(16), caller_pc)
+            }
+        };
+        let Some(caller_pc) = caller_pc.filter(|&caller_pc| caller_pc != 0) else {
+            break;
+        };
+        // the stack grows down, a caller's frame is above, or at the same place for a leaf
+        // function that doesn't use the stack
+        let sp = regs.get(&sp_reg).copied().unwrap_or(0);
+        if cfa < sp || (cfa == sp && caller_pc == pc) {
+            break;
+        }
+        regs.insert(sp_reg, cfa);
+        pc = caller_pc;
+        frames.push(pc);
+    }
+    frames
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{arch::X86_64, register::Reg};
+
+    fn x86_64_regset(values: &[(Reg, u64)]) -> Vec<u64> {
+        let mut regset = vec![0; X86_64.regset_len()];
+        for &(r, value) in values {
+            let desc = X86_64.registers().iter().find(|desc| desc.r == r).unwrap();
+            regset[desc.index] = value;
+        }
+        regset
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let regset = x86_64_regset(&[(Reg::Rip, 0x1010), (Reg::Rsp, 0x7000), (Reg::Rbp, 0x7100)]);
+        let stack = HashMap::from([
+            // pushed by the function at 0x1010, which has call frame information
+            (0x7000, 0x7100),
+            (0x7008, 0x2020),
+            // frames linked by their frame pointer
+            (0x7100, 0x7200),
+            (0x7108, 0x3030),
+            (0x7200, 0),
+            (0x7208, 0),
+        ]);
+        let rule_at = |address| {
+            (0x1000..0x1100).contains(&address).then(|| FrameRule {
+                cfa_register: 7,
+                cfa_offset: 16,
+                saved: vec![(6, -16), (16, -8)],
+                return_address_register: 16,
+            })
+        };
+        let frames = backtrace(&X86_64, &regset, rule_at, |address| {
+            stack.get(&address).copied()
+        });
+        assert_eq!(frames, vec![0x1010, 0x2020, 0x3030]);
+
+        // a frame pointer pointing at itself doesn't loop
+        let stack = HashMap::from([(0x7100, 0x7100), (0x7108, 0x2020)]);
+        let frames = backtrace(
+            &X86_64,
+            &regset,
+            |_| None,
+            |address| stack.get(&address).copied(),
+        );
+        assert_eq!(frames, vec![0x1010, 0x2020]);
+    }
+}
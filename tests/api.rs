@@ -0,0 +1,56 @@
+use std::{path::PathBuf, process::Command};
+
+use nix::sys::signal::Signal;
+use vdebugger::{Debugger, DebuggerError, LaunchConfig, Location, Reg, StopReason, TerminalMode};
+
+#[cfg(target_arch = "x86_64")]
+const PC: Reg = Reg::Rip;
+#[cfg(target_arch = "aarch64")]
+const PC: Reg = Reg::Pc;
+
+/// Compiles `tests/fixtures/<name>.c` with its debug information
+fn build_fixture(name: &str) -> PathBuf {
+    let source = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{name}.c"));
+    let program = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let status = Command::new("cc")
+        .args(["-g", "-O0", "-o"])
+        .arg(&program)
+        .arg(&source)
+        .status()
+        .expect("cannot run cc");
+    assert!(status.success(), "cannot compile {}", source.display());
+    program
+}
+
+#[test]
+fn test_run_to_breakpoints() {
+    let program = build_fixture("counter");
+    let mut config = LaunchConfig::new(program.to_string_lossy().into_owned(), vec![]);
+    config.terminal = TerminalMode::Inherit;
+    let mut debugger = Debugger::new(config);
+
+    debugger
+        .set_breakpoint("work".parse().unwrap())
+        .expect("work is defined");
+    debugger.start_debuggee().unwrap();
+    let counter: Location = "counter".parse().unwrap();
+    let counter = debugger.resolve_location(&counter)[0];
+
+    // work adds 1, 2 then 3
+    for expected in [0, 1, 3] {
+        let (_, reason) = debugger.last_stop().unwrap();
+        assert_eq!(*reason, StopReason::Signal(Signal::SIGTRAP));
+        let pc = debugger.read_register(PC).unwrap();
+        assert_eq!(debugger.breakpoints(), vec![pc]);
+        let value = debugger.read_memory(counter, 4).unwrap();
+        assert_eq!(i32::from_ne_bytes(value.try_into().unwrap()), expected);
+        debugger.resume().unwrap();
+    }
+
+    // the program ran to its end
+    assert_eq!(debugger.last_stop(), None);
+    assert_eq!(debugger.pid(), None);
+    assert_eq!(debugger.read_register(PC), Err(DebuggerError::NoThread));
+}
@@ -0,0 +1,16 @@
+#include <stdio.h>
+
+int counter;
+
+void work(int i)
+{
+    counter += i;
+}
+
+int main(void)
+{
+    for (int i = 1; i <= 3; i++)
+        work(i);
+    printf("%d\n", counter);
+    return 0;
+}